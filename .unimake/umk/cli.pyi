@@ -1,7 +1,19 @@
 def cmd(
     name: str,
     *,
-    help: str = ...
+    help: str = ...,
+    inputs: list[str] | None = None,
+    outputs: list[str] | None = None,
+    deps: list[str] | None = None,
+    finally_: list[str] | None = None,
+    aliases: list[str] | None = None,
+    hidden: bool = False,
+    category: str | None = None,
+    deprecated: str | None = None,
+    default: bool = False,
+    timeout: float | None = None,
+    retries: int = 0,
+    retry_delay: float = 0.0,
 ): ...
 
 
@@ -24,3 +36,18 @@ def arg(
     var: str | None = None,
     help: str = ...,
 ): ...
+
+
+def rule(
+    pattern: str,
+    *,
+    inputs: list[str] | None = None,
+    deps: list[str] | None = None,
+    help: str = ...,
+): ...
+
+
+def before(command: str): ...
+
+
+def after(command: str): ...
@@ -0,0 +1,18 @@
+import typing as t
+
+T = t.TypeVar("T")
+
+
+def setting(
+    klass: type[T],
+    name: str,
+    *,
+    default: T | None = None,
+    help: str = ...,
+) -> T: ...
+
+
+def get(name: str) -> t.Any: ...
+
+
+def profile() -> str | None: ...
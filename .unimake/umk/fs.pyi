@@ -0,0 +1,38 @@
+import os
+import typing as t
+
+PathLike: t.TypeAlias = str | os.PathLike[str]
+Patterns: t.TypeAlias = PathLike | list[PathLike]
+
+
+def glob(patterns: Patterns) -> list[str]: ...
+
+
+def newer(outputs: Patterns, inputs: Patterns) -> bool: ...
+
+
+def copy(src: PathLike, dst: PathLike) -> str: ...
+
+
+def copytree(src: PathLike, dst: PathLike) -> int: ...
+
+
+def move(src: PathLike, dst: PathLike, force: bool = False) -> str: ...
+
+
+def remove(path: PathLike, force: bool = False) -> bool: ...
+
+
+def removetree(path: PathLike, force: bool = False) -> bool: ...
+
+
+def mkdir(path: PathLike) -> str: ...
+
+
+def touch(path: PathLike) -> str: ...
+
+
+def read_text(path: PathLike) -> str: ...
+
+
+def write_text(path: PathLike, text: str) -> str: ...
@@ -0,0 +1,22 @@
+import typing as t
+
+F = t.TypeVar("F", bound=t.Callable[[], t.Any])
+
+
+def run(
+    args: str | list[str],
+    cwd: str | None = None,
+    env: dict[str, str] | None = None,
+    check: bool = True,
+) -> int: ...
+
+
+def output(
+    args: str | list[str],
+    cwd: str | None = None,
+    env: dict[str, str] | None = None,
+    check: bool = True,
+) -> str: ...
+
+
+def cleanup(function: F) -> F: ...
@@ -30,14 +30,10 @@ class Info:
     ): ...
 
 
-class Project:
-    info: Info = ...
+def get() -> Info: ...
 
 
-def get() -> Project: ...
-
-
-Initializer = t.Callable[..., t.Any] | t.Callable[[Project], t.Any]
+Initializer = t.Callable[[Info], t.Any] | type[Info]
 
 
 def init(func: Initializer): ...
@@ -0,0 +1,13 @@
+import os
+import typing as t
+
+
+def render(text: str, /, **variables: t.Any) -> str: ...
+
+
+def render_file(
+    src: str | os.PathLike[str],
+    dst: str | os.PathLike[str] | None = None,
+    /,
+    **variables: t.Any,
+) -> bool: ...
@@ -0,0 +1,211 @@
+use sdk::{ArtifactManifest, ArtifactSpace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Unreferenced objects younger than this are kept by the collection,
+/// their manifest may be still being written by another process.
+const GRACE: Duration = Duration::from_secs(600);
+
+/// Stores each blob in its own file, objects are spread over 256
+/// subdirectories by the first byte of the hash. Reading the manifest
+/// touches its modification time, so the collection removes the least
+/// recently used targets first.
+pub struct ArtifactsDriver {
+    root: PathBuf,
+    limit: u64,
+}
+
+/// What the collection has removed.
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub targets: usize,
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+/// What the store keeps.
+#[derive(Debug, Default)]
+pub struct Usage {
+    pub targets: usize,
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+struct Object {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl ArtifactsDriver {
+    pub fn new(root: PathBuf, limit: u64) -> Self {
+        Self { root, limit }
+    }
+
+    fn path(&self, space: ArtifactSpace, key: &str) -> PathBuf {
+        let directory = self.root.join(space.name());
+        match space {
+            ArtifactSpace::Objects => directory.join(&key[..2.min(key.len())]).join(key),
+            ArtifactSpace::Targets => directory.join(key),
+        }
+    }
+
+    pub fn usage(&self) -> sdk::Result<Usage> {
+        let objects = self.objects()?;
+        Ok(Usage {
+            targets: self.targets()?.len(),
+            objects: objects.len(),
+            bytes: objects.values().map(|o| o.size).sum(),
+        })
+    }
+
+    /// Removes the least recently used targets until the objects fit
+    /// into the limit, and the objects no target refers to.
+    pub fn collect(&self, limit: u64) -> sdk::Result<Collected> {
+        let mut result = Collected::default();
+        let mut objects = self.objects()?;
+        let mut targets = Vec::new();
+        let mut references: HashMap<String, usize> = HashMap::new();
+        for (path, modified) in self.targets()? {
+            let manifest = std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<ArtifactManifest>(&data).ok());
+            let Some(manifest) = manifest else {
+                remove(&path)?;
+                result.targets += 1;
+                continue;
+            };
+            for output in manifest.outputs.iter() {
+                *references.entry(output.hash.clone()).or_default() += 1;
+            }
+            targets.push((path, modified, manifest));
+        }
+
+        let now = SystemTime::now();
+        let orphans: Vec<String> = objects
+            .iter()
+            .filter(|(hash, object)| {
+                !references.contains_key(*hash)
+                    && now.duration_since(object.modified).unwrap_or_default() > GRACE
+            })
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        for hash in orphans {
+            let object = objects.remove(&hash).unwrap();
+            remove(&object.path)?;
+            result.objects += 1;
+            result.bytes += object.size;
+        }
+
+        let mut total: u64 = objects.values().map(|o| o.size).sum();
+        targets.sort_by_key(|(_, modified, _)| *modified);
+        for (path, _, manifest) in targets {
+            if total <= limit {
+                break;
+            }
+            remove(&path)?;
+            result.targets += 1;
+            for output in manifest.outputs.iter() {
+                let Some(count) = references.get_mut(&output.hash) else {
+                    continue;
+                };
+                *count -= 1;
+                if *count > 0 {
+                    continue;
+                }
+                references.remove(&output.hash);
+                if let Some(object) = objects.remove(&output.hash) {
+                    remove(&object.path)?;
+                    total -= object.size;
+                    result.objects += 1;
+                    result.bytes += object.size;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn targets(&self) -> sdk::Result<Vec<(PathBuf, SystemTime)>> {
+        let mut result = Vec::new();
+        for path in files(&self.root.join(ArtifactSpace::Targets.name()))? {
+            // Another process may be collecting concurrently.
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            result.push((path, metadata.modified()?));
+        }
+        Ok(result)
+    }
+
+    fn objects(&self) -> sdk::Result<HashMap<String, Object>> {
+        let mut result = HashMap::new();
+        for directory in files(&self.root.join(ArtifactSpace::Objects.name()))? {
+            for path in files(&directory)? {
+                let Ok(metadata) = std::fs::metadata(&path) else {
+                    continue;
+                };
+                let hash = path.file_name().unwrap().to_string_lossy().to_string();
+                let object = Object {
+                    path,
+                    size: metadata.len(),
+                    modified: metadata.modified()?,
+                };
+                result.insert(hash, object);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl sdk::ArtifactDriver for ArtifactsDriver {
+    fn get(&self, space: ArtifactSpace, key: &str) -> sdk::Result<Option<Vec<u8>>> {
+        let path = self.path(space, key);
+        match std::fs::read(&path) {
+            Ok(data) => {
+                if space == ArtifactSpace::Targets
+                    && let Ok(file) = std::fs::File::options().write(true).open(&path)
+                {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Ok(Some(data))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, space: ArtifactSpace, key: &str, data: &[u8]) -> sdk::Result {
+        sdk::write_atomic(&self.path(space, key), data)?;
+        if space == ArtifactSpace::Targets && self.usage()?.bytes > self.limit {
+            self.collect(self.limit)?;
+        }
+        Ok(())
+    }
+
+    fn has(&self, space: ArtifactSpace, key: &str) -> sdk::Result<bool> {
+        Ok(self.path(space, key).is_file())
+    }
+}
+
+/// Entries of the directory except hidden ones (unfinished writes).
+fn files(directory: &Path) -> sdk::Result<Vec<PathBuf>> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut result = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with('.') {
+            result.push(entry.path());
+        }
+    }
+    Ok(result)
+}
+
+fn remove(path: &Path) -> sdk::Result {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
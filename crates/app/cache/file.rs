@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+type JsonValue = serde_json::Value;
+type Entries = BTreeMap<String, JsonValue>;
+
+/// Stores all entries in the single JSON file. The file is read on each
+/// access (other processes may change it) and rewritten atomically on each
+/// change, so readers never see a partial file. Unreadable file is treated
+/// as empty.
+pub struct FileDriver {
+    file: PathBuf,
+}
+
+impl FileDriver {
+    pub fn new(file: PathBuf) -> Self {
+        Self { file }
+    }
+
+    fn read(&self) -> sdk::Result<Entries> {
+        match std::fs::read_to_string(&self.file) {
+            Ok(data) => Ok(serde_json::from_str(&data).unwrap_or_else(|e| {
+                let file = self.file.display();
+                sdk::warning(format!("Ignoring unreadable cache file '{}': {}", file, e));
+                Entries::new()
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Entries::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, entries: &Entries) -> sdk::Result {
+        let data = serde_json::to_string_pretty(entries)?;
+        sdk::write_atomic(&self.file, data.as_bytes())
+    }
+}
+
+impl sdk::CacheDriver for FileDriver {
+    fn get(&self, key: &str) -> sdk::Result<Option<JsonValue>> {
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&mut self, key: &str, value: JsonValue) -> sdk::Result {
+        let mut entries = self.read()?;
+        entries.insert(key.to_string(), value);
+        self.write(&entries)
+    }
+
+    fn del(&mut self, key: &str) -> sdk::Result {
+        let mut entries = self.read()?;
+        if entries.remove(key).is_some() {
+            self.write(&entries)?;
+        }
+        Ok(())
+    }
+
+    fn keys(&self) -> sdk::Result<Vec<String>> {
+        Ok(self.read()?.into_keys().collect())
+    }
+}
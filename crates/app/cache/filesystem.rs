@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+type JsonValue = serde_json::Value;
+
+/// Stores each entry in its own pretty-printed JSON file.
+pub struct FilesystemDriver {
+    root: PathBuf,
+}
+
+impl FilesystemDriver {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+impl sdk::CacheDriver for FilesystemDriver {
+    fn get(&self, key: &str) -> sdk::Result<Option<JsonValue>> {
+        let file = self.root.join(key);
+        if !file.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read_to_string(file)?;
+        let value = JsonValue::from_str(&data)?;
+        Ok(Some(value))
+    }
+
+    fn set(&mut self, key: &str, value: JsonValue) -> sdk::Result {
+        let data = serde_json::to_string_pretty(&value)?;
+        sdk::write_atomic(&self.root.join(key), data.as_bytes())
+    }
+
+    fn del(&mut self, key: &str) -> sdk::Result {
+        match std::fs::remove_file(self.root.join(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Hidden files are temporary files of unfinished writes.
+    fn keys(&self) -> sdk::Result<Vec<String>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+        let mut result = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_file() && !name.starts_with('.') {
+                result.push(name);
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+}
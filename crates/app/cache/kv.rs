@@ -0,0 +1,84 @@
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition, TableError};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+type JsonValue = serde_json::Value;
+
+const TABLE: TableDefinition<&str, &str> = TableDefinition::new("entries");
+
+/// Stores entries in the embedded transactional key-value database,
+/// each change is committed separately. The database is opened for each
+/// access, since it can't be opened by several processes at once.
+pub struct KvDriver {
+    file: PathBuf,
+}
+
+impl KvDriver {
+    pub fn new(file: PathBuf) -> Self {
+        Self { file }
+    }
+
+    fn open(&self) -> sdk::Result<Database> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Database::create(&self.file).map_err(failed)
+    }
+}
+
+fn failed(err: impl Into<redb::Error>) -> sdk::Error {
+    sdk::Error::from(format!("Key-value cache error: {}", err.into()))
+}
+
+impl sdk::CacheDriver for KvDriver {
+    fn get(&self, key: &str) -> sdk::Result<Option<JsonValue>> {
+        let database = self.open()?;
+        let transaction = database.begin_read().map_err(failed)?;
+        let table = match transaction.open_table(TABLE) {
+            Ok(v) => v,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(failed(e)),
+        };
+        match table.get(key).map_err(failed)? {
+            None => Ok(None),
+            Some(data) => Ok(Some(JsonValue::from_str(data.value())?)),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: JsonValue) -> sdk::Result {
+        let data = serde_json::to_string(&value)?;
+        let database = self.open()?;
+        let transaction = database.begin_write().map_err(failed)?;
+        {
+            let mut table = transaction.open_table(TABLE).map_err(failed)?;
+            table.insert(key, data.as_str()).map_err(failed)?;
+        }
+        transaction.commit().map_err(failed)
+    }
+
+    fn del(&mut self, key: &str) -> sdk::Result {
+        let database = self.open()?;
+        let transaction = database.begin_write().map_err(failed)?;
+        {
+            let mut table = transaction.open_table(TABLE).map_err(failed)?;
+            table.remove(key).map_err(failed)?;
+        }
+        transaction.commit().map_err(failed)
+    }
+
+    fn keys(&self) -> sdk::Result<Vec<String>> {
+        let database = self.open()?;
+        let transaction = database.begin_read().map_err(failed)?;
+        let table = match transaction.open_table(TABLE) {
+            Ok(v) => v,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(failed(e)),
+        };
+        let mut result = Vec::new();
+        for item in table.iter().map_err(failed)? {
+            let (key, _) = item.map_err(failed)?;
+            result.push(key.value().to_string());
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,102 @@
+use sdk::CacheDriver;
+use std::fs::File;
+use std::path::Path;
+
+type JsonValue = serde_json::Value;
+
+//////////////////////////////////////////////////////////////////
+// Lock
+//////////////////////////////////////////////////////////////////
+
+/// Advisory lock of the cache directory, it serializes cache access of
+/// parallel processes. Readers share the lock, writers hold it exclusively.
+pub struct Lock {
+    file: File,
+}
+
+/// Releases the lock on drop.
+pub struct Guard<'a> {
+    file: &'a File,
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl Lock {
+    pub fn new(file: &Path) -> sdk::Result<Self> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(file)?;
+        Ok(Self { file })
+    }
+
+    pub fn shared(&self) -> sdk::Result<Guard<'_>> {
+        self.file.lock_shared()?;
+        Ok(Guard { file: &self.file })
+    }
+
+    pub fn exclusive(&self) -> sdk::Result<Guard<'_>> {
+        self.file.lock()?;
+        Ok(Guard { file: &self.file })
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Locked driver
+//////////////////////////////////////////////////////////////////
+
+/// Holds the directory lock during each operation of the inner driver.
+/// Reads hold the lock exclusively too, if the driver storage can't be
+/// opened by several readers at once.
+pub struct Locked {
+    inner: Box<dyn CacheDriver>,
+    lock: Lock,
+    shared_reads: bool,
+}
+
+impl Locked {
+    pub fn new(inner: Box<dyn CacheDriver>, lock: Lock, shared_reads: bool) -> Self {
+        Self {
+            inner,
+            lock,
+            shared_reads,
+        }
+    }
+
+    fn read(&self) -> sdk::Result<Guard<'_>> {
+        match self.shared_reads {
+            true => self.lock.shared(),
+            false => self.lock.exclusive(),
+        }
+    }
+}
+
+impl CacheDriver for Locked {
+    fn get(&self, key: &str) -> sdk::Result<Option<JsonValue>> {
+        let _guard = self.read()?;
+        self.inner.get(key)
+    }
+
+    fn set(&mut self, key: &str, value: JsonValue) -> sdk::Result {
+        let _guard = self.lock.exclusive()?;
+        self.inner.set(key, value)
+    }
+
+    fn del(&mut self, key: &str) -> sdk::Result {
+        let _guard = self.lock.exclusive()?;
+        self.inner.del(key)
+    }
+
+    fn keys(&self) -> sdk::Result<Vec<String>> {
+        let _guard = self.read()?;
+        self.inner.keys()
+    }
+}
@@ -0,0 +1,161 @@
+//////////////////////////////////////////////////////////////////
+// Mod
+//////////////////////////////////////////////////////////////////
+
+mod artifacts;
+mod file;
+mod filesystem;
+mod kv;
+mod lock;
+mod remote;
+
+pub use artifacts::ArtifactsDriver;
+pub use file::FileDriver;
+pub use filesystem::FilesystemDriver;
+pub use kv::KvDriver;
+pub use lock::Lock;
+pub use lock::Locked;
+pub use remote::LayeredDriver;
+pub use remote::RemoteDriver;
+
+use crate::config;
+use sdk::write_atomic;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+//////////////////////////////////////////////////////////////////
+// Backend
+//////////////////////////////////////////////////////////////////
+
+/// File which keeps the name of the backend the cache is stored by.
+const MARKER: &str = "backend";
+
+/// File which is locked during the cache access.
+const LOCK: &str = ".lock";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Nothing is stored between runs.
+    Memory,
+    /// One JSON file per entry in the 'entries' directory.
+    #[default]
+    Filesystem,
+    /// All entries in the single 'cache.json' file.
+    Json,
+    /// Embedded key-value database in the 'cache.redb' file,
+    /// it can't be opened by several processes at once.
+    Kv,
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Memory => "memory",
+            Backend::Filesystem => "filesystem",
+            Backend::Json => "json",
+            Backend::Kv => "kv",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::Memory, Self::Filesystem, Self::Json, Self::Kv]
+            .into_iter()
+            .find(|b| b.name() == name.trim())
+    }
+
+    fn driver(&self, root: &Path) -> Box<dyn sdk::CacheDriver> {
+        match self {
+            Backend::Memory => Box::new(sdk::CacheMemory::default()),
+            Backend::Filesystem => Box::new(FilesystemDriver::new(root.join("entries"))),
+            Backend::Json => Box::new(FileDriver::new(root.join("cache.json"))),
+            Backend::Kv => Box::new(KvDriver::new(root.join("cache.redb"))),
+        }
+    }
+
+    /// Removes files of the backend from the cache directory.
+    fn remove(&self, root: &Path) -> sdk::Result {
+        let result = match self {
+            Backend::Memory => return Ok(()),
+            Backend::Filesystem => std::fs::remove_dir_all(root.join("entries")),
+            Backend::Json => std::fs::remove_file(root.join("cache.json")),
+            Backend::Kv => std::fs::remove_file(root.join("cache.redb")),
+        };
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Open
+//////////////////////////////////////////////////////////////////
+
+/// Opens the cache in the given directory. If it was stored by another
+/// backend, entries are moved to the requested one. The memory backend
+/// leaves the stored cache untouched.
+pub fn open(root: &Path, backend: Backend) -> sdk::Result<sdk::Cache> {
+    if backend == Backend::Memory {
+        return Ok(sdk::share_cache(sdk::CacheMemory::default()));
+    }
+    let lock = Lock::new(&root.join(LOCK))?;
+    let mut driver = backend.driver(root);
+    {
+        let _guard = lock.exclusive()?;
+        let marker = root.join(MARKER);
+        let previous = std::fs::read_to_string(&marker)
+            .ok()
+            .and_then(|n| Backend::parse(&n));
+        if let Some(previous) = previous
+            && previous != backend
+        {
+            migrate(previous.driver(root).as_ref(), driver.as_mut()).map_err(|e| {
+                let msg = format!(
+                    "Failed to migrate cache from '{}' to '{}' backend",
+                    previous.name(),
+                    backend.name()
+                );
+                e.wrap(sdk::ErrorKind::Generic, msg)
+            })?;
+            previous.remove(root)?;
+        }
+        if previous != Some(backend) {
+            write_atomic(&marker, backend.name().as_bytes())?;
+        }
+    }
+    let shared_reads = backend != Backend::Kv;
+    Ok(sdk::share_cache(Locked::new(driver, lock, shared_reads)))
+}
+
+/// Copies all entries from one driver to another.
+pub fn migrate(from: &dyn sdk::CacheDriver, to: &mut dyn sdk::CacheDriver) -> sdk::Result {
+    for key in from.keys()? {
+        if let Some(value) = from.get(&key)? {
+            to.set(&key, value)?;
+        }
+    }
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////////
+// Artifacts
+//////////////////////////////////////////////////////////////////
+
+/// Driver of the artifacts in the given directory, backed by the remote
+/// server if it is configured.
+pub fn artifacts(root: PathBuf, config: &config::Artifacts) -> Box<dyn sdk::ArtifactDriver> {
+    let local = ArtifactsDriver::new(root, config.limit.0);
+    let Some(remote) = &config.remote else {
+        return Box::new(local);
+    };
+    let headers = remote
+        .headers
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let timeout = Duration::from_secs(remote.timeout);
+    let remote = RemoteDriver::new(&remote.url, headers, remote.read_only, timeout);
+    Box::new(LayeredDriver::new(local, remote))
+}
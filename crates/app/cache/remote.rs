@@ -0,0 +1,378 @@
+use crate::cache::ArtifactsDriver;
+use sdk::{ArtifactDriver, ArtifactSpace};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+//////////////////////////////////////////////////////////////////
+// Remote
+//////////////////////////////////////////////////////////////////
+
+/// Artifacts on the HTTP server. The blob is the '<url>/<space>/<key>'
+/// resource: 'GET' reads it, 'PUT' writes it and 'HEAD' checks it exists,
+/// the missing blob is '404'. Headers (e.g. authorization) are sent with
+/// every request. Read-only driver never writes.
+pub struct RemoteDriver {
+    url: String,
+    headers: Vec<(String, String)>,
+    read_only: bool,
+    agent: ureq::Agent,
+}
+
+impl RemoteDriver {
+    pub fn new(
+        url: &str,
+        headers: Vec<(String, String)>,
+        read_only: bool,
+        timeout: Duration,
+    ) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(Some(timeout))
+            .build()
+            .into();
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            headers,
+            read_only,
+            agent,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn headers<B>(&self, request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+        self.headers
+            .iter()
+            .fold(request, |r, (name, value)| r.header(name, value))
+    }
+
+    /// Missing blob is not an error, the caller checks the '404' status.
+    fn check(
+        &self,
+        response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+        method: &str,
+        space: ArtifactSpace,
+        key: &str,
+    ) -> sdk::Result<ureq::http::Response<ureq::Body>> {
+        let failed = |reason: String| {
+            sdk::Error::new(
+                sdk::ErrorKind::Generic,
+                format!(
+                    "Remote cache {} {}/{} failed: {}",
+                    method,
+                    space.name(),
+                    key,
+                    reason
+                ),
+            )
+        };
+        let response = response.map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() || status == ureq::http::StatusCode::NOT_FOUND {
+            return Ok(response);
+        }
+        let reason = match status.as_u16() {
+            401 | 403 => format!("{} (check the authorization headers)", status),
+            _ => status.to_string(),
+        };
+        Err(failed(reason))
+    }
+
+    fn resource(&self, space: ArtifactSpace, key: &str) -> String {
+        format!("{}/{}/{}", self.url, space.name(), key)
+    }
+}
+
+impl ArtifactDriver for RemoteDriver {
+    fn get(&self, space: ArtifactSpace, key: &str) -> sdk::Result<Option<Vec<u8>>> {
+        let request = self.headers(self.agent.get(self.resource(space, key)));
+        let mut response = self.check(request.call(), "GET", space, key)?;
+        if response.status() == ureq::http::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let data = response
+            .body_mut()
+            .with_config()
+            .limit(u64::MAX)
+            .read_to_vec()
+            .map_err(|e| sdk::Error::new(sdk::ErrorKind::Generic, e.to_string()))?;
+        Ok(Some(data))
+    }
+
+    fn put(&self, space: ArtifactSpace, key: &str, data: &[u8]) -> sdk::Result {
+        if self.read_only {
+            return Ok(());
+        }
+        let request = self.headers(self.agent.put(self.resource(space, key)));
+        self.check(request.send(data), "PUT", space, key)?;
+        Ok(())
+    }
+
+    fn has(&self, space: ArtifactSpace, key: &str) -> sdk::Result<bool> {
+        let request = self.headers(self.agent.head(self.resource(space, key)));
+        let response = self.check(request.call(), "HEAD", space, key)?;
+        Ok(response.status() != ureq::http::StatusCode::NOT_FOUND)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Layered
+//////////////////////////////////////////////////////////////////
+
+/// Local artifacts backed by the remote ones. Blobs missing locally are
+/// downloaded and kept, the stored ones are uploaded unless the remote is
+/// read-only. The remote is optional: after the first failure it is not
+/// used anymore and the build goes on with the local artifacts.
+pub struct LayeredDriver {
+    local: ArtifactsDriver,
+    remote: RemoteDriver,
+    offline: AtomicBool,
+}
+
+impl LayeredDriver {
+    pub fn new(local: ArtifactsDriver, remote: RemoteDriver) -> Self {
+        Self {
+            local,
+            remote,
+            offline: AtomicBool::new(false),
+        }
+    }
+
+    fn remote<T>(&self, f: impl FnOnce(&RemoteDriver) -> sdk::Result<T>) -> Option<T> {
+        if self.offline.load(Ordering::Relaxed) {
+            return None;
+        }
+        match f(&self.remote) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.offline.store(true, Ordering::Relaxed);
+                sdk::warning(format!(
+                    "Remote cache '{}' is not used: {}",
+                    self.remote.url(),
+                    e
+                ));
+                None
+            }
+        }
+    }
+}
+
+impl ArtifactDriver for LayeredDriver {
+    fn get(&self, space: ArtifactSpace, key: &str) -> sdk::Result<Option<Vec<u8>>> {
+        if let Some(data) = self.local.get(space, key)? {
+            return Ok(Some(data));
+        }
+        let Some(data) = self.remote(|r| r.get(space, key)).flatten() else {
+            return Ok(None);
+        };
+        if space == ArtifactSpace::Objects && sdk::digest(&data) != key {
+            sdk::warning(format!("Ignoring corrupted remote object '{}'", key));
+            return Ok(None);
+        }
+        self.local.put(space, key, &data)?;
+        Ok(Some(data))
+    }
+
+    fn put(&self, space: ArtifactSpace, key: &str, data: &[u8]) -> sdk::Result {
+        if !self.local.has(space, key)? {
+            self.local.put(space, key, data)?;
+        }
+        if !self.remote.read_only() && self.remote(|r| r.has(space, key)) == Some(false) {
+            self.remote(|r| r.put(space, key, data));
+        }
+        Ok(())
+    }
+
+    fn has(&self, space: ArtifactSpace, key: &str) -> sdk::Result<bool> {
+        if !self.local.has(space, key)? {
+            return Ok(false);
+        }
+        if self.remote.read_only() {
+            return Ok(true);
+        }
+        Ok(self.remote(|r| r.has(space, key)).unwrap_or(true))
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Tests
+//////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    const TOKEN: &str = "Bearer secret";
+
+    type Blobs = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// In-process server which keeps blobs in memory and requires
+    /// the authorization header.
+    struct Server {
+        url: String,
+        blobs: Blobs,
+    }
+
+    impl Server {
+        fn start() -> Self {
+            let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/cache", server.server_addr());
+            let blobs = Blobs::default();
+            let storage = blobs.clone();
+            std::thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let authorized = request
+                        .headers()
+                        .iter()
+                        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == TOKEN);
+                    let path = request.url().to_string();
+                    let response = match (authorized, request.method()) {
+                        (false, _) => {
+                            tiny_http::Response::from_data(Vec::new()).with_status_code(401)
+                        }
+                        (true, tiny_http::Method::Put) => {
+                            let mut data = Vec::new();
+                            request.as_reader().read_to_end(&mut data).unwrap();
+                            storage.lock().unwrap().insert(path, data);
+                            tiny_http::Response::from_data(Vec::new()).with_status_code(201)
+                        }
+                        (true, method) => match storage.lock().unwrap().get(&path) {
+                            None => {
+                                tiny_http::Response::from_data(Vec::new()).with_status_code(404)
+                            }
+                            Some(_) if *method == tiny_http::Method::Head => {
+                                tiny_http::Response::from_data(Vec::new())
+                            }
+                            Some(data) => tiny_http::Response::from_data(data.clone()),
+                        },
+                    };
+                    let _ = request.respond(response);
+                }
+            });
+            Self { url, blobs }
+        }
+
+        fn driver(&self, token: &str, read_only: bool) -> RemoteDriver {
+            let headers = vec![("Authorization".to_string(), token.to_string())];
+            RemoteDriver::new(&self.url, headers, read_only, Duration::from_secs(5))
+        }
+
+        fn blob(&self, space: ArtifactSpace, key: &str) -> Option<Vec<u8>> {
+            let path = format!("/cache/{}/{}", space.name(), key);
+            self.blobs.lock().unwrap().get(&path).cloned()
+        }
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let result =
+            std::env::temp_dir().join(format!("unimake-remote-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&result);
+        result
+    }
+
+    #[test]
+    fn remote_roundtrip() {
+        let server = Server::start();
+        let remote = server.driver(TOKEN, false);
+        let hash = sdk::digest(b"data");
+        assert!(!remote.has(ArtifactSpace::Objects, &hash).unwrap());
+        assert_eq!(remote.get(ArtifactSpace::Objects, &hash).unwrap(), None);
+        remote.put(ArtifactSpace::Objects, &hash, b"data").unwrap();
+        assert!(remote.has(ArtifactSpace::Objects, &hash).unwrap());
+        let data = remote.get(ArtifactSpace::Objects, &hash).unwrap();
+        assert_eq!(data.as_deref(), Some(b"data".as_slice()));
+    }
+
+    #[test]
+    fn remote_requires_authorization() {
+        let server = Server::start();
+        let remote = server.driver("Bearer wrong", false);
+        let error = remote.get(ArtifactSpace::Targets, "key").unwrap_err();
+        assert!(error.message().contains("401"), "{}", error.message());
+        assert!(remote.put(ArtifactSpace::Targets, "key", b"{}").is_err());
+    }
+
+    #[test]
+    fn remote_read_only_never_writes() {
+        let server = Server::start();
+        server
+            .driver(TOKEN, true)
+            .put(ArtifactSpace::Targets, "key", b"{}")
+            .unwrap();
+        assert_eq!(server.blob(ArtifactSpace::Targets, "key"), None);
+    }
+
+    #[test]
+    fn layered_shares_outputs() {
+        let server = Server::start();
+        let hash = sdk::digest(b"output");
+
+        let root = directory("writer");
+        let writer = LayeredDriver::new(
+            ArtifactsDriver::new(root.clone(), u64::MAX),
+            server.driver(TOKEN, false),
+        );
+        writer
+            .put(ArtifactSpace::Objects, &hash, b"output")
+            .unwrap();
+        assert_eq!(
+            server.blob(ArtifactSpace::Objects, &hash).as_deref(),
+            Some(b"output".as_slice())
+        );
+
+        let root = directory("reader");
+        let reader = LayeredDriver::new(
+            ArtifactsDriver::new(root.clone(), u64::MAX),
+            server.driver(TOKEN, true),
+        );
+        let data = reader.get(ArtifactSpace::Objects, &hash).unwrap();
+        assert_eq!(data.as_deref(), Some(b"output".as_slice()));
+        let local = ArtifactsDriver::new(root.clone(), u64::MAX);
+        assert!(local.has(ArtifactSpace::Objects, &hash).unwrap());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn layered_rejects_corrupted_objects() {
+        let server = Server::start();
+        let hash = sdk::digest(b"output");
+        server
+            .driver(TOKEN, false)
+            .put(ArtifactSpace::Objects, &hash, b"other")
+            .unwrap();
+        let root = directory("corrupted");
+        let layered = LayeredDriver::new(
+            ArtifactsDriver::new(root.clone(), u64::MAX),
+            server.driver(TOKEN, true),
+        );
+        assert_eq!(layered.get(ArtifactSpace::Objects, &hash).unwrap(), None);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn layered_works_without_remote() {
+        let server = Server::start();
+        let root = directory("offline");
+        let layered = LayeredDriver::new(
+            ArtifactsDriver::new(root.clone(), u64::MAX),
+            server.driver("Bearer wrong", false),
+        );
+        layered.put(ArtifactSpace::Targets, "key", b"{}").unwrap();
+        let data = layered.get(ArtifactSpace::Targets, "key").unwrap();
+        assert_eq!(data.as_deref(), Some(b"{}".as_slice()));
+        assert_eq!(
+            layered.get(ArtifactSpace::Targets, "missing").unwrap(),
+            None
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -1,127 +1,343 @@
-use clap;
+use crate::commands;
+use crate::plugins;
+use crate::plugins::Plugins;
+use clap::error::ErrorKind as ClapErrorKind;
+use std::collections::{HashMap, HashSet};
 
-pub struct Interface {
-    workspace: Option<sdk::Workspace>,
-    root: clap::Command,
+//////////////////////////////////////////////////////////////////
+// Global options
+//////////////////////////////////////////////////////////////////
+
+pub const VERBOSE: &str = "verbose";
+pub const SET: &str = "set";
+pub const PROFILE: &str = "profile";
+pub const TIMINGS: &str = "timings";
+pub const TRACE: &str = "trace";
+
+/// Global options which take a value.
+const VALUED: &[&str] = &[SET, PROFILE, TRACE];
+
+/// Global options must be known before the command line is parsed,
+/// because the workspace loading may fail before that.
+pub fn verbose() -> bool {
+    flag(VERBOSE)
 }
 
-impl Interface {
-    pub fn run(self) -> sdk::Result {
-        match self.root.clone().get_matches().subcommand() {
-            Some(("cache", matches)) => match &self.workspace {
-                None => Err(sdk::Error::new(
-                    format!(
-                        "Failed to generate, cache current working directory is not an {} project",
-                        sdk::global::project::NAME
-                    )
-                    .as_str(),
-                )),
-                Some(w) => {
-                    w.load()?;
-                    w.instantiate()?;
-                    w.caches()
-                }
-            },
-            Some(("cli", matches)) => self.call(matches.clone()),
-            _ => unreachable!("Unreachable subcommand"),
+/// Whether the global flag is given, e.g. 'true' for '--timings'.
+pub fn flag(name: &str) -> bool {
+    let option = format!("--{}", name);
+    std::env::args()
+        .skip(1)
+        .take_while(|a| a != "--")
+        .any(|a| a == option)
+}
+
+/// Values of the global option, e.g. ["a=1", "b=2"] of '--set a=1 --set=b=2'.
+pub fn values(name: &str) -> Vec<String> {
+    let (option, prefix) = (format!("--{}", name), format!("--{}=", name));
+    let mut result = Vec::new();
+    let mut args = std::env::args().skip(1).take_while(|a| a != "--");
+    while let Some(arg) = args.next() {
+        if arg == option {
+            result.extend(args.next());
+        } else if let Some(value) = arg.strip_prefix(&prefix) {
+            result.push(value.to_string());
         }
     }
+    result
+}
 
-    fn call(&self, matches: clap::ArgMatches) -> sdk::Result {
-        if self.workspace.is_none() {
-            return Err(sdk::Error::new("Failed to call CLI, no workspace found"));
-        }
-        let mut matches = matches;
-        let mut sequence = Vec::<(String, clap::ArgMatches)>::with_capacity(8);
-        loop {
-            match matches.subcommand() {
-                None => break,
-                Some((sub_cmd, sub_matches)) => {
-                    sequence.push((sub_cmd.to_string(), sub_matches.clone()));
-                    matches = sub_matches.clone();
-                }
-            }
-        }
-        let workspace = self.workspace.as_ref().unwrap();
-        for (name, matches) in sequence {
-            println!(
-                "src={:?} dst={:?}",
-                matches.get_one::<String>("src"),
-                matches.get_one::<String>("dst")
-            )
+/// Whether the builtin command which must not load the workspace script
+/// is called.
+pub fn standalone() -> bool {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    subcommand(&args).is_some_and(|i| commands::STANDALONE.contains(&args[i].as_str()))
+}
+
+/// Index of the subcommand: the first positional argument, except values
+/// of the global options.
+fn subcommand(args: &[String]) -> Option<usize> {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        if VALUED.iter().any(|v| arg.strip_prefix("--") == Some(*v)) {
+            index += 1;
+        } else if !arg.starts_with('-') {
+            return Some(index);
         }
-        Ok(())
+        index += 1;
     }
+    None
+}
+
+//////////////////////////////////////////////////////////////////
+// Interface
+//////////////////////////////////////////////////////////////////
+
+pub struct Interface {
+    root: clap::Command,
+    owners: HashMap<String, usize>,
+    /// Workspace commands available at the top level.
+    lifted: HashSet<String>,
+    /// Workspace commands whose names are taken by the builtin commands.
+    conflicts: Vec<String>,
+    /// Workspace command which is run when no command is given.
+    default: Option<String>,
 }
 
 impl Interface {
-    pub fn new(workspace: Option<sdk::Workspace>) -> sdk::Result<Self> {
-        let mut root = clap::Command::new(sdk::global::project::NAME)
-            .version(sdk::global::project::VERSION)
+    pub fn new(plugins: &Plugins, cache: Option<&sdk::Cache>) -> sdk::Result<Self> {
+        let mut root = clap::Command::new(global::project::NAME)
+            .version(global::project::VERSION)
             .author("Edward Sarkisyan <edw.sarkisyan@gmail.com>")
             .about("Unimake (umk) is a `make` alternative based on Python")
             .disable_help_subcommand(true)
             .arg_required_else_help(true)
-            .subcommand(clap::Command::new("cache").about("Resolve project and cache all entries"));
-        if let Some(w) = &workspace {
-            let graph = w.cli()?;
-            root = root.subcommand(build::cli(graph));
+            .arg(
+                clap::Arg::new(VERBOSE)
+                    .long(VERBOSE)
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Show the full cause chain of errors"),
+            )
+            .arg(
+                clap::Arg::new(global::flags::DRY_RUN)
+                    .long(global::flags::DRY_RUN)
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print the commands which would run, without running them"),
+            )
+            .arg(
+                clap::Arg::new(global::flags::EXPLAIN)
+                    .long(global::flags::EXPLAIN)
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print why each command is run or its outputs are restored"),
+            )
+            .arg(
+                clap::Arg::new(SET)
+                    .long(SET)
+                    .global(true)
+                    .value_name("KEY=VALUE")
+                    .action(clap::ArgAction::Append)
+                    .help("Override the workspace setting (see 'umk.config')"),
+            )
+            .arg(
+                clap::Arg::new(PROFILE)
+                    .long(PROFILE)
+                    .global(true)
+                    .value_name("NAME")
+                    .help("Apply the settings profile from the config files"),
+            )
+            .arg(
+                clap::Arg::new(TIMINGS)
+                    .long(TIMINGS)
+                    .global(true)
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print how long the loading phases and each target take"),
+            )
+            .arg(
+                clap::Arg::new(TRACE)
+                    .long(TRACE)
+                    .global(true)
+                    .value_name("FILE")
+                    .help("Write the timings as Chrome trace events (open in Perfetto)"),
+            )
+            .subcommands(commands::all());
+        let mut owners = HashMap::new();
+        let (mut lifted, mut conflicts, mut default) = (HashSet::new(), Vec::new(), None);
+        if let Some(cache) = cache {
+            for (index, plugin) in plugins.iter().enumerate() {
+                if let Some(cmd) = plugin.cli(cache)? {
+                    owners.insert(cmd.get_name().to_string(), index);
+                    root = root.subcommand(cmd);
+                }
+            }
+            if let Some(&index) = owners.get(plugin_cli::SUBCOMMAND) {
+                // Builtin and plugin commands take priority, the workspace
+                // command is still available under the 'cli' subcommand.
+                let taken = |root: &clap::Command, name: &str| {
+                    root.get_subcommands()
+                        .any(|c| c.get_name() == name || c.get_all_aliases().any(|a| a == name))
+                };
+                for info in plugin_cli::commands(cache)? {
+                    if info.default {
+                        default = Some(info.name.clone());
+                    }
+                    if std::iter::once(&info.name)
+                        .chain(info.aliases.iter())
+                        .any(|n| taken(&root, n))
+                    {
+                        conflicts.push(info.name);
+                        continue;
+                    }
+                    owners.insert(info.name.clone(), index);
+                    root = root.subcommand(clap::Command::from(&info));
+                    lifted.insert(info.name);
+                }
+            }
+        }
+        if default.is_some() {
+            root = root.arg_required_else_help(false);
         }
-        Ok(Self { workspace, root })
+        Ok(Self {
+            root: group(root),
+            owners,
+            lifted,
+            conflicts,
+            default,
+        })
     }
 }
 
-mod build {
-    use clap::value_parser;
+/// Lifted workspace commands keep their categories, so all top-level
+/// commands are listed under the headings, like in 'cli --help'.
+fn group(root: clap::Command) -> clap::Command {
+    let commands: Vec<clap::Command> = root.get_subcommands().cloned().collect();
+    let Some(listing) = plugin_cli::categories(&commands.iter().collect::<Vec<_>>()) else {
+        return root;
+    };
+    // Hidden subcommands are not shown in the usage either.
+    let usage = format!("{} [OPTIONS] [COMMAND]", root.get_name());
+    let mut root = root
+        .help_template(plugin_cli::HELP_TEMPLATE)
+        .before_help(listing)
+        .override_usage(usage);
+    for cmd in commands.iter() {
+        root = root.mut_subcommand(cmd.get_name(), |c| c.hide(true));
+    }
+    root
+}
 
-    pub fn cli(src: unisdk::umk::Cli) -> clap::Command {
-        let children: Vec<clap::Command> = src.nodes.into_iter().map(|c| cmd(c)).collect();
-        clap::Command::new("cli")
-            .about("CLI declared in workspace")
-            .arg_required_else_help(true)
-            .disable_help_subcommand(true)
-            .subcommands(children)
+impl Interface {
+    /// Workspace commands which are available under the 'cli' subcommand
+    /// only, because their names (or aliases) are taken.
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
     }
 
-    pub fn cmd(src: unisdk::umk::Cmd) -> clap::Command {
-        let options: Vec<clap::Arg> = src.options.into_iter().map(|o| opt(o)).collect();
-        let arguments: Vec<clap::Arg> = src.arguments.into_iter().map(|a| arg(a)).collect();
-        clap::Command::new(src.name)
-            .about(src.help.unwrap_or_default())
-            .args(options.iter().chain(arguments.iter()))
+    /// Name of the workspace command the matches refer to, if any.
+    pub fn workspace_command(&self, matches: &clap::ArgMatches) -> Option<String> {
+        match path(matches).as_slice() {
+            [owner, name] if owner == plugin_cli::SUBCOMMAND => Some(name.clone()),
+            [name] if self.lifted.contains(name) => Some(name.clone()),
+            _ => None,
+        }
     }
+}
 
-    pub fn opt(src: unisdk::umk::Opt) -> clap::Arg {
-        let name = if src.long != "" {
-            src.long.clone()
-        } else {
-            String::from(src.short.unwrap())
+impl Interface {
+    /// Parses the command line and calls a plugin which owns the subcommand
+    /// (or the builtin command). The unknown subcommand is the file made by
+    /// the pattern rules if some of them matches it, like 'cli build/main.o'.
+    /// The 'context.missing' error is returned if the command line refers to something
+    /// unknown, it is usually the workspace absence reason.
+    pub fn run(self, mut context: commands::Context) -> sdk::Result {
+        let matches = match self.root.clone().try_get_matches() {
+            Ok(v) => v,
+            Err(e) => match (e.kind(), context.missing.take()) {
+                (ClapErrorKind::InvalidSubcommand | ClapErrorKind::UnknownArgument, Some(m)) => {
+                    return Err(m);
+                }
+                (ClapErrorKind::InvalidSubcommand, None) => match self.file(&context)? {
+                    Some(matches) => matches,
+                    None => e.exit(),
+                },
+                _ => e.exit(),
+            },
         };
-        let mut result = clap::Arg::new(name)
-            .long(src.long)
-            .required(src.required)
-            .help(src.help.unwrap_or_default())
-            .value_parser(parser(src.class));
-        if let Some(v) = src.short {
-            result = result.short(v);
+        if matches.subcommand().is_none()
+            && let Some(default) = &self.default
+        {
+            // Global options are kept, e.g. 'unimake --dry-run'.
+            let mut args: Vec<String> = std::env::args().skip(1).collect();
+            args.extend([plugin_cli::SUBCOMMAND.to_string(), default.clone()]);
+            let matches = self.parse(&args).unwrap_or_else(|e| e.exit());
+            return self.dispatch(&matches, &context);
         }
-        result
+        self.dispatch(&matches, &context)
     }
 
-    pub fn arg(src: unisdk::umk::Arg) -> clap::Arg {
-        clap::Arg::new(src.name)
-            .required(true)
-            .help(src.help.unwrap_or_default())
-            .value_parser(parser(src.class))
+    /// Matches of the 'cli' subcommand if the unknown subcommand is the file
+    /// which the rules make.
+    fn file(&self, context: &commands::Context) -> sdk::Result<Option<clap::ArgMatches>> {
+        let mut args: Vec<String> = std::env::args().skip(1).collect();
+        let Some(index) = subcommand(&args) else {
+            return Ok(None);
+        };
+        if !self.owners.contains_key(plugin_cli::SUBCOMMAND)
+            || !context
+                .interpreter
+                .exec(|vm| plugin_cli::makes(vm, &args[index]))?
+        {
+            return Ok(None);
+        }
+        args.insert(index, plugin_cli::SUBCOMMAND.to_string());
+        Ok(Some(self.parse(&args).unwrap_or_else(|e| e.exit())))
     }
 
-    fn parser(class: unisdk::umk::ArgClass) -> clap::builder::ValueParser {
-        match class {
-            unisdk::umk::ArgClass::String => value_parser!(String),
-            unisdk::umk::ArgClass::Integer => value_parser!(i64).into(),
-            unisdk::umk::ArgClass::Float => value_parser!(f64).into(),
-            unisdk::umk::ArgClass::Boolean => value_parser!(bool).into(),
-            unisdk::umk::ArgClass::Custom => value_parser!(String).into(),
-        }
+    /// Parses the arguments which follow the program name.
+    pub fn parse(&self, args: &[String]) -> clap::error::Result<clap::ArgMatches> {
+        let program = std::iter::once(global::project::NAME.to_string());
+        self.root
+            .clone()
+            .try_get_matches_from(program.chain(args.iter().cloned()))
+    }
+
+    /// Calls a plugin which owns the subcommand (or the builtin command)
+    /// surrounded by the command hooks of all plugins.
+    pub fn dispatch(&self, matches: &clap::ArgMatches, context: &commands::Context) -> sdk::Result {
+        let (plugins, interpreter) = (context.plugins, context.interpreter);
+        let Some((name, sub)) = matches.subcommand() else {
+            return Ok(());
+        };
+        let command = path(matches);
+        interpreter
+            .enter(|vm| plugins::forward(plugins, vm, |p, vm| p.before_command(&command, vm)))?;
+        let result = match self.owners.get(name) {
+            // The plugin expects the matches whose subcommand is the
+            // workspace command, which are the root ones at the top level.
+            Some(&index) if self.lifted.contains(name) => {
+                interpreter.enter(|vm| plugins[index].call(matches, vm))
+            }
+            Some(&index) => interpreter.enter(|vm| plugins[index].call(sub, vm)),
+            None => commands::call(name, sub, context)
+                .unwrap_or_else(|| unreachable!("Unreachable subcommand: {}", name)),
+        };
+        let hooks = interpreter.enter(|vm| {
+            plugins::backward(plugins, vm, |p, vm| p.after_command(&command, &result, vm))
+        });
+        result.and(hooks)
+    }
+}
+
+/// Names of the nested subcommands, e.g. ["cli", "build"].
+pub fn path(matches: &clap::ArgMatches) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        result.push(name.to_string());
+        current = sub;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(args: &[&str]) -> Option<usize> {
+        subcommand(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn subcommand_skips_global_values() {
+        assert_eq!(index(&["build/main.o"]), Some(0));
+        assert_eq!(index(&["--dry-run", "build/main.o"]), Some(1));
+        assert_eq!(
+            index(&["--set", "jobs=2", "--profile", "ci", "a.o"]),
+            Some(4)
+        );
+        assert_eq!(index(&["--set=jobs=2", "a.o", "b.o"]), Some(1));
+        assert_eq!(index(&["--verbose"]), None);
     }
 }
@@ -0,0 +1,293 @@
+use crate::cache::ArtifactsDriver;
+use crate::commands::{Context, JSON, age, json, print_json, size};
+use crate::config::Size;
+use crate::style::Style;
+use crate::workspace;
+use sdk::{Error, ErrorKind};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const NAME: &str = "cache";
+
+const STATUS: &str = "status";
+const CLEAR: &str = "clear";
+const SHOW: &str = "show";
+const REBUILD: &str = "rebuild";
+const GC: &str = "gc";
+const KEY: &str = "key";
+const LIMIT: &str = "limit";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(NAME)
+        .about("Inspect and reset the workspace cache")
+        .arg_required_else_help(true)
+        .disable_help_subcommand(true)
+        .subcommand(
+            clap::Command::new(STATUS)
+                .about("Show cache entries and whether they match the workspace sources")
+                .arg(json()),
+        )
+        .subcommand(
+            clap::Command::new(CLEAR)
+                .about("Remove the cache entry (all entries if the key is omitted)")
+                .arg(clap::Arg::new(KEY).help("Cache entry key")),
+        )
+        .subcommand(
+            clap::Command::new(SHOW)
+                .about("Print the cache entry as JSON")
+                .arg(clap::Arg::new(KEY).required(true).help("Cache entry key")),
+        )
+        .subcommand(
+            clap::Command::new(REBUILD).about("Remove all entries and load the workspace script"),
+        )
+        .subcommand(
+            clap::Command::new(GC)
+                .about("Remove least recently used artifacts until they fit into the limit")
+                .arg(
+                    clap::Arg::new(LIMIT)
+                        .long(LIMIT)
+                        .value_name("SIZE")
+                        .value_parser(|v: &str| Size::parse(v).ok_or("invalid size"))
+                        .help("Size to fit into, '0' removes everything [default: from config]"),
+                ),
+        )
+}
+
+pub fn run(matches: &clap::ArgMatches, context: &Context) -> sdk::Result {
+    let Some((name, matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    let key = matches.try_get_one::<String>(KEY).ok().flatten();
+    match name {
+        STATUS => status(context, matches.get_flag(JSON)),
+        CLEAR => clear(context, key.map(String::as_str)),
+        SHOW => show(context, key.unwrap()),
+        REBUILD => rebuild(context),
+        GC => gc(context, matches.get_one::<Size>(LIMIT).copied()),
+        _ => unreachable!("Unreachable subcommand: {}", name),
+    }
+}
+
+fn not_found(key: &str) -> Error {
+    Error::new(
+        ErrorKind::Generic,
+        format!("Cache entry not found: '{}'", key),
+    )
+}
+
+//////////////////////////////////////////////////////////////////
+// Status
+//////////////////////////////////////////////////////////////////
+
+/// Entry is valid if it is written by the running unimake version
+/// and the workspace sources are not changed since then.
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Valid,
+    Stale,
+    Outdated,
+    Unreadable,
+}
+
+impl State {
+    fn name(&self) -> &'static str {
+        match self {
+            State::Valid => "valid",
+            State::Stale => "stale",
+            State::Outdated => "outdated",
+            State::Unreadable => "unreadable",
+        }
+    }
+}
+
+struct Row {
+    key: String,
+    size: u64,
+    header: Option<sdk::CacheHeader>,
+    state: State,
+}
+
+fn status(context: &Context, json: bool) -> sdk::Result {
+    let (workspace, cache) = context.workspace()?;
+    let current = workspace.sources()?;
+    let stored =
+        sdk::CacheEntry::<workspace::Sources>::new(workspace::CACHE_KEY, cache.clone()).get()?;
+    let changed: Option<Vec<String>> = stored.as_ref().map(|stored| {
+        let mut result: Vec<String> = current
+            .iter()
+            .filter(|(file, hash)| stored.get(*file) != Some(*hash))
+            .map(|(file, _)| file.clone())
+            .chain(stored.keys().filter(|f| !current.contains_key(*f)).cloned())
+            .collect();
+        result.sort();
+        result
+    });
+    let fresh = changed.as_ref().is_some_and(|c| c.is_empty());
+    let limit = limit(context);
+    let artifacts = ArtifactsDriver::new(workspace.artifacts(), limit.0).usage()?;
+    let remote = context
+        .config
+        .and_then(|c| c.cache.artifacts.remote.as_ref());
+
+    let mut rows = Vec::new();
+    let driver = cache.read();
+    for key in driver.keys()? {
+        let value = driver.get(&key).ok().flatten();
+        let header = value.as_ref().and_then(sdk::CacheHeader::read);
+        let state = match &header {
+            None => State::Unreadable,
+            Some(h) if !h.is_current() => State::Outdated,
+            Some(_) if !fresh => State::Stale,
+            Some(_) => State::Valid,
+        };
+        rows.push(Row {
+            size: value.map(|v| v.to_string().len()).unwrap_or_default() as u64,
+            key,
+            header,
+            state,
+        });
+    }
+
+    let sources = match &changed {
+        None => "unknown",
+        Some(c) if c.is_empty() => "unchanged",
+        Some(_) => "changed",
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    if json {
+        let entries: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|r| {
+                json!({
+                    "key": r.key,
+                    "size": r.size,
+                    "time": r.header.as_ref().map(|h| h.time),
+                    "unimake": r.header.as_ref().map(|h| h.stamp.unimake.clone()),
+                    "schema": r.header.as_ref().map(|h| h.stamp.schema),
+                    "state": r.state.name(),
+                })
+            })
+            .collect();
+        return print_json(&json!({
+            "directory": workspace.cache(),
+            "sources": { "state": sources, "changed": changed.unwrap_or_default() },
+            "entries": entries,
+            "artifacts": {
+                "directory": workspace.artifacts(),
+                "targets": artifacts.targets,
+                "objects": artifacts.objects,
+                "size": artifacts.bytes,
+                "limit": limit.0,
+                "remote": remote.map(|r| json!({ "url": r.url, "read_only": r.read_only })),
+            },
+        }));
+    }
+
+    let style = Style::stdout();
+    println!(
+        "{} {}",
+        style.bold("Directory:"),
+        workspace.cache().display()
+    );
+    println!("{} {}", style.bold("Sources:"), sources);
+    for file in changed.iter().flatten() {
+        println!("  {} {}", style.warning("~"), file);
+    }
+    println!(
+        "{} {} targets, {} of {}",
+        style.bold("Artifacts:"),
+        artifacts.targets,
+        size(artifacts.bytes),
+        size(limit.0)
+    );
+    if let Some(remote) = remote {
+        let mode = if remote.read_only { " (read-only)" } else { "" };
+        println!("{} {}{}", style.bold("Remote:"), remote.url, mode);
+    }
+    if rows.is_empty() {
+        println!("{}", style.dim("Cache is empty"));
+        return Ok(());
+    }
+    let width = rows.iter().map(|r| r.key.len()).max().unwrap_or(0).max(3);
+    println!();
+    let header = format!("{:width$}  {:>9}  {:>6}  STATE", "KEY", "SIZE", "AGE");
+    println!("{}", style.bold(&header));
+    for row in rows.iter() {
+        let age = row.header.as_ref().map(|h| age(now.saturating_sub(h.time)));
+        let state = match row.state {
+            State::Valid => style.accent(row.state.name()),
+            State::Stale | State::Outdated => style.warning(row.state.name()),
+            State::Unreadable => style.error(row.state.name()),
+        };
+        println!(
+            "{:width$}  {:>9}  {:>6}  {}",
+            row.key,
+            size(row.size),
+            age.unwrap_or_else(|| "-".into()),
+            state
+        );
+    }
+    Ok(())
+}
+
+fn limit(context: &Context) -> Size {
+    context
+        .config
+        .map(|c| c.cache.artifacts.limit)
+        .unwrap_or_default()
+}
+
+//////////////////////////////////////////////////////////////////
+// Clear, show, rebuild and gc
+//////////////////////////////////////////////////////////////////
+
+fn clear(context: &Context, key: Option<&str>) -> sdk::Result {
+    let (_, cache) = context.workspace()?;
+    let keys = cache.read().keys()?;
+    let keys = match key {
+        None => keys,
+        Some(key) if keys.iter().any(|k| k == key) => vec![key.to_string()],
+        Some(key) => return Err(not_found(key)),
+    };
+    let mut driver = cache.write();
+    for key in keys.iter() {
+        driver.del(key)?;
+    }
+    eprintln!("Removed {} cache entries", keys.len());
+    Ok(())
+}
+
+fn show(context: &Context, key: &str) -> sdk::Result {
+    let (_, cache) = context.workspace()?;
+    match cache.read().get(key)? {
+        None => Err(not_found(key)),
+        Some(value) => print_json(&value),
+    }
+}
+
+fn rebuild(context: &Context) -> sdk::Result {
+    let (workspace, cache) = context.workspace()?;
+    let keys = cache.read().keys()?;
+    for key in keys.iter() {
+        cache.write().del(key)?;
+    }
+    workspace.refresh(context.plugins, context.interpreter, cache)?;
+    eprintln!("Rebuilt {} cache entries", cache.read().keys()?.len());
+    Ok(())
+}
+
+fn gc(context: &Context, limit: Option<Size>) -> sdk::Result {
+    let (workspace, _) = context.workspace()?;
+    let limit = limit.unwrap_or_else(|| self::limit(context));
+    let collected = ArtifactsDriver::new(workspace.artifacts(), limit.0).collect(limit.0)?;
+    eprintln!(
+        "Removed {} artifact targets and {} objects, freed {}",
+        collected.targets,
+        collected.objects,
+        size(collected.bytes)
+    );
+    Ok(())
+}
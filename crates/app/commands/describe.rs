@@ -0,0 +1,281 @@
+use crate::commands::{Context, JSON, json, plugins, print_json};
+use crate::style::Style;
+use plugin_cli::{ArgumentInfo, CommandInfo, OptionInfo, RuleInfo};
+use serde_json::json;
+
+pub const NAME: &str = "describe";
+
+/// Version of the JSON layout, it is increased on incompatible changes
+/// only (new fields may be added without the increase).
+///
+/// ```text
+/// {
+///   "schema": 2,
+///   "unimake": { "version": str },
+///   "project": null | { "name": str, "version": str, "title": str, "description": str },
+///   "workspace": null | { "root": str, "mode": "single" | "tree", "script": str },
+///   "plugins": [ { "name": str, "version": str, "description": str, "dependencies": [str] } ],
+///   "commands": [ {
+///     "name": str, "help": str | null, "aliases": [str], "hidden": bool,
+///     "category": str | null, "deprecated": str | null, "default": bool,
+///     "timeout": float | null, "retries": int, "retry_delay": float,
+///     "options": [ { "name": str, "short": str | null, "type": TYPE, "default": any,
+///                    "required": bool, "var": str, "help": str | null } ],
+///     "arguments": [ { "name": str, "type": TYPE, "default": any,
+///                      "required": bool, "var": str, "help": str | null } ],
+///     "inputs": [str], "outputs": [str], "deps": [str], "finally": [str]
+///   } ],
+///   "rules": [ { "pattern": str, "inputs": [str], "deps": [str], "help": str | null } ],
+///   "edges": [ { "from": str, "to": str } ]
+/// }
+/// ```
+///
+/// TYPE is one of "string", "integer", "float", "boolean" or "custom".
+/// Commands and options are sorted by name, arguments keep their order.
+/// Rules keep the declaration order, the '%' in their paths is the stem.
+/// The edge means the 'from' command depends on the 'to' one. The
+/// 'project' is declared by 'umk.project', it is null without the
+/// workspace.
+pub const SCHEMA: u32 = 2;
+
+pub fn command() -> clap::Command {
+    clap::Command::new(NAME)
+        .about("Describe the workspace, its commands and plugins")
+        .long_about(format!(
+            "Describe the workspace, its commands and plugins. The JSON output \
+            is meant for tools and follows the schema version {}.",
+            SCHEMA
+        ))
+        .arg(json())
+}
+
+pub fn run(matches: &clap::ArgMatches, context: &Context) -> sdk::Result {
+    let (commands, rules) = declared(context)?;
+    let project = project(context)?;
+    if matches.get_flag(JSON) {
+        return print_json(&to_json(context, project.as_ref(), &commands, &rules));
+    }
+    let style = Style::stdout();
+    println!(
+        "{} {}",
+        style.accent(global::project::NAME),
+        global::project::VERSION
+    );
+    match context.workspace {
+        Some(workspace) => println!(
+            "{} {} ({})",
+            style.bold("Workspace:"),
+            workspace.root().display(),
+            workspace.mode().name()
+        ),
+        None => println!("{} none", style.bold("Workspace:")),
+    }
+    if let Some(project) = &project {
+        match project.version.is_empty() {
+            true => println!("{} {}", style.bold("Project:"), project.name),
+            false => println!(
+                "{} {} {}",
+                style.bold("Project:"),
+                project.name,
+                project.version
+            ),
+        }
+    }
+    let names: Vec<String> = context.plugins.iter().map(|p| p.info().name).collect();
+    println!("{} {}", style.bold("Plugins:"), names.join(", "));
+    if !commands.is_empty() {
+        println!();
+        println!("{}", style.bold("Commands:"));
+    }
+    for command in commands.iter().filter(|c| !c.hidden) {
+        let mut usage = vec![style.bold(&command.name)];
+        usage.extend(options(command).iter().map(|o| match o.required {
+            true => format!("--{} <{}>", o.name, o.class.name()),
+            false => format!("[--{}]", o.name),
+        }));
+        usage.extend(command.arguments.iter().map(|a| match a.required {
+            true => format!("<{}>", a.name),
+            false => format!("[{}]", a.name),
+        }));
+        println!("  {}", usage.join(" "));
+        if let Some(help) = &command.help {
+            println!("      {}", style.dim(help));
+        }
+        if !command.aliases.is_empty() {
+            let aliases = format!("aliases: {}", command.aliases.join(", "));
+            println!("      {}", style.dim(&aliases));
+        }
+        if let Some(message) = &command.deprecated {
+            println!("      {}", style.dim(&format!("deprecated: {}", message)));
+        }
+        if command.default {
+            println!("      {}", style.dim("default"));
+        }
+        if !command.deps.is_empty() {
+            println!(
+                "      {}",
+                style.dim(&format!("after {}", command.deps.join(", ")))
+            );
+        }
+        if !command.finally.is_empty() {
+            println!(
+                "      {}",
+                style.dim(&format!("finally {}", command.finally.join(", ")))
+            );
+        }
+    }
+    if !rules.is_empty() {
+        println!();
+        println!("{}", style.bold("Rules:"));
+    }
+    for rule in rules.iter() {
+        println!("  {}: {}", style.bold(&rule.pattern), rule.inputs.join(" "));
+        if let Some(help) = &rule.help {
+            println!("      {}", style.dim(help));
+        }
+    }
+    Ok(())
+}
+
+/// Workspace commands sorted by name and rules, there are none
+/// without the workspace.
+fn declared(context: &Context) -> sdk::Result<(Vec<CommandInfo>, Vec<RuleInfo>)> {
+    if context.workspace.is_none() {
+        return Ok(Default::default());
+    }
+    let (commands, rules) = context.interpreter.exec(|vm| {
+        let commands = sdk::py::service::<plugin_cli::Commands>(vm)?;
+        let rules = sdk::py::service::<plugin_cli::Rules>(vm)?;
+        Ok((commands, rules))
+    })?;
+    let mut commands: Vec<CommandInfo> = commands
+        .map(|r| r.0.values().map(CommandInfo::from).collect())
+        .unwrap_or_default();
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+    let rules = rules
+        .map(|r| r.0.iter().map(RuleInfo::from).collect())
+        .unwrap_or_default();
+    Ok((commands, rules))
+}
+
+/// Values of 'umk.project', there are none without the workspace.
+fn project(context: &Context) -> sdk::Result<Option<sdk::ProjectInfo>> {
+    if context.workspace.is_none() {
+        return Ok(None);
+    }
+    let project = context
+        .interpreter
+        .exec(sdk::py::service::<sdk::ProjectInfo>)?;
+    Ok(project.map(|p| p.as_ref().clone()))
+}
+
+fn options(command: &CommandInfo) -> Vec<&OptionInfo> {
+    let mut result: Vec<&OptionInfo> = command.options.values().collect();
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    result
+}
+
+//////////////////////////////////////////////////////////////////
+// JSON
+//////////////////////////////////////////////////////////////////
+
+fn to_json(
+    context: &Context,
+    project: Option<&sdk::ProjectInfo>,
+    commands: &[CommandInfo],
+    rules: &[RuleInfo],
+) -> serde_json::Value {
+    let workspace = context.workspace.map(|w| {
+        json!({
+            "root": w.root(),
+            "mode": w.mode().name(),
+            "script": w.root().join(global::workspace::FILE),
+        })
+    });
+    let edges: Vec<serde_json::Value> = commands
+        .iter()
+        .flat_map(|c| c.deps.iter().map(|d| json!({ "from": c.name, "to": d })))
+        .collect();
+    json!({
+        "schema": SCHEMA,
+        "unimake": {
+            "version": global::project::VERSION,
+        },
+        "project": project.map(|p| json!({
+            "name": p.name,
+            "version": p.version,
+            "title": p.title,
+            "description": p.description,
+        })),
+        "workspace": workspace,
+        "plugins": context
+            .plugins
+            .iter()
+            .map(|p| plugins::to_json(p.as_ref()))
+            .collect::<Vec<_>>(),
+        "commands": commands.iter().map(command_to_json).collect::<Vec<_>>(),
+        "rules": rules.iter().map(rule_to_json).collect::<Vec<_>>(),
+        "edges": edges,
+    })
+}
+
+fn command_to_json(command: &CommandInfo) -> serde_json::Value {
+    json!({
+        "name": command.name,
+        "help": command.help,
+        "aliases": command.aliases,
+        "hidden": command.hidden,
+        "category": command.category,
+        "deprecated": command.deprecated,
+        "default": command.default,
+        "timeout": command.timeout,
+        "retries": command.retries,
+        "retry_delay": command.retry_delay,
+        "options": options(command).into_iter().map(option_to_json).collect::<Vec<_>>(),
+        "arguments": command.arguments.iter().map(argument_to_json).collect::<Vec<_>>(),
+        "inputs": command.inputs,
+        "outputs": command.outputs,
+        "deps": command.deps,
+        "finally": command.finally,
+    })
+}
+
+fn rule_to_json(rule: &RuleInfo) -> serde_json::Value {
+    json!({
+        "pattern": rule.pattern,
+        "inputs": rule.inputs,
+        "deps": rule.deps,
+        "help": rule.help,
+    })
+}
+
+fn option_to_json(option: &OptionInfo) -> serde_json::Value {
+    json!({
+        "name": option.name,
+        "short": option.short.map(String::from),
+        "type": option.class.name(),
+        "default": option.default,
+        "required": option.required,
+        "var": variable(&option.name, &option.variable),
+        "help": option.help,
+    })
+}
+
+fn argument_to_json(argument: &ArgumentInfo) -> serde_json::Value {
+    json!({
+        "name": argument.name,
+        "type": argument.class.name(),
+        "default": argument.default,
+        "required": argument.required,
+        "var": variable(&argument.name, &argument.variable),
+        "help": argument.help,
+    })
+}
+
+/// Name of the python function keyword argument.
+fn variable(name: &str, variable: &Option<String>) -> String {
+    match variable {
+        Some(v) => v.clone(),
+        None => name.replace('-', "_"),
+    }
+}
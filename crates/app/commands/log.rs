@@ -0,0 +1,240 @@
+use crate::commands::{Context, JSON, age, duration, json, print_json};
+use crate::style::Style;
+use sdk::{Error, ErrorKind, LogRun, LogStatus, Logs};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const NAME: &str = "log";
+
+const TARGET: &str = "target";
+const RUN: &str = "run";
+
+pub fn command() -> clap::Command {
+    clap::Command::new(NAME)
+        .about("Show recent runs and replay the captured output of their targets")
+        .after_help(
+            "Without arguments the recent runs are listed, '--run' lists the targets of the run",
+        )
+        .arg(clap::Arg::new(TARGET).help("Print the output of the target from the latest run"))
+        .arg(
+            clap::Arg::new(RUN)
+                .long(RUN)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .help("Take the run with the given ID"),
+        )
+        .arg(json())
+}
+
+pub fn run(matches: &clap::ArgMatches, context: &Context) -> sdk::Result {
+    let (workspace, _) = context.workspace()?;
+    let keep = context.config.map(|c| c.logs.keep).unwrap_or_default();
+    let logs = Logs::new(workspace.logs(), keep);
+    let history = logs.history()?;
+    let run = match matches.get_one::<u64>(RUN) {
+        None => None,
+        Some(id) => match history.iter().find(|r| r.id == *id) {
+            Some(run) => Some(run),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Generic,
+                    format!("Run not found in the logs: {}", id),
+                ));
+            }
+        },
+    };
+    let json = matches.get_flag(JSON);
+    match (matches.get_one::<String>(TARGET), run) {
+        (Some(target), run) => replay(&logs, &history, run, target),
+        (None, Some(run)) => targets(&logs, run, json),
+        (None, None) => runs(&history, json),
+    }
+}
+
+/// Width of the status column.
+const STATUS: usize = 11;
+
+fn status(style: &Style, status: LogStatus) -> String {
+    match status {
+        LogStatus::Succeeded => style.accent(status.name()),
+        LogStatus::Restored => style.dim(status.name()),
+        LogStatus::Failed => style.error(status.name()),
+        LogStatus::Interrupted => style.warning(status.name()),
+    }
+}
+
+/// The status column, padding is added after the colors.
+fn column(style: &Style, value: LogStatus) -> String {
+    let padding = STATUS.saturating_sub(value.name().len());
+    format!("{}{}", status(style, value), " ".repeat(padding))
+}
+
+//////////////////////////////////////////////////////////////////
+// Runs and targets
+//////////////////////////////////////////////////////////////////
+
+/// Recent runs, the latest first.
+fn runs(history: &[LogRun], json: bool) -> sdk::Result {
+    if json {
+        return print_json(&serde_json::to_value(history)?);
+    }
+    let style = Style::stdout();
+    if history.is_empty() {
+        println!("{}", style.dim("No runs recorded"));
+        return Ok(());
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let rows: Vec<[String; 3]> = history
+        .iter()
+        .rev()
+        .map(|r| {
+            [
+                r.id.to_string(),
+                age(now.saturating_sub(r.started)),
+                duration(r.duration),
+            ]
+        })
+        .collect();
+    let [id, age, elapsed] = widths(["ID", "AGE", "DURATION"], &rows);
+    let header = format!(
+        "{:>id$}  {:>age$}  {:>elapsed$}  {:STATUS$}  COMMAND",
+        "ID", "AGE", "DURATION", "STATUS"
+    );
+    println!("{}", style.bold(&header));
+    for (run, [number, started, took]) in history.iter().rev().zip(rows) {
+        println!(
+            "{:>id$}  {:>age$}  {:>elapsed$}  {}  {}",
+            number,
+            started,
+            took,
+            column(&style, run.status),
+            run.command.join(" ")
+        );
+    }
+    Ok(())
+}
+
+fn targets(logs: &Logs, run: &LogRun, json: bool) -> sdk::Result {
+    if json {
+        return print_json(&serde_json::to_value(run)?);
+    }
+    let style = Style::stdout();
+    println!(
+        "{} {} ({}, {})",
+        style.bold("Run:"),
+        run.id,
+        status(&style, run.status),
+        duration(run.duration)
+    );
+    println!("{} {}", style.bold("Command:"), run.command.join(" "));
+    if run.targets.is_empty() {
+        println!("{}", style.dim("No targets are run"));
+        return Ok(());
+    }
+    let rows: Vec<[String; 2]> = run
+        .targets
+        .iter()
+        .map(|t| [t.name.clone(), duration(t.duration)])
+        .collect();
+    let [name, elapsed] = widths(["TARGET", "DURATION"], &rows);
+    println!();
+    let header = format!(
+        "{:name$}  {:>elapsed$}  {:STATUS$}  LOG",
+        "TARGET", "DURATION", "STATUS"
+    );
+    println!("{}", style.bold(&header));
+    for (target, [title, took]) in run.targets.iter().zip(rows) {
+        let log = logs.file(run, target);
+        println!(
+            "{:name$}  {:>elapsed$}  {}  {}",
+            title,
+            took,
+            column(&style, target.status),
+            log.map(|l| l.display().to_string())
+                .unwrap_or_else(|| "-".into())
+        );
+    }
+    Ok(())
+}
+
+/// Widths of the table columns: the longest value or the header.
+fn widths<const N: usize>(headers: [&str; N], rows: &[[String; N]]) -> [usize; N] {
+    let mut result = headers.map(str::len);
+    for row in rows {
+        for (width, value) in result.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    result
+}
+
+//////////////////////////////////////////////////////////////////
+// Replay
+//////////////////////////////////////////////////////////////////
+
+/// Prints the captured output of the target. Without the run the latest
+/// one which has run the target is taken, restored targets are skipped.
+fn replay(logs: &Logs, history: &[LogRun], run: Option<&LogRun>, target: &str) -> sdk::Result {
+    let runs: Vec<&LogRun> = match run {
+        Some(run) => vec![run],
+        None => history.iter().rev().collect(),
+    };
+    let found = runs.iter().find_map(|run| {
+        let entry = run
+            .targets
+            .iter()
+            .rev()
+            .find(|t| t.name == target && t.log.is_some())?;
+        Some((run, entry))
+    });
+    let Some((run, entry)) = found else {
+        let place = match run {
+            Some(run) => format!("run {}", run.id),
+            None => String::from("recent runs"),
+        };
+        return Err(Error::new(
+            ErrorKind::Generic,
+            format!("No output of '{}' in the {}", target, place),
+        ));
+    };
+    let file = logs.file(run, entry).unwrap_or_default();
+    let data = std::fs::read(&file).map_err(|e| {
+        Error::new(
+            ErrorKind::Generic,
+            format!("Failed to read log '{}': {}", file.display(), e),
+        )
+    })?;
+    let style = Style::stderr();
+    eprintln!(
+        "{}",
+        style.dim(&format!(
+            "Run {}, '{}' {} in {}",
+            run.id,
+            entry.name,
+            entry.status.name(),
+            duration(entry.duration)
+        ))
+    );
+    match std::io::stdout().lock().write_all(&data) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths_fit_values_and_headers() {
+        let rows = [
+            [String::from("12345"), String::from("1.2s")],
+            [String::from("7"), String::from("1h 02m 03s")],
+        ];
+        assert_eq!(widths(["ID", "DURATION"], &rows), [5, 10]);
+        assert_eq!(widths(["TARGET", "DURATION"], &[]), [6, 8]);
+    }
+}
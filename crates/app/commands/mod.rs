@@ -0,0 +1,128 @@
+//////////////////////////////////////////////////////////////////
+// Mod
+//////////////////////////////////////////////////////////////////
+
+mod cache;
+mod describe;
+mod log;
+mod plugins;
+mod watch;
+
+//////////////////////////////////////////////////////////////////
+// Builtin commands
+//////////////////////////////////////////////////////////////////
+
+use crate::config::Config;
+use crate::interpreter::Interpreter;
+use crate::plugins::Plugins;
+use crate::workspace::Workspace;
+use std::io::Write;
+
+/// Everything builtin commands may need to run. The 'missing' is
+/// the reason of the workspace absence.
+pub struct Context<'a> {
+    pub plugins: &'a Plugins,
+    pub interpreter: &'a Interpreter,
+    pub workspace: Option<&'a Workspace>,
+    pub config: Option<&'a Config>,
+    pub cache: Option<&'a sdk::Cache>,
+    pub missing: Option<sdk::Error>,
+}
+
+impl<'a> Context<'a> {
+    /// Returns the workspace and its cache or the reason of their absence.
+    pub fn workspace(&self) -> sdk::Result<(&'a Workspace, &'a sdk::Cache)> {
+        match (self.workspace, self.cache) {
+            (Some(workspace), Some(cache)) => Ok((workspace, cache)),
+            _ => Err(match &self.missing {
+                Some(e) => sdk::Error::new(e.kind(), e.message()),
+                None => sdk::Error::new(sdk::ErrorKind::WorkspaceNotFound, "Workspace not found"),
+            }),
+        }
+    }
+}
+
+/// Builtin commands which are called before the workspace script
+/// is loaded, so they see the cache as it was left by previous runs.
+pub const STANDALONE: &[&str] = &[cache::NAME, log::NAME];
+
+/// Subcommands which are provided by the application itself.
+pub fn all() -> Vec<clap::Command> {
+    vec![
+        cache::command(),
+        describe::command(),
+        log::command(),
+        plugins::command(),
+        watch::command(),
+    ]
+}
+
+/// Runs the builtin command, returns 'None' if the name is unknown.
+pub fn call(name: &str, matches: &clap::ArgMatches, context: &Context) -> Option<sdk::Result> {
+    match name {
+        cache::NAME => Some(cache::run(matches, context)),
+        describe::NAME => Some(describe::run(matches, context)),
+        log::NAME => Some(log::run(matches, context)),
+        plugins::NAME => Some(plugins::run(matches, context)),
+        watch::NAME => Some(watch::run(matches, context)),
+        _ => None,
+    }
+}
+
+/// Common '--json' flag of the commands which print reports.
+const JSON: &str = "json";
+
+fn json() -> clap::Arg {
+    clap::Arg::new(JSON)
+        .long(JSON)
+        .action(clap::ArgAction::SetTrue)
+        .help("Print the output as JSON")
+}
+
+/// JSON is usually piped to other tools, which may close the pipe early.
+fn print_json(value: &serde_json::Value) -> sdk::Result {
+    let text = serde_json::to_string_pretty(value)?;
+    print(|out| writeln!(out, "{}", text))
+}
+
+/// Writes the report to the locked stdout. The closed pipe (e.g. by 'head')
+/// is not an error, the rest of the report is just not needed.
+fn print(write: impl FnOnce(&mut dyn Write) -> std::io::Result<()>) -> sdk::Result {
+    match write(&mut std::io::stdout().lock()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Formatting
+//////////////////////////////////////////////////////////////////
+
+/// Size in bytes with the binary unit, e.g. '1.5 MiB'.
+fn size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1048576..1073741824 => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1073741824.0),
+    }
+}
+
+/// Elapsed time in its largest whole unit, e.g. '3h'.
+fn age(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+/// Duration of a run or a target, e.g. '2m05s'.
+fn duration(seconds: f64) -> String {
+    match seconds {
+        s if s < 60.0 => format!("{:.1}s", s),
+        s if s < 3600.0 => format!("{}m{:02}s", s as u64 / 60, s as u64 % 60),
+        s => format!("{}h{:02}m", s as u64 / 3600, s as u64 % 3600 / 60),
+    }
+}
@@ -0,0 +1,317 @@
+use crate::commands::{Context, JSON, json, print, print_json};
+use crate::style::Style;
+use plugin::Interface;
+use rustpython_vm::builtins::{PyModule, PyType};
+use rustpython_vm::{PyObjectRef, PyResult, VirtualMachine};
+use sdk::{Error, ErrorKind};
+use serde_json::json;
+
+pub const NAME: &str = "plugins";
+
+const LIST: &str = "list";
+const INFO: &str = "info";
+const EXAMPLES: &str = "examples";
+const PLUGIN: &str = "plugin";
+
+pub fn command() -> clap::Command {
+    let plugin = || {
+        clap::Arg::new(PLUGIN)
+            .required(true)
+            .help("Plugin name (case insensitive)")
+    };
+    clap::Command::new(NAME)
+        .about("Inspect loaded plugins")
+        .arg_required_else_help(true)
+        .disable_help_subcommand(true)
+        .subcommand(
+            clap::Command::new(LIST)
+                .about("List loaded plugins")
+                .arg(json()),
+        )
+        .subcommand(
+            clap::Command::new(INFO)
+                .about("Show functions and classes registered by the plugin")
+                .arg(plugin())
+                .arg(json()),
+        )
+        .subcommand(
+            clap::Command::new(EXAMPLES)
+                .about("Print usage examples of the plugin")
+                .arg(plugin())
+                .arg(json()),
+        )
+}
+
+pub fn run(matches: &clap::ArgMatches, context: &Context) -> sdk::Result {
+    let Some((name, matches)) = matches.subcommand() else {
+        return Ok(());
+    };
+    let json = matches.get_flag(JSON);
+    let plugin = || {
+        let name = matches.get_one::<String>(PLUGIN).unwrap();
+        find(context, name)
+    };
+    match name {
+        LIST => list(context, json),
+        INFO => info(plugin()?, context, json),
+        EXAMPLES => examples(plugin()?, json),
+        _ => unreachable!("Unreachable subcommand: {}", name),
+    }
+}
+
+fn find<'a>(context: &'a Context, name: &str) -> sdk::Result<&'a dyn Interface> {
+    context
+        .plugins
+        .iter()
+        .find(|p| p.info().name.eq_ignore_ascii_case(name))
+        .map(|p| p.as_ref())
+        .ok_or_else(|| Error::new(ErrorKind::Plugin, format!("Plugin not found: '{}'", name)))
+}
+
+//////////////////////////////////////////////////////////////////
+// List
+//////////////////////////////////////////////////////////////////
+
+fn list(context: &Context, json: bool) -> sdk::Result {
+    if json {
+        let value: Vec<serde_json::Value> = context
+            .plugins
+            .iter()
+            .map(|p| to_json(p.as_ref()))
+            .collect();
+        return print_json(&json!(value));
+    }
+    let infos: Vec<plugin::Info> = context.plugins.iter().map(|p| p.info()).collect();
+    let style = Style::stdout();
+    let name = infos.iter().map(|i| i.name.len()).max().unwrap_or(0).max(4);
+    let version = infos
+        .iter()
+        .map(|i| i.version.len())
+        .max()
+        .unwrap_or(0)
+        .max(7);
+    let header = format!("{:name$}  {:version$}  DESCRIPTION", "NAME", "VERSION");
+    print(|out| {
+        writeln!(out, "{}", style.bold(&header))?;
+        for info in infos.iter() {
+            writeln!(
+                out,
+                "{}  {:version$}  {}",
+                style.accent(&format!("{:name$}", info.name)),
+                info.version,
+                info.description.lines().next().unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    })
+}
+
+pub fn to_json(plugin: &dyn Interface) -> serde_json::Value {
+    let info = plugin.info();
+    json!({
+        "name": info.name,
+        "version": info.version,
+        "description": info.description,
+        "dependencies": plugin.dependencies(),
+    })
+}
+
+//////////////////////////////////////////////////////////////////
+// Info
+//////////////////////////////////////////////////////////////////
+
+/// Function or class which the plugin exposes to python.
+struct Member {
+    name: String,
+    class: bool,
+    signature: String,
+    doc: Option<String>,
+}
+
+impl Member {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "name": self.name,
+            "kind": if self.class { "class" } else { "function" },
+            "signature": self.signature,
+            "doc": self.doc,
+        })
+    }
+}
+
+fn info(plugin: &dyn Interface, context: &Context, json: bool) -> sdk::Result {
+    let info = plugin.info();
+    let members = context.interpreter.exec(|vm| members(&info, vm))?;
+    if json {
+        let mut value = to_json(plugin);
+        value["members"] = members.iter().map(Member::to_json).collect();
+        return print_json(&value);
+    }
+    let style = Style::stdout();
+    let dependencies = plugin.dependencies();
+    print(|out| {
+        writeln!(out, "{} {}", style.accent(&info.name), info.version)?;
+        if !info.description.is_empty() {
+            writeln!(out, "{}", info.description)?;
+        }
+        if !dependencies.is_empty() {
+            writeln!(
+                out,
+                "{} {}",
+                style.bold("Requires:"),
+                dependencies.join(", ")
+            )?;
+        }
+        for (title, class) in [("Functions", false), ("Classes", true)] {
+            let members: Vec<&Member> = members.iter().filter(|m| m.class == class).collect();
+            if members.is_empty() {
+                continue;
+            }
+            writeln!(out)?;
+            writeln!(out, "{}", style.bold(&format!("{}:", title)))?;
+            for member in members {
+                writeln!(out, "  {}{}", style.bold(&member.name), member.signature)?;
+                if let Some(doc) = &member.doc {
+                    writeln!(out, "      {}", style.dim(doc))?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Plugin python module is expected to be 'umk.<lowercase plugin name>'.
+/// Public callables of the module are reported, submodules are skipped.
+fn members(info: &plugin::Info, vm: &VirtualMachine) -> PyResult<Vec<Member>> {
+    let kit = vm.import(global::kit::NAME, 0)?;
+    let name = vm.ctx.new_str(info.name.to_lowercase());
+    let Some(module) = vm.get_attribute_opt(kit, &name)? else {
+        return Ok(Vec::new());
+    };
+    let names = vm
+        .builtins
+        .get_attr("dir", vm)?
+        .call((module.clone(),), vm)?;
+    let names = vm.extract_elements_with(&names, |n| n.str(vm).map(|s| s.to_string()))?;
+    let inspect = vm.import("inspect", 0)?;
+    let mut result = Vec::new();
+    for name in names.into_iter().filter(|n| !n.starts_with('_')) {
+        let object = module.get_attr(&vm.ctx.new_str(name.as_str()), vm)?;
+        if object.downcast_ref::<PyModule>().is_some() || !object.is_callable() {
+            continue;
+        }
+        result.push(Member {
+            class: object.downcast_ref::<PyType>().is_some(),
+            signature: signature(&inspect, &object, vm),
+            doc: doc(&object, vm),
+            name,
+        });
+    }
+    Ok(result)
+}
+
+/// Native functions have no signature if they are declared without
+/// the '__text_signature__', such functions are shown with '(...)'.
+fn signature(inspect: &PyObjectRef, object: &PyObjectRef, vm: &VirtualMachine) -> String {
+    vm.call_method(inspect, "signature", (object.clone(),))
+        .and_then(|s| s.str(vm))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|_| String::from("(...)"))
+}
+
+fn doc(object: &PyObjectRef, vm: &VirtualMachine) -> Option<String> {
+    let doc = object.get_attr("__doc__", vm).ok()?;
+    if vm.is_none(&doc) {
+        return None;
+    }
+    let doc = doc.str(vm).ok()?;
+    // Native functions docs may start with the text signature header.
+    let doc = match doc.as_str().split_once("\n--\n\n") {
+        Some((_, doc)) => doc,
+        None => doc.as_str(),
+    };
+    doc.lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(String::from)
+}
+
+//////////////////////////////////////////////////////////////////
+// Examples
+//////////////////////////////////////////////////////////////////
+
+fn examples(plugin: &dyn Interface, json: bool) -> sdk::Result {
+    let info = plugin.info();
+    let examples = plugin.examples();
+    if json {
+        return print_json(&examples_to_json(&info, &examples));
+    }
+    if examples.is_empty() {
+        eprintln!("Plugin '{}' has no examples", info.name);
+        return Ok(());
+    }
+    let style = Style::stdout();
+    print(|out| {
+        for (index, example) in examples.iter().enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", style.dim(&format!("# Example {}", index + 1)))?;
+            writeln!(out, "{}", example.trim_end())?;
+        }
+        Ok(())
+    })
+}
+
+fn examples_to_json(info: &plugin::Info, examples: &[String]) -> serde_json::Value {
+    json!({ "name": info.name, "examples": examples })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(value: &serde_json::Value) -> Vec<&str> {
+        let mut result: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn plugin_json() {
+        let value = to_json(&plugin_template::Instance::default());
+        assert_eq!(
+            keys(&value),
+            ["dependencies", "description", "name", "version"]
+        );
+        assert_eq!(value["name"], "Template");
+        assert!(value["dependencies"].is_array());
+    }
+
+    #[test]
+    fn member_json() {
+        let member = Member {
+            name: String::from("render"),
+            class: false,
+            signature: String::from("(text, /, **variables)"),
+            doc: None,
+        };
+        let value = member.to_json();
+        assert_eq!(keys(&value), ["doc", "kind", "name", "signature"]);
+        assert_eq!(value["kind"], "function");
+        assert!(value["doc"].is_null());
+    }
+
+    #[test]
+    fn examples_json() {
+        let plugin = plugin_template::Instance::default();
+        let value = examples_to_json(&plugin.info(), &plugin.examples());
+        assert_eq!(keys(&value), ["examples", "name"]);
+        assert_eq!(value["examples"].as_array().unwrap().len(), 1);
+    }
+}
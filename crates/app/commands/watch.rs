@@ -0,0 +1,315 @@
+use crate::cli;
+use crate::commands::Context;
+use crate::config;
+use crate::report;
+use crate::style::Style;
+use notify::Watcher;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+pub const NAME: &str = "watch";
+
+const COMMAND: &str = "command";
+const PATTERN: &str = "pattern";
+const DEBOUNCE: &str = "debounce";
+
+/// How often the signals (Ctrl+C) are checked while waiting for changes.
+const POLL: Duration = Duration::from_millis(100);
+
+/// Never trigger the command: the cache and the logs are written by the
+/// command itself.
+const IGNORE: &[&str] = &[
+    ".unimake/.cache/**",
+    ".unimake/logs/**",
+    "**/.git/**",
+    "**/__pycache__/**",
+];
+
+type Events = mpsc::Receiver<notify::Result<notify::Event>>;
+
+pub fn command() -> clap::Command {
+    clap::Command::new(NAME)
+        .about("Run the command and run it again when the watched files change")
+        .long_about(
+            "Run the command and run it again when the watched files change. \
+            The workspace script is reloaded when it changes. \
+            Changes made while the command runs are ignored.",
+        )
+        .arg(
+            clap::Arg::new(PATTERN)
+                .long(PATTERN)
+                .short('p')
+                .value_name("GLOB")
+                .action(clap::ArgAction::Append)
+                .help("Files to watch [default: inputs of the command or the whole workspace]"),
+        )
+        .arg(
+            clap::Arg::new(DEBOUNCE)
+                .long(DEBOUNCE)
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Milliseconds without changes before the command is run again"),
+        )
+        .arg(
+            clap::Arg::new(COMMAND)
+                .required(true)
+                .num_args(1..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)
+                .value_name("COMMAND")
+                .help("Command with its arguments, e.g. 'cli build --release'"),
+        )
+}
+
+pub fn run(matches: &clap::ArgMatches, context: &Context) -> sdk::Result {
+    let (workspace, cache) = context.workspace()?;
+    let args: Vec<String> = matches
+        .get_many::<String>(COMMAND)
+        .unwrap()
+        .cloned()
+        .collect();
+    if args[0] == NAME {
+        return Err(sdk::Error::new(
+            sdk::ErrorKind::Generic,
+            "Watch command can't watch itself",
+        ));
+    }
+    let default = config::Watch::default();
+    let settings = context.config.map(|c| &c.watch).unwrap_or(&default);
+    let patterns: Vec<String> = matches
+        .get_many::<String>(PATTERN)
+        .map(|v| v.cloned().collect())
+        .unwrap_or_default();
+    let debounce = matches
+        .get_one::<u64>(DEBOUNCE)
+        .copied()
+        .unwrap_or(settings.debounce);
+    let debounce = Duration::from_millis(debounce);
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(failed)?;
+    watcher
+        .watch(workspace.root(), notify::RecursiveMode::Recursive)
+        .map_err(failed)?;
+
+    let script = workspace.root().join(global::workspace::FILE);
+    let verbose = cli::verbose();
+    let style = Style::stderr();
+    loop {
+        let interface = cli::Interface::new(context.plugins, Some(cache))?;
+        let target = match interface.parse(&args) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                let _ = e.print();
+                None
+            }
+        };
+        let (inputs, outputs) = match &target {
+            Some(target) => declared(&interface, target, context)?,
+            None => Default::default(),
+        };
+        let include = match (patterns.is_empty(), inputs.is_empty()) {
+            (false, _) => &patterns,
+            (true, false) => &inputs,
+            (true, true) => &settings.patterns,
+        };
+        let exclude = IGNORE
+            .iter()
+            .map(|p| p.to_string())
+            .chain(settings.ignore.iter().cloned())
+            .chain(outputs);
+        let filter = Filter::new(workspace.root(), include, exclude, &script)?;
+
+        if let Some(target) = &target
+            && let Err(e) = interface.dispatch(target, context)
+        {
+            if interrupted(&e) {
+                return Err(e);
+            }
+            report::error(&e, verbose);
+        }
+        context.interpreter.enter(sdk::py::flush);
+        while events.try_recv().is_ok() {}
+
+        let watched = match include.is_empty() {
+            true => "workspace".to_string(),
+            false => include.join(", "),
+        };
+        let message = format!("Watching {} for changes, press Ctrl+C to stop", watched);
+        eprintln!("{}", style.dim(&message));
+        let signals = || {
+            context
+                .interpreter
+                .exec(|vm| vm.check_signals())
+                .map_err(|e| e.wrap(sdk::ErrorKind::Interrupted, "Watch is interrupted"))
+        };
+        let changed = wait(&events, &filter, debounce, signals)?;
+        if changed.contains(&script) {
+            eprintln!("{}", style.dim("Reloading the workspace script"));
+            if let Err(e) = workspace.refresh(context.plugins, context.interpreter, cache) {
+                report::error(&e, verbose);
+            }
+        }
+    }
+}
+
+fn failed(error: notify::Error) -> sdk::Error {
+    sdk::Error::new(
+        sdk::ErrorKind::Generic,
+        format!("Failed to watch the workspace: {}", error),
+    )
+}
+
+/// Inputs and outputs declared by the workspace command.
+fn declared(
+    interface: &cli::Interface,
+    matches: &clap::ArgMatches,
+    context: &Context,
+) -> sdk::Result<(Vec<String>, Vec<String>)> {
+    let Some(name) = interface.workspace_command(matches) else {
+        return Ok(Default::default());
+    };
+    let commands = context
+        .interpreter
+        .exec(sdk::py::service::<plugin_cli::Commands>)?;
+    Ok(commands
+        .and_then(|c| {
+            c.0.get(&name)
+                .map(|c| (c.inputs.clone(), c.outputs.clone()))
+        })
+        .unwrap_or_default())
+}
+
+fn interrupted(error: &sdk::Error) -> bool {
+    error.kind() == sdk::ErrorKind::Interrupted
+        || error.innermost().kind() == sdk::ErrorKind::Interrupted
+}
+
+/// Waits for the change of the matching files and then until there are
+/// no changes during the debounce period. The signals are checked while
+/// waiting, since the interpreter sees them only when it runs the code.
+fn wait(
+    events: &Events,
+    filter: &Filter,
+    debounce: Duration,
+    signals: impl Fn() -> sdk::Result,
+) -> sdk::Result<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    let mut quiet = Duration::ZERO;
+    loop {
+        signals()?;
+        let event = match events.recv_timeout(POLL) {
+            Ok(v) => v,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                quiet += POLL;
+                if !changed.is_empty() && quiet >= debounce {
+                    return Ok(changed);
+                }
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(disconnected()),
+        };
+        let event = event.map_err(failed)?;
+        if event.kind.is_access() {
+            continue;
+        }
+        let before = changed.len();
+        changed.extend(event.paths.into_iter().filter(|p| filter.matches(p)));
+        if changed.len() > before {
+            quiet = Duration::ZERO;
+        }
+    }
+}
+
+fn disconnected() -> sdk::Error {
+    sdk::Error::new(sdk::ErrorKind::Generic, "File watcher has stopped")
+}
+
+//////////////////////////////////////////////////////////////////
+// Filter
+//////////////////////////////////////////////////////////////////
+
+/// Patterns are relative to the workspace root, no include patterns
+/// means the whole workspace. The script is always watched.
+struct Filter {
+    root: PathBuf,
+    script: PathBuf,
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Filter {
+    fn new(
+        root: &Path,
+        include: &[String],
+        exclude: impl Iterator<Item = String>,
+        script: &Path,
+    ) -> sdk::Result<Self> {
+        let compile = |pattern: &str| {
+            glob::Pattern::new(pattern).map_err(|e| {
+                sdk::Error::new(
+                    sdk::ErrorKind::Generic,
+                    format!("Invalid file pattern '{}': {}", pattern, e.msg),
+                )
+            })
+        };
+        Ok(Self {
+            root: root.to_path_buf(),
+            script: script.to_path_buf(),
+            include: include
+                .iter()
+                .map(|p| compile(p))
+                .collect::<Result<_, _>>()?,
+            exclude: exclude.map(|p| compile(&p)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        if path == self.script {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        !self.exclude.iter().any(|p| p.matches_path(relative))
+            && (self.include.is_empty() || self.include.iter().any(|p| p.matches_path(relative)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> Filter {
+        let include: Vec<String> = include.iter().map(|p| p.to_string()).collect();
+        let exclude = IGNORE.iter().chain(exclude).map(|p| p.to_string());
+        let root = Path::new("/ws");
+        Filter::new(root, &include, exclude, &root.join("unimake.py")).unwrap()
+    }
+
+    #[test]
+    fn ignores_own_files() {
+        let filter = filter(&[], &[]);
+        assert!(filter.matches(Path::new("/ws/src/main.c")));
+        assert!(filter.matches(Path::new("/ws/unimake.py")));
+        assert!(filter.matches(Path::new("/ws/.unimake/plugins/tools.py")));
+        assert!(!filter.matches(Path::new("/ws/.unimake/.cache/data.db")));
+        assert!(!filter.matches(Path::new("/ws/.unimake/logs/index.json")));
+        assert!(!filter.matches(Path::new("/ws/.unimake/logs/12/build.log")));
+        assert!(!filter.matches(Path::new("/ws/.git/index")));
+        assert!(!filter.matches(Path::new("/ws/lib/__pycache__/a.pyc")));
+        assert!(!filter.matches(Path::new("/other/main.c")));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let filter = filter(&["src/**/*.c"], &["src/gen/**"]);
+        assert!(filter.matches(Path::new("/ws/src/a/main.c")));
+        assert!(!filter.matches(Path::new("/ws/src/main.h")));
+        assert!(!filter.matches(Path::new("/ws/src/gen/table.c")));
+        // The script is reloaded on changes whatever the patterns are.
+        assert!(filter.matches(Path::new("/ws/unimake.py")));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::cache;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+//////////////////////////////////////////////////////////////////
+// Config
+//////////////////////////////////////////////////////////////////
+
+/// Overrides the 'cache.backend' value of the config file.
+const ENV_CACHE_BACKEND: &str = "UNIMAKE_CACHE_BACKEND";
+
+/// Enables the remote artifacts or overrides their URL.
+const ENV_REMOTE_URL: &str = "UNIMAKE_REMOTE_CACHE_URL";
+
+/// Token sent as 'Authorization: Bearer <token>' to the remote artifacts.
+const ENV_REMOTE_TOKEN: &str = "UNIMAKE_REMOTE_CACHE_TOKEN";
+
+/// Overrides the 'read_only' value of the remote artifacts ('1' or '0').
+const ENV_REMOTE_READ_ONLY: &str = "UNIMAKE_REMOTE_CACHE_READ_ONLY";
+
+/// Prefix of the variables which override the script settings,
+/// e.g. 'UNIMAKE_CONFIG_JOBS=8' sets the 'jobs'.
+const ENV_SETTING_PREFIX: &str = "UNIMAKE_CONFIG_";
+
+/// Application settings from the workspace config file. Every value
+/// is optional, missing file means the default settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub cache: Cache,
+    pub watch: Watch,
+    pub logs: Logs,
+    /// Values of the settings declared by the script ('umk.config').
+    pub settings: toml::Table,
+    /// Named groups of the settings selected by '--profile', they
+    /// override the 'settings' of the same file.
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cache {
+    pub backend: cache::Backend,
+    pub artifacts: Artifacts,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Artifacts {
+    /// Least recently used outputs are removed above this size.
+    pub limit: Size,
+    pub remote: Option<Remote>,
+}
+
+/// Artifacts server shared by the team, CI usually writes to it and
+/// developers only read.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Remote {
+    pub url: String,
+    #[serde(default)]
+    pub read_only: bool,
+    /// Sent with every request, e.g. the authorization.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Seconds to wait for the server.
+    #[serde(default = "Remote::timeout")]
+    pub timeout: u64,
+}
+
+impl Remote {
+    fn timeout() -> u64 {
+        30
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Watch {
+    /// Files to watch if the command has no declared inputs.
+    pub patterns: Vec<String>,
+    /// Files which never trigger the command.
+    pub ignore: Vec<String>,
+    /// Milliseconds without changes before the command is run again.
+    pub debounce: u64,
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            ignore: Vec::new(),
+            debounce: 200,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Logs {
+    /// Number of recent runs whose logs are kept.
+    pub keep: usize,
+}
+
+impl Default for Logs {
+    fn default() -> Self {
+        Self { keep: 20 }
+    }
+}
+
+impl Config {
+    pub fn load(file: &Path) -> sdk::Result<Self> {
+        let failed = |reason: String| {
+            sdk::Error::new(
+                sdk::ErrorKind::Config,
+                format!("Failed to read config '{}': {}", file.display(), reason),
+            )
+        };
+        let mut result: Config = match std::fs::read_to_string(file) {
+            Ok(data) => toml::from_str(&data).map_err(|e| failed(e.message().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(failed(e.to_string())),
+        };
+        if let Ok(name) = std::env::var(ENV_CACHE_BACKEND) {
+            result.cache.backend = cache::Backend::parse(&name).ok_or_else(|| {
+                sdk::Error::new(
+                    sdk::ErrorKind::Config,
+                    format!("Unknown cache backend in {}: '{}'", ENV_CACHE_BACKEND, name),
+                )
+            })?;
+        }
+        result.cache.artifacts.remote = remote(result.cache.artifacts.remote.take())?;
+        Ok(result)
+    }
+}
+
+/// Config of the current user, only its settings and profiles are used.
+pub fn user() -> Option<PathBuf> {
+    let directory = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(v) if !v.is_empty() => PathBuf::from(v),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(directory.join(global::project::NAME).join("config.toml"))
+}
+
+/// Applies environment overrides to the remote artifacts settings.
+fn remote(config: Option<Remote>) -> sdk::Result<Option<Remote>> {
+    let mut result = match (config, std::env::var(ENV_REMOTE_URL)) {
+        (Some(remote), Ok(url)) => Remote { url, ..remote },
+        (None, Ok(url)) => Remote {
+            url,
+            read_only: false,
+            headers: BTreeMap::new(),
+            timeout: Remote::timeout(),
+        },
+        (Some(remote), Err(_)) => remote,
+        (None, Err(_)) => return Ok(None),
+    };
+    if let Ok(token) = std::env::var(ENV_REMOTE_TOKEN) {
+        let authorization = format!("Bearer {}", token.trim());
+        result.headers.insert("Authorization".into(), authorization);
+    }
+    if let Ok(value) = std::env::var(ENV_REMOTE_READ_ONLY) {
+        result.read_only = match value.trim() {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => {
+                return Err(sdk::Error::new(
+                    sdk::ErrorKind::Config,
+                    format!("Invalid {} value: '{}'", ENV_REMOTE_READ_ONLY, value),
+                ));
+            }
+        };
+    }
+    Ok(Some(result))
+}
+
+//////////////////////////////////////////////////////////////////
+// Settings
+//////////////////////////////////////////////////////////////////
+
+/// Collects overrides of the script settings. From the lowest priority:
+/// the workspace config, the user config (each followed by its profile),
+/// environment variables and the '--set KEY=VALUE' options.
+/// The 'files' are configs with their source names.
+pub fn settings(
+    files: &[(&Config, String)],
+    profile: Option<&str>,
+    overrides: &[String],
+) -> sdk::Result<sdk::Settings> {
+    let mut layers = Vec::new();
+    let mut found = false;
+    for (config, source) in files {
+        layers.push(layer(source.clone(), &config.settings));
+        if let Some(name) = profile
+            && let Some(values) = config.profiles.get(name)
+        {
+            layers.push(layer(format!("{} [{}]", source, name), values));
+            found = true;
+        }
+    }
+    if let Some(name) = profile
+        && !found
+    {
+        return Err(sdk::Error::new(
+            sdk::ErrorKind::Config,
+            format!("Profile not found in the config files: '{}'", name),
+        ));
+    }
+
+    let mut variables: Vec<(String, String)> = std::env::vars()
+        .filter(|(k, _)| k.starts_with(ENV_SETTING_PREFIX))
+        .collect();
+    variables.sort();
+    for (variable, value) in variables {
+        let name = variable[ENV_SETTING_PREFIX.len()..].to_lowercase();
+        layers.push(sdk::SettingsLayer {
+            source: variable,
+            values: BTreeMap::from([(name, serde_json::Value::String(value))]),
+        });
+    }
+
+    let mut values = BTreeMap::new();
+    for item in overrides {
+        let Some((name, value)) = item.split_once('=') else {
+            return Err(sdk::Error::new(
+                sdk::ErrorKind::Config,
+                format!("Invalid '--set {}', expected KEY=VALUE", item),
+            ));
+        };
+        values.insert(name.trim().to_string(), value.into());
+    }
+    layers.push(sdk::SettingsLayer {
+        source: String::from("--set"),
+        values,
+    });
+    layers.retain(|l| !l.values.is_empty());
+    Ok(sdk::Settings {
+        profile: profile.map(String::from),
+        layers,
+    })
+}
+
+fn layer(source: String, table: &toml::Table) -> sdk::SettingsLayer {
+    sdk::SettingsLayer {
+        source,
+        values: table
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), serde_json::to_value(v).ok()?)))
+            .collect(),
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Size
+//////////////////////////////////////////////////////////////////
+
+/// Amount of bytes, written as the number or the string with the
+/// unit suffix: "512MiB", "2 GB", "100k".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size(pub u64);
+
+impl Default for Size {
+    fn default() -> Self {
+        Self(1 << 30)
+    }
+}
+
+impl Size {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let split = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: u64 = number.parse().ok()?;
+        let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1000,
+            "m" | "mb" => 1000 * 1000,
+            "g" | "gb" => 1000 * 1000 * 1000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            _ => return None,
+        };
+        number.checked_mul(multiplier).map(Self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(v) => Ok(Self(v)),
+            Raw::Text(v) => Self::parse(&v)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid size '{}'", v))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn config(text: &str) -> Config {
+        toml::from_str(text).unwrap()
+    }
+
+    fn sources(settings: &sdk::Settings) -> Vec<&str> {
+        settings.layers.iter().map(|l| l.source.as_str()).collect()
+    }
+
+    #[test]
+    fn settings_layers() {
+        let workspace = config("[settings]\njobs = 2\nmode = 'debug'\n[profiles.ci]\njobs = 8\n");
+        let user = config("[settings]\njobs = 4\n[profiles.ci]\nmode = 'release'\n");
+        let files = [
+            (&workspace, String::from("workspace")),
+            (&user, String::from("user")),
+        ];
+        let overrides = [String::from("mode=fast")];
+
+        let result = settings(&files, None, &[]).unwrap();
+        assert_eq!(sources(&result), ["workspace", "user"]);
+        assert_eq!(result.find("jobs").unwrap().1, &Value::from(4));
+        assert_eq!(result.find("mode").unwrap().0.source, "workspace");
+
+        let result = settings(&files, Some("ci"), &overrides).unwrap();
+        assert_eq!(
+            sources(&result),
+            ["workspace", "workspace [ci]", "user", "user [ci]", "--set"]
+        );
+        // The user config goes after the workspace profile.
+        assert_eq!(result.find("jobs").unwrap().1, &Value::from(4));
+        assert_eq!(result.find("mode").unwrap().1, &Value::from("fast"));
+        assert_eq!(result.profile.as_deref(), Some("ci"));
+    }
+
+    #[test]
+    fn settings_errors() {
+        let workspace = config("[settings]\njobs = 2\n");
+        let files = [(&workspace, String::from("workspace"))];
+        let error = settings(&files, Some("ci"), &[]).unwrap_err();
+        assert_eq!(error.kind(), sdk::ErrorKind::Config);
+        assert_eq!(
+            error.message(),
+            "Profile not found in the config files: 'ci'"
+        );
+        let error = settings(&files, None, &[String::from("jobs")]).unwrap_err();
+        assert_eq!(error.kind(), sdk::ErrorKind::Config);
+    }
+
+    #[test]
+    fn size_units() {
+        assert_eq!(Size::parse("512"), Some(Size(512)));
+        assert_eq!(Size::parse("100k"), Some(Size(100_000)));
+        assert_eq!(Size::parse(" 2 GB "), Some(Size(2_000_000_000)));
+        assert_eq!(Size::parse("512MiB"), Some(Size(512 << 20)));
+        assert_eq!(Size::parse("1gib"), Some(Size(1 << 30)));
+        assert_eq!(Size::parse("1.5GB"), None);
+        assert_eq!(Size::parse("GB"), None);
+        assert_eq!(Size::parse("10 parsecs"), None);
+        assert_eq!(Size::parse("99999999999999 GiB"), None);
+    }
+}
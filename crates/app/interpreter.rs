@@ -1,20 +1,88 @@
+use crate::plugins::Plugins;
+use ahash::HashMapExt;
+use rustpython::InterpreterConfig;
 use rustpython::vm::Interpreter as PyInterpreter;
 use rustpython::vm::{PyResult, VirtualMachine};
-use sdk;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::class::PyClassImpl;
+use rustpython_vm::convert::ToPyObject;
+use rustpython_vm::stdlib::StdlibMap;
+use rustpython_vm::{PyRef, pymodule};
+
+/// Category of the timings recorded while the application starts.
+pub const PHASE: &str = "phase";
 
 pub struct Interpreter {
     inner: PyInterpreter,
 }
 
+impl Interpreter {
+    pub fn new(plugins: Plugins, timings: &sdk::Timings) -> sdk::Result<Self> {
+        let kit = {
+            let plugins = plugins.clone();
+            let package = move |vm: &VirtualMachine| -> PyRef<PyModule> {
+                let framework = module::make_module(vm);
+                sdk::py::register_errors(vm, &framework);
+                plugins.iter().for_each(|p| p.register(&framework, vm));
+                framework
+            };
+            let mut result = StdlibMap::new();
+            result.insert(global::kit::NAME.into(), Box::new(package));
+            result
+        };
+
+        let (interrupter, signals) = rustpython_vm::signal::user_signal_channel();
+        let result = InterpreterConfig::new()
+            .init_stdlib()
+            .init_hook(Box::new(|vm| {
+                vm.add_native_modules(kit);
+                vm.set_user_signal_channel(signals);
+            }))
+            .interpreter();
+
+        // The container must exist before plugins initialization,
+        // since python plugins are executed there.
+        result.enter(|vm| -> sdk::Result {
+            sdk::py::Container::make_class(&vm.ctx);
+            let _ = vm.builtins.set_attr(
+                global::kit::CONTAINER,
+                sdk::py::Container::default().to_pyobject(vm),
+                vm,
+            );
+            sdk::py::provide(vm, sdk::Interrupter::new(interrupter))
+                .map_err(|e| sdk::Error::python(e, vm))?;
+            sdk::py::provide(vm, timings.clone()).map_err(|e| sdk::Error::python(e, vm))?;
+            terminate(vm).map_err(|e| sdk::Error::python(e, vm))?;
+            for plugin in plugins.iter() {
+                let name = format!("initialize {}", plugin.info().name);
+                timings.measure(&name, PHASE, || plugin.initialize(vm))?;
+            }
+            Ok(())
+        })?;
+
+        Ok(result.into())
+    }
+}
+
 impl Interpreter {
     pub fn exec<F, P>(&self, f: F) -> sdk::Result<P>
     where
         F: FnOnce(&VirtualMachine) -> PyResult<P>,
     {
-        match self.inner.enter(f) {
-            Ok(v) => Ok(v),
-            Err(e) => Err(e.into()),
-        }
+        self.inner
+            .enter(|vm| f(vm).map_err(|e| sdk::Error::python(e, vm)))
+    }
+
+    pub fn enter<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&VirtualMachine) -> R,
+    {
+        self.inner.enter(f)
+    }
+
+    /// Flushes python standard streams and runs 'atexit' functions.
+    pub fn finalize(self) {
+        self.inner.finalize(None);
     }
 }
 
@@ -23,3 +91,16 @@ impl From<PyInterpreter> for Interpreter {
         Self { inner: value }
     }
 }
+
+/// The SIGTERM interrupts the python code like the SIGINT (Ctrl+C) does,
+/// so commands are cancelled the same way.
+fn terminate(vm: &VirtualMachine) -> PyResult<()> {
+    let signal = vm.import("signal", 0)?;
+    let number = signal.get_attr("SIGTERM", vm)?;
+    let handler = signal.get_attr("default_int_handler", vm)?;
+    vm.call_method(&signal, "signal", (number, handler))?;
+    Ok(())
+}
+
+#[pymodule(name = "umk")]
+mod module {}
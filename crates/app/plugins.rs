@@ -0,0 +1,627 @@
+use libloading::{Library, Symbol};
+use plugin::Interface;
+use rustpython_parser::Mode;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::function::{FuncArgs, KwArgs};
+use rustpython_vm::{PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::{Error, ErrorKind};
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+pub type Plugins = Arc<Vec<Box<dyn Interface>>>;
+
+//////////////////////////////////////////////////////////////////
+// Loading
+//////////////////////////////////////////////////////////////////
+
+/// Plugins compiled into the application.
+fn builtin() -> Vec<Box<dyn Interface>> {
+    vec![
+        Box::new(plugin_cli::Instance::default()),
+        Box::new(plugin_config::Instance::default()),
+        Box::new(plugin_process::Instance::default()),
+        Box::new(plugin_fs::Instance::default()),
+        Box::new(plugin_project::Instance::default()),
+        Box::new(plugin_template::Instance::default()),
+    ]
+}
+
+/// Collects builtin plugins and plugins discovered in the workspace
+/// plugins directory (if any).
+pub fn load(directory: Option<&Path>) -> sdk::Result<Plugins> {
+    let mut result = builtin();
+    if let Some(directory) = directory {
+        result.extend(discover(directory)?);
+    }
+    let mut names = Vec::with_capacity(result.len());
+    for plugin in result.iter() {
+        let name = plugin.info().name.to_lowercase();
+        if names.contains(&name) {
+            let msg = format!("Plugin '{}' is declared more than once", name);
+            return Err(Error::new(ErrorKind::Plugin, msg));
+        }
+        names.push(name);
+    }
+    Ok(Arc::new(sort(result)?))
+}
+
+/// Orders plugins so that each one follows its dependencies. Independent
+/// plugins keep the discovery order (builtin plugins go first).
+fn sort(plugins: Vec<Box<dyn Interface>>) -> sdk::Result<Vec<Box<dyn Interface>>> {
+    let name = |p: &dyn Interface| p.info().name.to_lowercase();
+    let names: Vec<String> = plugins.iter().map(|p| name(p.as_ref())).collect();
+    let mut dependencies = Vec::with_capacity(plugins.len());
+    for (index, plugin) in plugins.iter().enumerate() {
+        let mut indices = Vec::new();
+        for dependency in plugin.dependencies() {
+            match names
+                .iter()
+                .position(|n| n.eq_ignore_ascii_case(&dependency))
+            {
+                Some(i) => indices.push(i),
+                None => {
+                    let msg = format!(
+                        "Plugin '{}' depends on unknown plugin '{}'",
+                        names[index], dependency
+                    );
+                    return Err(Error::new(ErrorKind::Plugin, msg));
+                }
+            }
+        }
+        dependencies.push(indices);
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(plugins.len());
+    while order.len() < plugins.len() {
+        let next = (0..plugins.len())
+            .find(|i| !order.contains(i) && dependencies[*i].iter().all(|d| order.contains(d)));
+        match next {
+            Some(index) => order.push(index),
+            None => {
+                let cycle = cycle(&dependencies, &order)
+                    .iter()
+                    .map(|&i| names[i].as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" -> ");
+                let msg = format!("Plugin dependency cycle: {}", cycle);
+                return Err(Error::new(ErrorKind::Plugin, msg));
+            }
+        }
+    }
+
+    let mut plugins: Vec<Option<Box<dyn Interface>>> = plugins.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .map(|i| plugins[i].take().unwrap())
+        .collect())
+}
+
+/// Walks dependencies of the first unordered plugin until some plugin is
+/// visited twice, everything starting from its first visit is the cycle.
+fn cycle(dependencies: &[Vec<usize>], ordered: &[usize]) -> Vec<usize> {
+    let pending = |i: &usize| !ordered.contains(i);
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = (0..dependencies.len()).find(pending).unwrap();
+    while !path.contains(&current) {
+        path.push(current);
+        current = *dependencies[current].iter().find(|d| pending(d)).unwrap();
+    }
+    let start = path.iter().position(|&i| i == current).unwrap();
+    let mut result = path.split_off(start);
+    result.push(current);
+    result
+}
+
+/// Shared libraries and python modules (single files or packages) are
+/// treated as plugins. Entries are sorted to make the order stable.
+fn discover(directory: &Path) -> sdk::Result<Vec<Box<dyn Interface>>> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+    let mut result: Vec<Box<dyn Interface>> = Vec::new();
+    for path in entries {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        if stem.starts_with(['.', '_']) {
+            continue;
+        }
+        if extension == std::env::consts::DLL_EXTENSION {
+            result.push(Box::new(Native::load(&path)?));
+        } else if extension == "py" {
+            result.push(Box::new(Python::new(stem, path.clone(), None)?));
+        } else if path.join("__init__.py").is_file() {
+            let file = path.join("__init__.py");
+            result.push(Box::new(Python::new(stem, file, Some(path.clone()))?));
+        }
+    }
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Hooks
+//////////////////////////////////////////////////////////////////
+
+/// Calls the hook of each plugin in the dependency order,
+/// stops on the first error.
+pub fn forward<F>(plugins: &Plugins, vm: &VirtualMachine, hook: F) -> sdk::Result
+where
+    F: Fn(&dyn Interface, &VirtualMachine) -> sdk::Result,
+{
+    plugins.iter().try_for_each(|p| hook(p.as_ref(), vm))
+}
+
+/// Calls the hook of each plugin in the reverse dependency order, so
+/// dependents are finished first. All plugins are called, the first
+/// error is returned.
+pub fn backward<F>(plugins: &Plugins, vm: &VirtualMachine, hook: F) -> sdk::Result
+where
+    F: Fn(&dyn Interface, &VirtualMachine) -> sdk::Result,
+{
+    let mut result = Ok(());
+    for plugin in plugins.iter().rev() {
+        let current = hook(plugin.as_ref(), vm);
+        if result.is_ok() {
+            result = current;
+        }
+    }
+    result
+}
+
+//////////////////////////////////////////////////////////////////
+// Shared library plugin
+//////////////////////////////////////////////////////////////////
+
+struct Native {
+    // The instance must be dropped before the library is unloaded,
+    // fields are dropped in declaration order.
+    instance: Box<dyn Interface>,
+    _library: Library,
+}
+
+impl Native {
+    fn load(path: &Path) -> sdk::Result<Self> {
+        let failed = |reason: String| {
+            Error::new(
+                ErrorKind::Plugin,
+                format!("Failed to load plugin '{}': {}", path.display(), reason),
+            )
+        };
+        // Safety: loading a library runs its initializers, plugins are
+        // trusted the same way as the workspace scripts are.
+        let library = unsafe { Library::new(path) }.map_err(|e| failed(e.to_string()))?;
+        let declaration = unsafe {
+            let entry: Symbol<plugin::EntryPoint> = library
+                .get(plugin::ENTRY_POINT)
+                .map_err(|e| failed(e.to_string()))?;
+            entry()
+        };
+        if declaration.abi != plugin::ABI_VERSION {
+            return Err(failed(format!(
+                "ABI version {} is not supported, expected {}",
+                declaration.abi,
+                plugin::ABI_VERSION
+            )));
+        }
+        let version = unsafe { CStr::from_ptr(declaration.version) }.to_string_lossy();
+        if version != plugin::VERSION {
+            return Err(failed(format!(
+                "it is built against plugin API {}, expected {}",
+                version,
+                plugin::VERSION
+            )));
+        }
+        let instance = unsafe { *Box::from_raw((declaration.create)()) };
+        Ok(Self {
+            instance,
+            _library: library,
+        })
+    }
+}
+
+impl Interface for Native {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.initialize(vm)
+    }
+
+    fn info(&self) -> plugin::Info {
+        self.instance.info()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.instance.dependencies()
+    }
+
+    fn examples(&self) -> Vec<String> {
+        self.instance.examples()
+    }
+
+    fn cli(&self, cache: &sdk::Cache) -> sdk::Result<Option<clap::Command>> {
+        self.instance.cli(cache)
+    }
+
+    fn cache(&self, cache: &sdk::Cache, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.cache(cache, vm)
+    }
+
+    fn call(&self, matches: &clap::ArgMatches, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.call(matches, vm)
+    }
+
+    fn register(&self, module: &PyRef<PyModule>, vm: &VirtualMachine) {
+        self.instance.register(module, vm)
+    }
+
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.before_load(vm)
+    }
+
+    fn after_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.after_load(vm)
+    }
+
+    fn before_command(&self, command: &[String], vm: &VirtualMachine) -> sdk::Result {
+        self.instance.before_command(command, vm)
+    }
+
+    fn after_command(
+        &self,
+        command: &[String],
+        result: &sdk::Result,
+        vm: &VirtualMachine,
+    ) -> sdk::Result {
+        self.instance.after_command(command, result, vm)
+    }
+
+    fn on_exit(&self, result: &sdk::Result, vm: &VirtualMachine) -> sdk::Result {
+        self.instance.on_exit(result, vm)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Python plugin
+//////////////////////////////////////////////////////////////////
+
+/// Python plugins API version, it does not depend on the native plugins ABI.
+const PYTHON_ABI_VERSION: u32 = 1;
+
+/// Optional module attribute, the plugin is rejected if it differs from the 'PYTHON_ABI_VERSION'.
+const PYTHON_ABI: &str = "__unimake_abi__";
+
+/// Optional module attribute, literal list of plugin names the module depends on.
+/// It is read from the source, since the order must be known before the import.
+const PYTHON_REQUIRES: &str = "__requires__";
+
+/// Optional module attribute, list of example snippets.
+const PYTHON_EXAMPLES: &str = "__examples__";
+
+/// Python module (or package) which is loaded as 'umk.<name>'.
+/// The module version and description are taken from its
+/// '__version__' and '__doc__' attributes. Lifecycle hooks are
+/// module functions with the same names:
+///
+/// ```python
+/// def before_command(command: list[str]): ...
+/// def after_command(command: list[str], error: str | None): ...
+/// def on_exit(error: str | None): ...
+/// ```
+struct Python {
+    name: String,
+    file: PathBuf,
+    package: Option<PathBuf>,
+    requires: Vec<String>,
+    module: OnceLock<PyObjectRef>,
+    metadata: OnceLock<(plugin::Info, Vec<String>)>,
+}
+
+impl Python {
+    fn new(name: &str, file: PathBuf, package: Option<PathBuf>) -> sdk::Result<Self> {
+        let source = std::fs::read_to_string(&file)?;
+        Ok(Self {
+            name: name.to_string(),
+            requires: Self::requires(&source, &file),
+            file,
+            package,
+            module: OnceLock::new(),
+            metadata: OnceLock::new(),
+        })
+    }
+
+    /// Syntax errors are ignored here, they are reported by the import.
+    fn requires(source: &str, file: &Path) -> Vec<String> {
+        use rustpython_parser::ast::{Constant, Expr, Mod, Stmt};
+        let path = file.to_string_lossy();
+        let Ok(Mod::Module(module)) = rustpython_parser::parse(source, Mode::Module, &path) else {
+            return Vec::new();
+        };
+        let value = module.body.iter().find_map(|s| match s {
+            Stmt::Assign(assign) => assign
+                .targets
+                .iter()
+                .any(|t| matches!(t, Expr::Name(n) if n.id.as_str() == PYTHON_REQUIRES))
+                .then_some(assign.value.as_ref()),
+            _ => None,
+        });
+        let elements = match value {
+            Some(Expr::List(list)) => &list.elts,
+            Some(Expr::Tuple(tuple)) => &tuple.elts,
+            _ => return Vec::new(),
+        };
+        elements
+            .iter()
+            .filter_map(|e| match e {
+                Expr::Constant(c) => match &c.value {
+                    Constant::Str(s) => Some(s.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn import(&self, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
+        let qualified = format!("{}.{}", global::kit::NAME, self.name);
+        let util = vm.import("importlib.util", 0)?.get_attr("util", vm)?;
+        let file = self.file.to_string_lossy().to_string();
+        let spec = match &self.package {
+            None => vm.call_method(&util, "spec_from_file_location", (qualified.clone(), file))?,
+            Some(package) => {
+                let locations = vm.ctx.new_list(vec![
+                    vm.ctx.new_str(package.to_string_lossy().to_string()).into(),
+                ]);
+                let function = util.get_attr("spec_from_file_location", vm)?;
+                let args = FuncArgs::new(
+                    vec![vm.new_pyobj(qualified.clone()), vm.new_pyobj(file)],
+                    KwArgs::from_iter([(
+                        String::from("submodule_search_locations"),
+                        locations.into(),
+                    )]),
+                );
+                function.call(args, vm)?
+            }
+        };
+        let module = vm.call_method(&util, "module_from_spec", (spec.clone(),))?;
+        let modules = vm.sys_module.get_attr("modules", vm)?;
+        modules.set_item(qualified.as_str(), module.clone(), vm)?;
+        let loader = spec.get_attr("loader", vm)?;
+        vm.call_method(&loader, "exec_module", (module.clone(),))?;
+        vm.import(global::kit::NAME, 0)?.set_attr(
+            vm.ctx.intern_str(self.name.as_str()),
+            module.clone(),
+            vm,
+        )?;
+        Ok(module)
+    }
+
+    /// Calls the module function if it is defined.
+    fn hook(&self, name: &'static str, args: Vec<PyObjectRef>, vm: &VirtualMachine) -> sdk::Result {
+        let Some(module) = self.module.get() else {
+            return Ok(());
+        };
+        let call = || -> PyResult<()> {
+            if let Some(function) = vm.get_attribute_opt(module.clone(), name)? {
+                function.call(args, vm)?;
+            }
+            Ok(())
+        };
+        call().map_err(|e| {
+            let msg = format!("Plugin '{}' failed in '{}' hook", self.name, name);
+            sdk::Error::python(e, vm).wrap(ErrorKind::Plugin, msg)
+        })
+    }
+
+    fn error(result: &sdk::Result, vm: &VirtualMachine) -> PyObjectRef {
+        match result {
+            Ok(_) => vm.ctx.none(),
+            Err(e) => vm.ctx.new_str(e.message()).into(),
+        }
+    }
+
+    fn command(command: &[String], vm: &VirtualMachine) -> PyObjectRef {
+        let items = command.iter().map(|c| vm.ctx.new_str(c.as_str()).into());
+        vm.ctx.new_list(items.collect()).into()
+    }
+
+    fn attr(module: &PyObjectRef, name: &'static str, vm: &VirtualMachine) -> Option<String> {
+        let value = module.get_attr(name, vm).ok()?;
+        if vm.is_none(&value) {
+            return None;
+        }
+        value.str(vm).ok().map(|s| s.as_str().trim().to_string())
+    }
+}
+
+impl Interface for Python {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result {
+        let module = self.import(vm).map_err(|e| {
+            sdk::Error::python(e, vm).with_note(format!(
+                "while loading plugin '{}' from '{}'",
+                self.name,
+                self.file.display()
+            ))
+        })?;
+        if let Ok(abi) = module.get_attr(PYTHON_ABI, vm) {
+            let abi = abi.try_into_value::<u32>(vm).ok();
+            if abi != Some(PYTHON_ABI_VERSION) {
+                let msg = format!(
+                    "Plugin '{}' requires ABI version {}, expected {}",
+                    self.name,
+                    abi.map(|v| v.to_string())
+                        .unwrap_or_else(|| "<invalid>".into()),
+                    PYTHON_ABI_VERSION
+                );
+                return Err(Error::new(ErrorKind::Plugin, msg));
+            }
+        }
+        let info = plugin::Info {
+            name: self.name.clone(),
+            version: Self::attr(&module, "__version__", vm).unwrap_or_default(),
+            description: Self::attr(&module, "__doc__", vm).unwrap_or_default(),
+        };
+        let examples = module
+            .get_attr(PYTHON_EXAMPLES, vm)
+            .ok()
+            .and_then(|v| {
+                vm.extract_elements_with(&v, |e| e.str(vm).map(|s| s.to_string()))
+                    .ok()
+            })
+            .unwrap_or_default();
+        let _ = self.metadata.set((info, examples));
+        let _ = self.module.set(module);
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        match self.metadata.get() {
+            Some((info, _)) => info.clone(),
+            None => plugin::Info {
+                name: self.name.clone(),
+                version: String::new(),
+                description: String::new(),
+            },
+        }
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.requires.clone()
+    }
+
+    fn examples(&self) -> Vec<String> {
+        match self.metadata.get() {
+            Some((_, examples)) => examples.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    fn cli(&self, _: &sdk::Cache) -> sdk::Result<Option<clap::Command>> {
+        Ok(None)
+    }
+
+    fn cache(&self, _: &sdk::Cache, _: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, _: &clap::ArgMatches, _: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn register(&self, _: &PyRef<PyModule>, _: &VirtualMachine) {
+        // The module is attached to the 'umk' in the 'initialize', because
+        // python code may import 'umk' which is not ready at this point.
+    }
+
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        self.hook("before_load", vec![], vm)
+    }
+
+    fn after_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        self.hook("after_load", vec![], vm)
+    }
+
+    fn before_command(&self, command: &[String], vm: &VirtualMachine) -> sdk::Result {
+        self.hook("before_command", vec![Self::command(command, vm)], vm)
+    }
+
+    fn after_command(
+        &self,
+        command: &[String],
+        result: &sdk::Result,
+        vm: &VirtualMachine,
+    ) -> sdk::Result {
+        let args = vec![Self::command(command, vm), Self::error(result, vm)];
+        self.hook("after_command", args, vm)
+    }
+
+    fn on_exit(&self, result: &sdk::Result, vm: &VirtualMachine) -> sdk::Result {
+        self.hook("on_exit", vec![Self::error(result, vm)], vm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake {
+        name: &'static str,
+        dependencies: &'static [&'static str],
+    }
+
+    impl Interface for Fake {
+        fn initialize(&self, _vm: &VirtualMachine) -> sdk::Result {
+            Ok(())
+        }
+
+        fn info(&self) -> plugin::Info {
+            plugin::Info {
+                name: self.name.to_string(),
+                version: String::from("0.1.0"),
+                description: String::new(),
+            }
+        }
+
+        fn dependencies(&self) -> Vec<String> {
+            self.dependencies.iter().map(|d| d.to_string()).collect()
+        }
+
+        fn examples(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<clap::Command>> {
+            Ok(None)
+        }
+
+        fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+            Ok(())
+        }
+
+        fn call(&self, _matches: &clap::ArgMatches, _vm: &VirtualMachine) -> sdk::Result {
+            Ok(())
+        }
+
+        fn register(&self, _module: &PyRef<PyModule>, _vm: &VirtualMachine) {}
+    }
+
+    fn sorted(plugins: &[(&'static str, &'static [&'static str])]) -> sdk::Result<Vec<String>> {
+        let plugins: Vec<Box<dyn Interface>> = plugins
+            .iter()
+            .map(|&(name, dependencies)| {
+                Box::new(Fake { name, dependencies }) as Box<dyn Interface>
+            })
+            .collect();
+        Ok(sort(plugins)?.iter().map(|p| p.info().name).collect())
+    }
+
+    #[test]
+    fn dependencies_go_first() {
+        let order = sorted(&[("CLI", &[]), ("Docs", &["Lint", "cli"]), ("Lint", &["CLI"])]);
+        assert_eq!(order.unwrap(), ["CLI", "Lint", "Docs"]);
+        let order = sorted(&[("B", &[]), ("A", &[])]);
+        assert_eq!(order.unwrap(), ["B", "A"]);
+    }
+
+    #[test]
+    fn unknown_dependency() {
+        let error = sorted(&[("Docs", &["Lint"])]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Plugin);
+        assert_eq!(
+            error.message(),
+            "Plugin 'docs' depends on unknown plugin 'Lint'"
+        );
+    }
+
+    #[test]
+    fn dependency_cycle() {
+        let error = sorted(&[("CLI", &[]), ("A", &["B"]), ("B", &["C"]), ("C", &["B"])]);
+        assert_eq!(
+            error.unwrap_err().message(),
+            "Plugin dependency cycle: b -> c -> b"
+        );
+        let error = sorted(&[("A", &["A"])]).unwrap_err();
+        assert_eq!(error.message(), "Plugin dependency cycle: a -> a");
+    }
+}
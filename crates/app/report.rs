@@ -0,0 +1,65 @@
+use crate::style::Style;
+use crate::traceback::Traceback;
+use sdk::Error;
+
+//////////////////////////////////////////////////////////////////
+// Error report
+//////////////////////////////////////////////////////////////////
+
+/// Prints the error to the stderr. The verbose mode prints the whole
+/// cause chain and internal traceback frames, otherwise only the
+/// innermost cause is shown.
+pub fn error(err: &Error, verbose: bool) {
+    let style = Style::stderr();
+    let mut traceback = Traceback::new(style, verbose);
+    let header = format!("error[{}]", err.kind());
+    eprintln!("{}: {}", style.error(&header), style.bold(err.message()));
+    if let Some(location) = err.location() {
+        eprintln!("  {} {}", style.accent("-->"), location);
+    }
+
+    // Python exception headline is printed after its traceback,
+    // so it is excluded from the cause list.
+    let mut causes = err.causes();
+    if let Some(exception) = err.exception() {
+        let mut frames = traceback.render(exception);
+        if frames.is_empty()
+            && let Some(location) = err.location()
+        {
+            frames = traceback.snippet(location);
+        }
+        eprint!("{}", frames);
+        let headline = err.innermost().message();
+        if headline != err.message() {
+            eprintln!("{}", style.error(headline));
+            causes.retain(|c| c != headline);
+        }
+    }
+    for note in err.notes() {
+        eprintln!("  {} {}", style.accent("= note:"), note);
+    }
+
+    if causes.is_empty() {
+        return;
+    }
+    if verbose {
+        eprintln!("{}", style.bold("caused by:"));
+        for (index, cause) in causes.iter().enumerate() {
+            eprintln!("  {}: {}", index, cause);
+        }
+    } else {
+        eprintln!("  {} {}", style.accent("cause:"), causes.last().unwrap());
+        if causes.len() > 1 {
+            eprintln!(
+                "{}",
+                style.dim("  (run with --verbose to see the full cause chain)")
+            );
+        }
+    }
+}
+
+/// Prints the recoverable problem to the stderr.
+pub fn warning(message: &str) {
+    let style = Style::stderr();
+    eprintln!("{}: {}", style.warning("warning"), message);
+}
@@ -0,0 +1,59 @@
+use std::io::IsTerminal;
+
+//////////////////////////////////////////////////////////////////
+// Terminal style
+//////////////////////////////////////////////////////////////////
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    /// Colors are enabled only for terminals and if 'NO_COLOR' is not set.
+    pub fn stderr() -> Self {
+        Self {
+            enabled: std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self {
+            enabled: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn paint(&self, codes: &[&str], text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        format!("{}{}{}", codes.concat(), text, RESET)
+    }
+
+    pub fn error(&self, text: &str) -> String {
+        self.paint(&[BOLD, RED], text)
+    }
+
+    pub fn warning(&self, text: &str) -> String {
+        self.paint(&[BOLD, YELLOW], text)
+    }
+
+    pub fn accent(&self, text: &str) -> String {
+        self.paint(&[BOLD, CYAN], text)
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        self.paint(&[BOLD], text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(&[DIM], text)
+    }
+}
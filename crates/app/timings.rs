@@ -0,0 +1,102 @@
+use crate::cli;
+use crate::style::Style;
+use std::path::PathBuf;
+use std::time::Duration;
+
+//////////////////////////////////////////////////////////////////
+// Timings report
+//////////////////////////////////////////////////////////////////
+
+/// Prints the table of the recorded timings ('--timings') and writes
+/// the trace file ('--trace FILE').
+pub fn report(timings: &sdk::Timings) -> sdk::Result {
+    if cli::flag(cli::TIMINGS) {
+        print(timings);
+    }
+    let Some(file) = cli::values(cli::TRACE).pop().map(PathBuf::from) else {
+        return Ok(());
+    };
+    timings.trace(&file).map_err(|e| {
+        let kind = e.kind();
+        e.wrap(
+            kind,
+            format!("Failed to write the trace '{}'", file.display()),
+        )
+    })?;
+    eprintln!("Trace is written to '{}'", file.display());
+    Ok(())
+}
+
+/// Spans in the start order, the nested ones are indented. The targets
+/// are summarized at the end, the slowest first.
+fn print(timings: &sdk::Timings) {
+    let style = Style::stderr();
+    let spans = timings.spans();
+    let total = timings.elapsed();
+    let mut rows = Vec::new();
+    let mut parents: Vec<&sdk::TimingSpan> = Vec::new();
+    for span in spans.iter() {
+        parents.retain(|p| p.lane != span.lane || p.end() >= span.end());
+        let depth = parents.iter().filter(|p| p.lane == span.lane).count();
+        let mut name = format!("{}{}", "  ".repeat(depth), span.name);
+        if span.lane > 0 {
+            name = format!("{} [lane {}]", name, span.lane);
+        }
+        rows.push((name, span));
+        parents.push(span);
+    }
+    let width = rows.iter().map(|(n, _)| n.len()).max().unwrap_or(0).max(5);
+    let header = format!(
+        "{:width$}  {:>9}  {:>9}  {:>6}  STATUS",
+        "PHASE", "START", "DURATION", "SHARE"
+    );
+    eprintln!("{}", style.bold(&header));
+    for (name, span) in rows.iter() {
+        let line = format!(
+            "{:width$}  {:>9}  {:>9}  {:>6}  {}",
+            name,
+            duration(span.start),
+            duration(span.duration),
+            share(span.duration, total),
+            span.status.as_deref().unwrap_or("")
+        );
+        eprintln!("{}", line.trim_end());
+    }
+    let line = format!("{:width$}  {:>9}  {:>9}", "total", "", duration(total));
+    eprintln!("{}", style.bold(&line));
+
+    let mut targets: Vec<&sdk::TimingSpan> = spans.iter().filter(|s| s.status.is_some()).collect();
+    if targets.len() < 2 {
+        return;
+    }
+    targets.sort_by_key(|s| std::cmp::Reverse(s.duration));
+    let busy: Duration = targets.iter().map(|s| s.duration).sum();
+    let slowest: Vec<String> = targets
+        .iter()
+        .take(3)
+        .map(|s| format!("{} ({})", s.name, duration(s.duration)))
+        .collect();
+    eprintln!(
+        "{} {} targets took {}, the slowest: {}",
+        style.bold("Targets:"),
+        targets.len(),
+        duration(busy),
+        slowest.join(", ")
+    );
+}
+
+fn duration(value: Duration) -> String {
+    let seconds = value.as_secs_f64();
+    match seconds {
+        s if s < 1.0 => format!("{:.1}ms", s * 1000.0),
+        s if s < 60.0 => format!("{:.2}s", s),
+        s => format!("{}m{:02}s", s as u64 / 60, s as u64 % 60),
+    }
+}
+
+fn share(value: Duration, total: Duration) -> String {
+    match total.is_zero() {
+        true => String::from("-"),
+        false => format!("{:.1}%", value.as_secs_f64() / total.as_secs_f64() * 100.0),
+    }
+}
@@ -0,0 +1,121 @@
+use crate::style::Style;
+use rustpython_vm::builtins::PyBaseExceptionRef;
+use sdk::ErrorLocation;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+//////////////////////////////////////////////////////////////////
+// Traceback
+//////////////////////////////////////////////////////////////////
+
+/// Number of source lines shown before the failed one.
+const CONTEXT: usize = 2;
+
+pub struct Traceback {
+    style: Style,
+    verbose: bool,
+    sources: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl Traceback {
+    pub fn new(style: Style, verbose: bool) -> Self {
+        Self {
+            style,
+            verbose,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Renders traceback frames with the source context. Internal frames
+    /// (frozen modules like importlib and the 'umk' package code) are shown
+    /// in the verbose mode only.
+    pub fn render(&mut self, exception: &PyBaseExceptionRef) -> String {
+        let mut result = String::new();
+        let frames = ErrorLocation::trace(exception);
+        let hidden = frames.iter().filter(|f| f.is_internal()).count();
+        let frames: Vec<ErrorLocation> = frames
+            .into_iter()
+            .filter(|f| self.verbose || !f.is_internal())
+            .collect();
+        if frames.is_empty() {
+            return result;
+        }
+        let _ = writeln!(
+            result,
+            "{}",
+            self.style.bold("Traceback (most recent call last):")
+        );
+        for frame in frames.iter() {
+            let _ = writeln!(
+                result,
+                "  File \"{}\", line {}, in {}",
+                self.style.accent(&frame.file.display().to_string()),
+                frame.line,
+                self.style.bold(&frame.function),
+            );
+            result.push_str(&self.snippet(frame));
+        }
+        if !self.verbose && hidden > 0 {
+            let text = format!("  ({} internal frames hidden, use --verbose)", hidden);
+            let _ = writeln!(result, "{}", self.style.dim(&text));
+        }
+        result
+    }
+
+    /// Renders source lines around the location with the caret line under
+    /// the failed expression.
+    pub fn snippet(&mut self, location: &ErrorLocation) -> String {
+        let mut result = String::new();
+        let style = self.style;
+        let Some(lines) = self.source(&location.file) else {
+            return result;
+        };
+        if location.line == 0 || location.line > lines.len() {
+            return result;
+        }
+        let first = location.line.saturating_sub(CONTEXT).max(1);
+        let width = location.line.to_string().len();
+        for number in first..=location.line {
+            let text = lines[number - 1].trim_end();
+            let gutter = format!("{:>width$} |", number, width = width);
+            if number != location.line {
+                let _ = writeln!(result, "      {} {}", style.dim(&gutter), style.dim(text));
+                continue;
+            }
+            let _ = writeln!(
+                result,
+                "    {} {} {}",
+                style.error(">"),
+                style.accent(&gutter),
+                text
+            );
+            let indent = text.len() - text.trim_start().len();
+            let start = match location.column {
+                Some(column) if column > indent && column <= text.len() => column - 1,
+                _ => indent,
+            };
+            let carets = "^".repeat(text.len().saturating_sub(start).max(1));
+            let padding = " ".repeat(width);
+            let _ = writeln!(
+                result,
+                "      {} {}{}",
+                style.accent(&format!("{} |", padding)),
+                " ".repeat(start),
+                style.error(&carets)
+            );
+        }
+        result
+    }
+
+    fn source(&mut self, file: &PathBuf) -> Option<&Vec<String>> {
+        self.sources
+            .entry(file.clone())
+            .or_insert_with(|| {
+                std::fs::read_to_string(file)
+                    .ok()
+                    .map(|s| s.lines().map(String::from).collect())
+            })
+            .as_ref()
+    }
+}
@@ -1,4 +1,9 @@
-use std::path::PathBuf;
+use crate::interpreter::{Interpreter, PHASE};
+use crate::plugins;
+use crate::plugins::Plugins;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 //////////////////////////////////////////////////////////////////
 // Mode
@@ -11,7 +16,7 @@ pub enum Mode {
 }
 
 impl Mode {
-    pub fn from(root: &PathBuf) -> Option<Mode> {
+    pub fn from(root: &Path) -> Option<Mode> {
         let path = root.join(global::workspace::FILE);
         if path.exists() {
             return Some(Mode::Single);
@@ -22,6 +27,13 @@ impl Mode {
         }
         None
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Single => "single",
+            Mode::Tree => "tree",
+        }
+    }
 }
 
 //////////////////////////////////////////////////////////////////
@@ -34,18 +46,21 @@ pub struct Workspace {
 }
 
 impl Workspace {
-    pub fn new(root: &PathBuf) -> sdk::Result<Self> {
+    pub fn new(root: &Path) -> sdk::Result<Self> {
         match Mode::from(root) {
             None => {
-                let err = sdk::Error::Text(format!(
-                    "Failed to wrap workspace. Given path is not an {} project: {}",
-                    global::project::NAME,
-                    root.display()
-                ));
+                let err = sdk::Error::new(
+                    sdk::ErrorKind::WorkspaceNotFound,
+                    format!(
+                        "Failed to wrap workspace. Given path is not an {} project: {}",
+                        global::project::NAME,
+                        root.display()
+                    ),
+                );
                 Err(err)
             }
             Some(v) => Ok(Self {
-                root: root.clone(),
+                root: root.to_path_buf(),
                 mode: v,
             }),
         }
@@ -71,7 +86,114 @@ impl Workspace {
 //////////////////////////////////////////////////////////////////
 
 impl Workspace {
-    pub fn exists(root: &PathBuf) -> bool {
-        Mode::from(root).is_some()
+    pub fn cache(&self) -> PathBuf {
+        self.root().join(global::workspace::CACHE)
+    }
+
+    pub fn artifacts(&self) -> PathBuf {
+        self.root().join(global::workspace::ARTIFACTS)
+    }
+
+    pub fn config(&self) -> PathBuf {
+        self.root().join(global::workspace::CONFIG)
+    }
+
+    pub fn plugins(&self) -> PathBuf {
+        self.root().join(global::workspace::PLUGINS)
+    }
+
+    pub fn logs(&self) -> PathBuf {
+        self.root().join(global::workspace::LOGS)
+    }
+
+    /// Loads the script and rewrites the cache of all plugins. The hashes
+    /// of the source files are stored along, to detect the stale cache.
+    /// Returns whether the sources differ from the previous refresh.
+    pub fn refresh(
+        &self,
+        plugins: &Plugins,
+        interpreter: &Interpreter,
+        cache: &sdk::Cache,
+    ) -> sdk::Result<bool> {
+        let timings = interpreter
+            .exec(sdk::py::service::<sdk::Timings>)?
+            .unwrap_or_default();
+        interpreter.enter(|vm| plugins::forward(plugins, vm, |p, vm| p.before_load(vm)))?;
+        timings.measure("load script", PHASE, || self.load(interpreter))?;
+        interpreter.enter(|vm| plugins::forward(plugins, vm, |p, vm| p.after_load(vm)))?;
+        timings.measure("write cache", PHASE, || {
+            plugins
+                .iter()
+                .try_for_each(|plugin| interpreter.enter(|vm| plugin.cache(cache, vm)))
+        })?;
+        let entry = sdk::CacheEntry::<Sources>::new(CACHE_KEY, cache.clone());
+        let sources = self.sources()?;
+        let changed = entry.get().ok().flatten().as_ref() != Some(&sources);
+        entry.set(sources)?;
+        Ok(changed)
+    }
+
+    /// Files the cache is built from (relative paths) and their hashes.
+    pub fn sources(&self) -> sdk::Result<Sources> {
+        let mut files = vec![self.root().join(global::workspace::FILE)];
+        collect(&self.plugins(), &mut files)?;
+        let mut result = Sources::new();
+        for file in files {
+            let name = file.strip_prefix(self.root()).unwrap_or(&file);
+            let hash = Sha256::digest(std::fs::read(&file)?);
+            let hash = hash.iter().map(|b| format!("{:02x}", b)).collect();
+            result.insert(name.to_string_lossy().replace('\\', "/"), hash);
+        }
+        Ok(result)
+    }
+
+    pub fn load(&self, interpreter: &Interpreter) -> sdk::Result {
+        let result = match self.mode() {
+            Mode::Single => interpreter.exec(|vm| {
+                vm.insert_sys_path(vm.new_pyobj(self.root().to_str().unwrap()))?;
+                // The script is executed again if it was loaded before.
+                let modules = vm.sys_module.get_attr("modules", vm)?;
+                if modules.get_item(global::workspace::SCRIPT, vm).is_ok() {
+                    modules.del_item(global::workspace::SCRIPT, vm)?;
+                }
+                vm.import(global::workspace::SCRIPT, 0)?;
+                Ok(())
+            }),
+            Mode::Tree => Err(sdk::Error::new(
+                sdk::ErrorKind::Generic,
+                "Workspace with tree mode is not supported yet",
+            )),
+        };
+        result.map_err(|e| {
+            let kind = e.kind();
+            e.wrap(kind, "Failed to load workspace script")
+        })
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Sources
+//////////////////////////////////////////////////////////////////
+
+/// Cache key of the sources the cache was built from.
+pub const CACHE_KEY: &str = "workspace";
+
+pub type Sources = BTreeMap<String, String>;
+
+fn collect(directory: &Path, files: &mut Vec<PathBuf>) -> sdk::Result {
+    if !directory.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n == "__pycache__") {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else {
+            files.push(path);
+        }
     }
+    Ok(())
 }
@@ -1,17 +1,26 @@
 pub mod project {
-    pub const NAME: &'static str = "unimake";
-    pub const NAME_SHORT: &'static str = "umk";
-    pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    pub const NAME: &str = "unimake";
+    pub const NAME_SHORT: &str = "umk";
+    pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 }
 
 pub mod workspace {
-    pub const FILE: &'static str = "unimake.py";
-    pub const SCRIPT: &'static str = "unimake";
-    pub const DIRECTORY: &'static str = ".unimake";
-    pub const CACHE: &'static str = ".unimake/.cache";
+    pub const FILE: &str = "unimake.py";
+    pub const SCRIPT: &str = "unimake";
+    pub const DIRECTORY: &str = ".unimake";
+    pub const CACHE: &str = ".unimake/.cache";
+    pub const ARTIFACTS: &str = ".unimake/.cache/artifacts";
+    pub const CONFIG: &str = ".unimake/config.toml";
+    pub const PLUGINS: &str = ".unimake/plugins";
+    pub const LOGS: &str = ".unimake/logs";
 }
 
 pub mod kit {
-    pub const NAME: &'static str = "umk";
-    pub const CONTAINER: &'static str = "__unimake__";
+    pub const NAME: &str = "umk";
+    pub const CONTAINER: &str = "__unimake__";
+}
+
+pub mod flags {
+    pub const DRY_RUN: &str = "dry-run";
+    pub const EXPLAIN: &str = "explain";
 }
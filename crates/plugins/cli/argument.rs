@@ -1,7 +1,6 @@
-use crate::class::Class;
+use crate::class::{self, Class};
 use crate::{command, consts};
 use builder_pattern::Builder;
-use const_format::concatcp;
 use rustpython_vm::builtins::{PyModule, PyTypeRef};
 use rustpython_vm::convert::{ToPyObject, ToPyResult};
 use rustpython_vm::function::{FuncArgs, PyMethodDef, PyMethodFlags};
@@ -39,19 +38,28 @@ pub struct Decorator {}
 
 impl Function for Decorator {
     const NAME: &'static str = consts::DECORATOR_ARG;
-    const METHOD: PyMethodDef =
-        PyMethodDef::new_const(Self::NAME, Self::decorate, PyMethodFlags::empty(), None);
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "arg(type, name, default=None, help=None, var=None)\n--\n\nDeclare the CLI command positional argument.",
+        ),
+    );
 }
 
 impl Decorator {
     fn decorate(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult {
         let inputs = Arc::new(py::args_to::<Pythonic>(inputs.clone(), vm)?);
         let inner = vm.new_function(
-            concatcp!("cli::", Decorator::NAME),
+            consts::DECORATOR_ARG_INNER,
             move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
-                let func = args.args.get(0).unwrap().clone();
+                let func = args.args.first().unwrap().clone();
                 let mut cmd = command::Pythonic::get(&func, vm)?;
-                cmd.argument(inputs.deref().clone(), vm)?;
+                cmd.argument(inputs.deref().clone(), vm).map_err(|e| {
+                    let decorator = format!("@cli.arg(\"{}\")", inputs.name);
+                    py::decorator_note(vm, e, &decorator, &func)
+                })?;
                 command::Pythonic::set(&func, cmd, vm)?;
                 func.to_pyresult(vm)
             },
@@ -66,6 +74,24 @@ impl py::Registerable for Decorator {
     }
 }
 
+impl Pythonic {
+    /// Name of the python function keyword argument.
+    pub fn key(&self) -> String {
+        match &self.variable {
+            Some(v) => v.clone(),
+            None => self.name.replace('-', "_"),
+        }
+    }
+
+    pub fn value(&self, matches: &clap::ArgMatches, vm: &VirtualMachine) -> Option<PyObjectRef> {
+        let class = Class::from(self.class.clone());
+        match class.extract(matches, &self.name, vm) {
+            Some(v) => Some(v),
+            None => self.default.clone(),
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////
 // Cache
 //////////////////////////////////////////////////////////////////
@@ -76,6 +102,9 @@ pub struct Cacheable {
     pub name: String,
     pub help: Option<String>,
     pub variable: Option<String>,
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
 }
 
 impl From<Pythonic> for Cacheable {
@@ -85,6 +114,17 @@ impl From<Pythonic> for Cacheable {
             name: value.name.clone(),
             help: value.help.clone(),
             variable: value.variable.clone(),
+            default: value.default.as_ref().map(class::literal),
+            required: value.default.is_none(),
         }
     }
 }
+
+impl From<&Cacheable> for clap::Arg {
+    fn from(value: &Cacheable) -> Self {
+        clap::Arg::new(value.name.clone())
+            .required(value.required)
+            .help(value.help.clone().unwrap_or_default())
+            .value_parser(value.class.parser())
+    }
+}
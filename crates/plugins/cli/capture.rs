@@ -0,0 +1,280 @@
+use rustpython_vm::builtins::PyStrRef;
+use rustpython_vm::common::lock::PyMutex;
+use rustpython_vm::convert::ToPyObject;
+use rustpython_vm::{PyObjectRef, PyPayload, PyResult, VirtualMachine, pyclass};
+use std::fs::File;
+use std::io::Write;
+
+//////////////////////////////////////////////////////////////////
+// Stream
+//////////////////////////////////////////////////////////////////
+
+/// Replaces 'sys.stdout' or 'sys.stderr' while the command runs, if the
+/// descriptors can't be redirected: the text goes to the original stream
+/// and to the log file. The output of 'process.run' is captured too,
+/// since it is written to them, other child processes are not.
+#[pyclass(module = false, name = "Capture")]
+#[derive(Debug, PyPayload)]
+pub struct Stream {
+    stream: PyObjectRef,
+    file: PyMutex<File>,
+}
+
+#[pyclass]
+impl Stream {
+    #[pymethod]
+    fn write(&self, text: PyStrRef, vm: &VirtualMachine) -> PyResult {
+        // The log must not break the command, e.g. if the disk is full.
+        let _ = self.file.lock().write_all(text.as_str().as_bytes());
+        vm.call_method(&self.stream, "write", (text,))
+    }
+
+    #[pymethod]
+    fn flush(&self, vm: &VirtualMachine) -> PyResult {
+        let _ = self.file.lock().flush();
+        vm.call_method(&self.stream, "flush", ())
+    }
+
+    #[pymethod]
+    fn isatty(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.stream, "isatty", ())
+    }
+
+    #[pymethod]
+    fn fileno(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(&self.stream, "fileno", ())
+    }
+
+    #[pygetset]
+    fn encoding(&self, vm: &VirtualMachine) -> PyResult {
+        self.stream.get_attr("encoding", vm)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Capture
+//////////////////////////////////////////////////////////////////
+
+const STREAMS: [&str; 2] = ["stdout", "stderr"];
+
+/// Installed capture of both streams, the originals are put back on drop.
+/// On unix the descriptors 1 and 2 are redirected, so the output of
+/// 'subprocess', 'os.system' and native code is captured too. The
+/// streams are pipes then, so 'isatty()' is false while the command runs.
+/// Elsewhere only the python streams are replaced.
+pub struct Capture<'a> {
+    originals: Vec<(&'static str, PyObjectRef)>,
+    #[cfg(unix)]
+    redirects: Vec<unix::Redirect>,
+    vm: &'a VirtualMachine,
+}
+
+impl<'a> Capture<'a> {
+    pub fn install(file: &File, vm: &'a VirtualMachine) -> std::io::Result<Self> {
+        #[cfg(unix)]
+        {
+            sdk::py::flush(vm);
+            match unix::Redirect::both(file) {
+                Ok(redirects) => {
+                    return Ok(Self {
+                        originals: Vec::new(),
+                        redirects,
+                        vm,
+                    });
+                }
+                Err(e) => sdk::warning(format!(
+                    "Failed to capture the output of child processes: {}",
+                    e
+                )),
+            }
+        }
+        Self::streams(file, vm)
+    }
+
+    fn streams(file: &File, vm: &'a VirtualMachine) -> std::io::Result<Self> {
+        let mut result = Self {
+            originals: Vec::new(),
+            #[cfg(unix)]
+            redirects: Vec::new(),
+            vm,
+        };
+        for name in STREAMS {
+            let Ok(stream) = vm.sys_module.get_attr(name, vm) else {
+                continue;
+            };
+            let capture = Stream {
+                stream: stream.clone(),
+                file: PyMutex::new(file.try_clone()?),
+            };
+            if vm
+                .sys_module
+                .set_attr(name, capture.into_ref(&vm.ctx).to_pyobject(vm), vm)
+                .is_ok()
+            {
+                result.originals.push((name, stream));
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Drop for Capture<'_> {
+    fn drop(&mut self) {
+        sdk::py::flush(self.vm);
+        for (name, stream) in self.originals.drain(..) {
+            let _ = self.vm.sys_module.set_attr(name, stream, self.vm);
+        }
+        #[cfg(unix)]
+        {
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().flush();
+            for redirect in self.redirects.drain(..) {
+                redirect.restore();
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::{FromRawFd, RawFd};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Output still written by the background processes after the command
+    /// is not waited for longer, it goes to the terminal only.
+    const DRAIN: Duration = Duration::from_millis(500);
+
+    fn check(result: libc::c_int) -> std::io::Result<libc::c_int> {
+        match result {
+            -1 => Err(std::io::Error::last_os_error()),
+            v => Ok(v),
+        }
+    }
+
+    /// The descriptor is replaced by the pipe, the thread copies the pipe
+    /// into the original descriptor and the log.
+    pub struct Redirect {
+        fd: RawFd,
+        saved: RawFd,
+        done: mpsc::Receiver<()>,
+    }
+
+    impl Redirect {
+        /// Redirects the standard output and error, nothing on failure.
+        pub fn both(log: &File) -> std::io::Result<Vec<Self>> {
+            let output = Self::install(1, log)?;
+            match Self::install(2, log) {
+                Ok(error) => Ok(vec![output, error]),
+                Err(e) => {
+                    output.restore();
+                    Err(e)
+                }
+            }
+        }
+
+        fn install(fd: RawFd, log: &File) -> std::io::Result<Self> {
+            let mut log = log.try_clone()?;
+            let mut pipe = [0; 2];
+            // SAFETY: the descriptors are owned by this function till they
+            // are passed to the thread or closed.
+            unsafe {
+                check(libc::pipe(pipe.as_mut_ptr()))?;
+                let saved = match check(libc::dup(fd)) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        libc::close(pipe[0]);
+                        libc::close(pipe[1]);
+                        return Err(e);
+                    }
+                };
+                let copy = check(libc::dup(saved));
+                let redirected = copy.and_then(|copy| check(libc::dup2(pipe[1], fd)).map(|_| copy));
+                libc::close(pipe[1]);
+                let copy = match redirected {
+                    Ok(v) => v,
+                    Err(e) => {
+                        libc::close(pipe[0]);
+                        libc::close(saved);
+                        return Err(e);
+                    }
+                };
+                let mut source = File::from_raw_fd(pipe[0]);
+                let mut target = File::from_raw_fd(copy);
+                let (sender, done) = mpsc::channel();
+                thread::spawn(move || {
+                    let mut buffer = [0; 8192];
+                    while let Ok(n) = source.read(&mut buffer) {
+                        if n == 0 {
+                            break;
+                        }
+                        let _ = target.write_all(&buffer[..n]);
+                        let _ = log.write_all(&buffer[..n]);
+                    }
+                    let _ = sender.send(());
+                });
+                Ok(Self { fd, saved, done })
+            }
+        }
+
+        /// Puts the original descriptor back and waits till the pipe is
+        /// drained, i.e. its last writer is closed.
+        pub fn restore(self) {
+            // SAFETY: the saved descriptor is owned by the redirect.
+            unsafe {
+                libc::dup2(self.saved, self.fd);
+                libc::close(self.saved);
+            }
+            let _ = self.done.recv_timeout(DRAIN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_vm::class::PyClassImpl;
+    use rustpython_vm::{AsObject, Interpreter};
+
+    #[test]
+    fn streams_go_to_log_and_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("target.log");
+        Interpreter::without_stdlib(Default::default()).enter(|vm| {
+            Stream::make_class(&vm.ctx);
+            let scope = vm.new_scope_with_builtins();
+            let source = r#"
+class Sink:
+    def __init__(self):
+        self.parts = []
+    def write(self, text):
+        self.parts.append(text)
+    def flush(self):
+        pass
+sink = Sink()
+"#;
+            vm.run_code_string(scope.clone(), source, "<test>".to_string())
+                .unwrap();
+            let sink = scope.globals.get_item("sink", vm).unwrap();
+            vm.sys_module.set_attr("stdout", sink.clone(), vm).unwrap();
+            let write = |text: &str| {
+                let stdout = vm.sys_module.get_attr("stdout", vm).unwrap();
+                vm.call_method(&stdout, "write", (text.to_string(),))
+                    .unwrap();
+            };
+            let capture = Capture::streams(&File::create(&path).unwrap(), vm).unwrap();
+            write("captured\n");
+            drop(capture);
+            write("not captured\n");
+            let stdout = vm.sys_module.get_attr("stdout", vm).unwrap();
+            assert!(stdout.is(&sink));
+            let parts = sink.get_attr("parts", vm).unwrap();
+            let parts: Vec<String> = parts.try_into_value(vm).unwrap();
+            assert_eq!(parts, ["captured\n", "not captured\n"]);
+        });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "captured\n");
+    }
+}
@@ -1,5 +1,6 @@
-use rustpython_vm::builtins::{PyBool, PyFloat, PyInt, PyStr, PyTypeRef};
+use rustpython_vm::builtins::{PyBool, PyFloat, PyInt, PyNone, PyStr, PyTypeRef};
 use rustpython_vm::class::StaticType;
+use rustpython_vm::{PyObjectRef, VirtualMachine};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -13,16 +14,82 @@ pub enum Class {
 
 impl From<PyTypeRef> for Class {
     fn from(value: PyTypeRef) -> Self {
-        if value.fast_issubclass(PyStr::static_type()) {
+        // The 'bool' is a subclass of the 'int', so it must be checked first.
+        if value.fast_issubclass(PyBool::static_type()) {
+            Class::Boolean
+        } else if value.fast_issubclass(PyStr::static_type()) {
             Class::String
         } else if value.fast_issubclass(PyInt::static_type()) {
             Class::Integer
         } else if value.fast_issubclass(PyFloat::static_type()) {
             Class::Float
-        } else if value.fast_issubclass(PyBool::static_type()) {
-            Class::Boolean
         } else {
             Class::Custom
         }
     }
 }
+
+impl Class {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::String => "string",
+            Class::Integer => "integer",
+            Class::Float => "float",
+            Class::Boolean => "boolean",
+            Class::Custom => "custom",
+        }
+    }
+
+    pub fn parser(&self) -> clap::builder::ValueParser {
+        match self {
+            Class::String => clap::value_parser!(String),
+            Class::Integer => clap::value_parser!(i64).into(),
+            Class::Float => clap::value_parser!(f64).into(),
+            Class::Boolean => clap::value_parser!(bool),
+            Class::Custom => clap::value_parser!(String),
+        }
+    }
+
+    /// Reads parsed value of the given argument and converts it to python object.
+    pub fn extract(
+        &self,
+        matches: &clap::ArgMatches,
+        id: &str,
+        vm: &VirtualMachine,
+    ) -> Option<PyObjectRef> {
+        if matches.value_source(id) != Some(clap::parser::ValueSource::CommandLine) {
+            return None;
+        }
+        match self {
+            Class::String | Class::Custom => matches
+                .get_one::<String>(id)
+                .map(|v| vm.new_pyobj(v.clone())),
+            Class::Integer => matches.get_one::<i64>(id).map(|v| vm.new_pyobj(*v)),
+            Class::Float => matches.get_one::<f64>(id).map(|v| vm.new_pyobj(*v)),
+            Class::Boolean => matches.get_one::<bool>(id).map(|v| vm.new_pyobj(*v)),
+        }
+    }
+}
+
+/// Converts the default value to JSON, so it can be cached and reported.
+/// Values of other types are represented by their type name.
+pub fn literal(value: &PyObjectRef) -> serde_json::Value {
+    if value.class().fast_issubclass(PyBool::static_type()) {
+        let value = value.downcast_ref::<PyInt>().unwrap();
+        serde_json::Value::Bool(value.as_bigint().to_string() != "0")
+    } else if let Some(value) = value.downcast_ref::<PyInt>() {
+        let text = value.as_bigint().to_string();
+        match text.parse::<i64>() {
+            Ok(v) => serde_json::Value::from(v),
+            Err(_) => serde_json::Value::String(text),
+        }
+    } else if let Some(value) = value.downcast_ref::<PyFloat>() {
+        serde_json::Value::from(value.to_f64())
+    } else if let Some(value) = value.downcast_ref::<PyStr>() {
+        serde_json::Value::from(value.as_str())
+    } else if value.downcast_ref::<PyNone>().is_some() {
+        serde_json::Value::Null
+    } else {
+        serde_json::Value::String(format!("<{}>", value.class().name()))
+    }
+}
@@ -1,41 +1,92 @@
 use crate::{argument, consts, option};
 use builder_pattern::Builder;
-use const_format::concatcp;
 use rustpython_vm::builtins::{PyBool, PyFloat, PyInt, PyModule, PyStr, PyTypeRef};
 use rustpython_vm::class::StaticType;
 use rustpython_vm::common::lock::PyRwLock;
 use rustpython_vm::convert::{ToPyObject, ToPyResult};
-use rustpython_vm::function::{FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::function::{ArgIntoFloat, FuncArgs, KwArgs, PyMethodDef, PyMethodFlags};
 use rustpython_vm::types::{Constructor, DefaultConstructor, Initializer};
 use rustpython_vm::{FromArgs, PyObjectRef, PyPayload, PyRef, PyResult, VirtualMachine, pyclass};
 use sdk::py;
 use sdk::py::Function;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::ops::Deref;
 use std::sync::Arc;
 
 //////////////////////////////////////////////////////////////////
 // Access
 //////////////////////////////////////////////////////////////////
 
-type Container = HashMap<String, Pythonic>;
+pub type Container = HashMap<String, Pythonic>;
+
+/// Declared commands, other plugins may read them as a service.
+#[derive(Debug, Clone, Default)]
+pub struct Registry(pub Container);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_COMMANDS;
+}
 
 fn set(cmd: Pythonic, vm: &VirtualMachine) -> PyResult<()> {
     let mut container = list(vm)?;
     if container.contains_key(&cmd.name) {
         let msg = format!("CLI command already exists: {}", cmd.name);
-        return Err(vm.new_value_error(msg));
-    } else {
-        container.insert(cmd.name.clone(), cmd);
+        return Err(py::registration_error(vm, msg));
+    }
+    // Aliases share the namespace with the command names.
+    let taken = |name: &String| {
+        container
+            .values()
+            .any(|c| &c.name == name || c.aliases.contains(name))
+    };
+    if let Some(alias) = cmd.aliases.iter().find(|a| taken(a) || **a == cmd.name) {
+        let msg = format!("CLI command alias already exists: {}", alias);
+        return Err(py::registration_error(vm, msg));
+    }
+    if let Some(other) = container.values().find(|c| c.aliases.contains(&cmd.name)) {
+        let msg = format!(
+            "CLI command name is an alias of '{}': {}",
+            other.name, cmd.name
+        );
+        return Err(py::registration_error(vm, msg));
+    }
+    if let Some(timeout) = cmd.timeout.filter(|t| !t.is_finite() || *t <= 0.0) {
+        let msg = format!("CLI command timeout must be positive: {}", timeout);
+        return Err(py::registration_error(vm, msg));
+    }
+    if !cmd.retry_delay.is_finite() || cmd.retry_delay < 0.0 {
+        let msg = format!(
+            "CLI command retry delay must not be negative: {}",
+            cmd.retry_delay
+        );
+        return Err(py::registration_error(vm, msg));
+    }
+    if let Some(other) = container.values().find(|c| c.default && cmd.default) {
+        let msg = format!("Default CLI command already exists: {}", other.name);
+        return Err(py::registration_error(vm, msg));
     }
-    py::set(vm, consts::GLOBAL_KEY, container)
+    container.insert(cmd.name.clone(), cmd);
+    py::provide(vm, Registry(container))
+}
+
+/// Removes the commands declared by the module, so it can be loaded again.
+pub fn forget(module: &str, vm: &VirtualMachine) -> PyResult<()> {
+    let mut container = list(vm)?;
+    container.retain(|_, cmd| {
+        let owner = cmd
+            .function
+            .as_ref()
+            .and_then(|f| f.get_attr("__module__", vm).ok())
+            .and_then(|m| m.str(vm).ok());
+        owner.is_none_or(|m| m.as_str() != module)
+    });
+    py::provide(vm, Registry(container))
 }
 
-fn list(vm: &VirtualMachine) -> PyResult<Container> {
-    match py::get::<Container>(vm, consts::GLOBAL_KEY)? {
+pub fn list(vm: &VirtualMachine) -> PyResult<Container> {
+    match py::service::<Registry>(vm)? {
         None => Ok(Default::default()),
-        Some(v) => Ok(v.deref().clone()),
+        Some(v) => Ok(v.0.clone()),
     }
 }
 
@@ -43,21 +94,63 @@ fn list(vm: &VirtualMachine) -> PyResult<Container> {
 // Decorator
 //////////////////////////////////////////////////////////////////
 
-#[derive(FromArgs, Debug, Clone, Builder)]
+#[derive(FromArgs, Debug, Builder)]
 pub struct DecoArgs {
     #[pyarg(positional)]
     pub name: String,
 
     #[pyarg(any, optional, default = "None")]
     pub help: Option<String>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub inputs: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub outputs: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub deps: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub finally_: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub aliases: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "false")]
+    pub hidden: bool,
+
+    #[pyarg(any, optional, default = "None")]
+    pub category: Option<String>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub deprecated: Option<String>,
+
+    #[pyarg(any, optional, default = "false")]
+    pub default: bool,
+
+    #[pyarg(any, optional, default = "None")]
+    pub timeout: Option<ArgIntoFloat>,
+
+    #[pyarg(any, optional, default = "0")]
+    pub retries: u32,
+
+    #[pyarg(any, optional, default = "None")]
+    pub retry_delay: Option<ArgIntoFloat>,
 }
 
 pub struct Decorator {}
 
 impl Function for Decorator {
     const NAME: &'static str = consts::DECORATOR_CMD;
-    const METHOD: PyMethodDef =
-        PyMethodDef::new_const(Self::NAME, Self::decorate, PyMethodFlags::empty(), None);
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "cmd(name, help=None, inputs=None, outputs=None, deps=None, finally_=None, aliases=None, hidden=False, category=None, deprecated=None, default=False, timeout=None, retries=0, retry_delay=0.0)\n--\n\nDeclare the CLI command, the decorated function is called on its invocation. Outputs of the command with 'outputs' patterns are cached and restored while the 'inputs' files and the option values are unchanged. Commands listed in 'deps' are run before, with default values. Commands listed in 'finally_' are run after, even if this one has failed. The 'category' groups commands in the help, 'hidden' ones are not listed there. The 'deprecated' message is printed when the command is used. The 'default' command is run by bare 'unimake'. The command running longer than 'timeout' seconds is interrupted (a blocking call like 'time.sleep' is interrupted once it returns, 'umk.process' calls are killed), the failed one is run again up to 'retries' times after 'retry_delay' seconds. Commands are also available at the top level ('unimake build'), unless the name is taken by the builtin command.",
+        ),
+    );
 }
 
 impl Decorator {
@@ -66,12 +159,27 @@ impl Decorator {
         let inner = vm.new_function(
             consts::DECORATOR_CMD_INNER,
             move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
-                let func = args.args.get(0).unwrap().clone();
+                let func = args.args.first().unwrap().clone();
                 let mut cmd = Pythonic::get(&func, vm)?;
                 cmd.name = inputs.name.clone();
                 cmd.help = inputs.help.clone();
+                cmd.inputs = inputs.inputs.clone().unwrap_or_default();
+                cmd.outputs = inputs.outputs.clone().unwrap_or_default();
+                cmd.deps = inputs.deps.clone().unwrap_or_default();
+                cmd.finally = inputs.finally_.clone().unwrap_or_default();
+                cmd.aliases = inputs.aliases.clone().unwrap_or_default();
+                cmd.hidden = inputs.hidden;
+                cmd.category = inputs.category.clone();
+                cmd.deprecated = inputs.deprecated.clone();
+                cmd.default = inputs.default;
+                cmd.timeout = inputs.timeout.as_deref().copied();
+                cmd.retries = inputs.retries;
+                cmd.retry_delay = inputs.retry_delay.as_deref().copied().unwrap_or_default();
                 cmd.function = Some(func.clone());
-                set(cmd, vm)?;
+                set(cmd, vm).map_err(|e| {
+                    let decorator = format!("@cli.cmd(\"{}\")", inputs.name);
+                    py::decorator_note(vm, e, &decorator, &func)
+                })?;
                 Pythonic::del(&func, vm)?;
                 func.to_pyresult(vm)
             },
@@ -94,8 +202,147 @@ impl py::Registerable for Decorator {
 pub struct Cacheable {
     pub name: String,
     pub options: HashMap<String, option::Cacheable>,
-    pub arguments: HashMap<String, argument::Cacheable>,
+    pub arguments: Vec<argument::Cacheable>,
     pub help: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    #[serde(default)]
+    pub deps: Vec<String>,
+    #[serde(default)]
+    pub finally: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub deprecated: Option<String>,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub retry_delay: f64,
+}
+
+impl From<&Pythonic> for Cacheable {
+    fn from(value: &Pythonic) -> Self {
+        Self {
+            name: value.name.clone(),
+            options: value
+                .options
+                .iter()
+                .map(|(k, v)| (k.clone(), option::Cacheable::from(v.clone())))
+                .collect(),
+            // Decorators are applied from the bottom to the top, so the
+            // last registered argument is the first one in the script.
+            arguments: value
+                .arguments
+                .iter()
+                .rev()
+                .map(|v| argument::Cacheable::from(v.clone()))
+                .collect(),
+            help: value.help.clone(),
+            inputs: value.inputs.clone(),
+            outputs: value.outputs.clone(),
+            deps: value.deps.clone(),
+            finally: value.finally.clone(),
+            aliases: value.aliases.clone(),
+            hidden: value.hidden,
+            category: value.category.clone(),
+            deprecated: value.deprecated.clone(),
+            default: value.default,
+            timeout: value.timeout,
+            retries: value.retries,
+            retry_delay: value.retry_delay,
+        }
+    }
+}
+
+impl From<&Cacheable> for clap::Command {
+    fn from(value: &Cacheable) -> Self {
+        let mut options: Vec<&option::Cacheable> = value.options.values().collect();
+        options.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut about = value.help.clone().unwrap_or_default();
+        if let Some(message) = &value.deprecated {
+            about = format!("{} [deprecated: {}]", about, message)
+                .trim_start()
+                .to_string();
+        }
+        let result = clap::Command::new(value.name.clone())
+            .about(about)
+            .visible_aliases(value.aliases.clone())
+            .hide(value.hidden)
+            .args(options.into_iter().map(clap::Arg::from))
+            .args(value.arguments.iter().map(clap::Arg::from));
+        // Set after the arguments, so it is the heading of the command
+        // itself rather than of its options, see 'categories'.
+        match &value.category {
+            Some(category) => result.next_help_heading(category.clone()),
+            None => result,
+        }
+    }
+}
+
+/// The default clap template with the commands list before the options,
+/// which is put into 'before_help' by 'categories'.
+pub const TEMPLATE: &str =
+    "{about-with-newline}\n{usage-heading} {usage}\n\n{before-help}{all-args}{after-help}";
+
+/// Heading of the commands without the category.
+const COMMANDS: &str = "Commands";
+
+/// Visible commands grouped by their categories (the help headings),
+/// 'None' if no command has the category. Commands without the category
+/// go first. Clap can't group subcommands under several headings, so
+/// the parent lists them in its 'before_help' and hides them.
+pub fn categories(commands: &[&clap::Command]) -> Option<String> {
+    let visible: Vec<&clap::Command> = commands
+        .iter()
+        .copied()
+        .filter(|c| !c.is_hide_set())
+        .collect();
+    if visible.iter().all(|c| c.get_next_help_heading().is_none()) {
+        return None;
+    }
+    let mut groups: Vec<(&str, Vec<&clap::Command>)> = Vec::new();
+    for cmd in visible.iter() {
+        let heading = cmd.get_next_help_heading().unwrap_or(COMMANDS);
+        match groups.iter_mut().find(|(h, _)| *h == heading) {
+            Some((_, group)) => group.push(cmd),
+            None => groups.push((heading, vec![cmd])),
+        }
+    }
+    groups.sort_by_key(|(h, _)| (*h != COMMANDS, *h));
+    let styles = clap::builder::Styles::default();
+    let (header, literal) = (styles.get_header(), styles.get_literal());
+    let width = visible
+        .iter()
+        .map(|c| c.get_name().len())
+        .max()
+        .unwrap_or(0);
+    let mut result = String::new();
+    for (heading, group) in groups {
+        result += &format!("{header}{heading}:{header:#}\n");
+        for cmd in group {
+            let mut about = cmd.get_about().map(|a| a.to_string()).unwrap_or_default();
+            let aliases: Vec<&str> = cmd.get_visible_aliases().collect();
+            if !aliases.is_empty() {
+                about = format!("{} [aliases: {}]", about, aliases.join(", "));
+            }
+            let name = format!("{:width$}", cmd.get_name());
+            let line = format!("  {literal}{name}{literal:#}  {}", about.trim());
+            result += line.trim_end();
+            result += "\n";
+        }
+        result += "\n";
+    }
+    Some(result)
 }
 
 //////////////////////////////////////////////////////////////////
@@ -106,9 +353,35 @@ pub struct Cacheable {
 pub struct Pythonic {
     pub name: String,
     pub options: HashMap<String, option::Pythonic>,
-    pub arguments: HashMap<String, argument::Pythonic>,
+    pub arguments: Vec<argument::Pythonic>,
     pub function: Option<PyObjectRef>,
     pub help: Option<String>,
+    /// Glob patterns of the files the command reads.
+    pub inputs: Vec<String>,
+    /// Glob patterns of the files the command produces.
+    pub outputs: Vec<String>,
+    /// Commands which are run before this one.
+    pub deps: Vec<String>,
+    /// Commands which are run after this one, even if it has failed.
+    pub finally: Vec<String>,
+    /// Other names of the command.
+    pub aliases: Vec<String>,
+    /// Hidden commands are not listed in the help.
+    pub hidden: bool,
+    /// Help section of the command.
+    pub category: Option<String>,
+    /// Message printed on every use, e.g. "use 'build' instead".
+    pub deprecated: Option<String>,
+    /// The default command is run when no command is given.
+    pub default: bool,
+    /// Seconds the function may run, it is interrupted after.
+    pub timeout: Option<f64>,
+    /// How many times the failed function is called again.
+    pub retries: u32,
+    /// Seconds between the retries.
+    pub retry_delay: f64,
+    /// Keyword arguments bound by the pattern rule.
+    pub bound: Vec<(String, PyObjectRef)>,
     pub types: Vec<PyTypeRef>,
 }
 
@@ -120,6 +393,19 @@ impl Default for Pythonic {
             arguments: Default::default(),
             function: Default::default(),
             help: Default::default(),
+            inputs: Default::default(),
+            outputs: Default::default(),
+            deps: Default::default(),
+            finally: Default::default(),
+            aliases: Default::default(),
+            hidden: Default::default(),
+            category: Default::default(),
+            deprecated: Default::default(),
+            default: Default::default(),
+            timeout: Default::default(),
+            retries: Default::default(),
+            retry_delay: Default::default(),
+            bound: Default::default(),
             types: vec![
                 PyStr::create_static_type(),
                 PyInt::create_static_type(),
@@ -146,6 +432,13 @@ impl Pythonic {
     }
 
     pub fn del(func: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        // Commands without options and arguments have no binding attribute.
+        if vm
+            .get_attribute_opt(func.clone(), consts::FUNC_ATTR)?
+            .is_none()
+        {
+            return Ok(());
+        }
         func.del_attr(consts::FUNC_ATTR, vm)
     }
 }
@@ -154,44 +447,80 @@ impl Pythonic {
     pub fn option(&mut self, value: option::Pythonic, vm: &VirtualMachine) -> PyResult<()> {
         if self.options.contains_key(&value.name) {
             let msg = format!("CLI command option already exists: '{}'", value.name);
-            return Err(vm.new_value_error(msg));
+            return Err(py::registration_error(vm, msg));
         }
         let allowed = self
             .types
             .iter()
-            .find(|&x| py::is_same_type(vm, x, &value.class))
-            .is_some();
+            .any(|x| py::is_same_type(vm, x, &value.class));
         if !allowed {
             let msg = format!(
                 "CLI command option has unsupported type '{}'",
                 value.class.name()
             );
-            return Err(vm.new_type_error(msg));
+            return Err(py::registration_error(vm, msg));
         }
         self.options.insert(value.name.clone(), value);
         Ok(())
     }
 
     pub fn argument(&mut self, value: argument::Pythonic, vm: &VirtualMachine) -> PyResult<()> {
-        if self.arguments.contains_key(&value.name) {
+        if self.arguments.iter().any(|x| x.name == value.name) {
             let msg = format!("CLI command argument already exists: '{}'", value.name);
-            return Err(vm.new_value_error(msg));
+            return Err(py::registration_error(vm, msg));
         }
         let allowed = self
             .types
             .iter()
-            .find(|&x| py::is_same_type(vm, x, &value.class))
-            .is_some();
+            .any(|x| py::is_same_type(vm, x, &value.class));
         if !allowed {
             let msg = format!(
                 "CLI command argument has unsupported type '{}'",
                 value.class.name()
             );
-            return Err(vm.new_type_error(msg));
+            return Err(py::registration_error(vm, msg));
         }
-        self.arguments.insert(value.name.clone(), value);
+        self.arguments.push(value);
         Ok(())
     }
+
+    /// Calls the command function with values parsed from the command line.
+    pub fn function(&self, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
+        match &self.function {
+            Some(v) => Ok(v.clone()),
+            None => {
+                let msg = format!("CLI command has no function: '{}'", self.name);
+                Err(vm.new_runtime_error(msg))
+            }
+        }
+    }
+
+    /// Values of the options, arguments and the rule bound arguments,
+    /// sorted by the keyword.
+    pub fn kwargs(
+        &self,
+        matches: &clap::ArgMatches,
+        vm: &VirtualMachine,
+    ) -> Vec<(String, PyObjectRef)> {
+        let options = self
+            .options
+            .values()
+            .filter_map(|o| o.value(matches, vm).map(|v| (o.key(), v)));
+        let arguments = self
+            .arguments
+            .iter()
+            .filter_map(|a| a.value(matches, vm).map(|v| (a.key(), v)));
+        let bound = self.bound.iter().cloned();
+        let mut result: Vec<_> = options.chain(arguments).chain(bound).collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    pub fn call(&self, kwargs: Vec<(String, PyObjectRef)>, vm: &VirtualMachine) -> PyResult {
+        let kwargs: KwArgs = kwargs.into_iter().collect();
+        self.function(vm)?
+            .call(FuncArgs::new(Vec::<PyObjectRef>::new(), kwargs), vm)
+    }
 }
 
 //////////////////////////////////////////////////////////////////
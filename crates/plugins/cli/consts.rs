@@ -1,8 +1,6 @@
 use const_format::concatcp;
-use rustpython_vm::common::lock::Lazy;
 
 pub const PREFIX: &str = concatcp!(global::kit::NAME, "/plugin/cli/");
-pub const GLOBAL_KEY: &str = concatcp!(global::kit::NAME, "/plugin/cli");
 pub const FUNC_ATTR: &str = concatcp!(PREFIX, "cmd/builder");
 
 pub const DECORATOR_CMD: &str = "cmd";
@@ -14,8 +12,24 @@ pub const DECORATOR_OPT_INNER: &str = concatcp!(PREFIX, DECORATOR_OPT, "/decorat
 pub const DECORATOR_ARG: &str = "arg";
 pub const DECORATOR_ARG_INNER: &str = concatcp!(PREFIX, DECORATOR_ARG, "/decorator");
 
-pub const PLUGIN_INFO: Lazy<plugin::Info> = Lazy::new(|| plugin::Info {
-    name: String::from("CLI"),
-    version: "0.1.0".to_string(),
-    description: "Allows users to declare its own command line interface".to_string(),
-});
+pub const DECORATOR_RULE: &str = "rule";
+pub const DECORATOR_RULE_INNER: &str = concatcp!(PREFIX, DECORATOR_RULE, "/decorator");
+
+pub const DECORATOR_BEFORE: &str = "before";
+pub const DECORATOR_BEFORE_INNER: &str = concatcp!(PREFIX, DECORATOR_BEFORE, "/decorator");
+
+pub const DECORATOR_AFTER: &str = "after";
+pub const DECORATOR_AFTER_INNER: &str = concatcp!(PREFIX, DECORATOR_AFTER, "/decorator");
+
+pub const SUBCOMMAND: &str = "cli";
+pub const CACHE_KEY: &str = "cli";
+pub const CACHE_SCHEMA: u32 = 1;
+pub const CACHE_KEY_HISTORY: &str = "cli.history";
+pub const SERVICE_COMMANDS: &str = "cli/commands";
+pub const SERVICE_RULES: &str = "cli/rules";
+pub const SERVICE_HOOKS: &str = "cli/hooks";
+pub const TIMING_TARGET: &str = "target";
+
+pub const PLUGIN_NAME: &str = "CLI";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str = "Allows users to declare its own command line interface";
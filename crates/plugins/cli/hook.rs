@@ -0,0 +1,196 @@
+use crate::consts;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::convert::{ToPyObject, ToPyResult};
+use rustpython_vm::function::{FuncArgs, KwArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::{PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+use std::fmt;
+
+/// Keyword arguments of the hooks besides the command options.
+pub const COMMAND: &str = "command";
+pub const RESULT: &str = "result";
+pub const EXCEPTION: &str = "exception";
+
+//////////////////////////////////////////////////////////////////
+// Access
+//////////////////////////////////////////////////////////////////
+
+/// Declared hooks in the declaration order.
+#[derive(Debug, Clone, Default)]
+pub struct Registry(pub Vec<Pythonic>);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_HOOKS;
+}
+
+pub fn list(vm: &VirtualMachine) -> PyResult<Vec<Pythonic>> {
+    match py::service::<Registry>(vm)? {
+        None => Ok(Default::default()),
+        Some(v) => Ok(v.0.clone()),
+    }
+}
+
+/// Removes the hooks declared by the module, so it can be loaded again.
+pub fn forget(module: &str, vm: &VirtualMachine) -> PyResult<()> {
+    let mut hooks = list(vm)?;
+    hooks.retain(|hook| {
+        let owner = hook
+            .function
+            .get_attr("__module__", vm)
+            .ok()
+            .and_then(|m| m.str(vm).ok());
+        owner.is_none_or(|m| m.as_str() != module)
+    });
+    py::provide(vm, Registry(hooks))
+}
+
+//////////////////////////////////////////////////////////////////
+// Decorators
+//////////////////////////////////////////////////////////////////
+
+pub struct Before {}
+
+impl Function for Before {
+    const NAME: &'static str = consts::DECORATOR_BEFORE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "before(command)\n--\n\nDeclare the function called before the CLI command (its name or alias). The function may accept the 'command' name and the option and argument values as keyword arguments. If the hook fails, the command is not called and fails too. The hook is not called if the command outputs are restored from the artifact cache, '--explain' lists such hooks.",
+        ),
+    );
+}
+
+impl Before {
+    fn decorate(command: String, vm: &VirtualMachine) -> PyResult {
+        decorator(Kind::Before, command, vm)
+    }
+}
+
+impl py::Registerable for Before {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct After {}
+
+impl Function for After {
+    const NAME: &'static str = consts::DECORATOR_AFTER;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "after(command)\n--\n\nDeclare the function called after the CLI command (its name or alias), even if it has failed. The function may accept the 'command' name, the option and argument values, the returned 'result' and the raised 'exception' (None on success) as keyword arguments. The failed hook fails the succeeded command, the failure of the failed command is kept and the hook failure is reported as a warning. The hook is not called if the command outputs are restored from the artifact cache, '--explain' lists such hooks.",
+        ),
+    );
+}
+
+impl After {
+    fn decorate(command: String, vm: &VirtualMachine) -> PyResult {
+        decorator(Kind::After, command, vm)
+    }
+}
+
+impl py::Registerable for After {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+fn decorator(kind: Kind, command: String, vm: &VirtualMachine) -> PyResult {
+    let name = match kind {
+        Kind::Before => consts::DECORATOR_BEFORE_INNER,
+        Kind::After => consts::DECORATOR_AFTER_INNER,
+    };
+    let inner = vm.new_function(
+        name,
+        move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+            let func = args.args.first().unwrap().clone();
+            let decorator = format!("@cli.{}(\"{}\")", kind, command);
+            register(kind, &command, &func, vm)
+                .map_err(|e| py::decorator_note(vm, e, &decorator, &func))?;
+            func.to_pyresult(vm)
+        },
+    );
+    inner.to_pyobject(vm).to_pyresult(vm)
+}
+
+fn register(kind: Kind, command: &str, func: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    let signature = py::Signature::of(func.clone(), vm).map_err(|e| {
+        let msg = format!("Hook function is not inspectable: {}", e.message());
+        py::registration_error(vm, msg)
+    })?;
+    let parameters = match signature.variadic_keyword() {
+        Some(_) => None,
+        None => Some(
+            signature
+                .iter()
+                .filter(|a| a.is_regular() || a.is_keyword())
+                .map(|a| a.name().to_string())
+                .collect(),
+        ),
+    };
+    let mut hooks = list(vm)?;
+    hooks.push(Pythonic {
+        kind,
+        command: command.to_string(),
+        parameters,
+        function: func.clone(),
+    });
+    py::provide(vm, Registry(hooks))
+}
+
+//////////////////////////////////////////////////////////////////
+// Hook
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Before,
+    After,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Before => f.write_str(consts::DECORATOR_BEFORE),
+            Kind::After => f.write_str(consts::DECORATOR_AFTER),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pythonic {
+    pub kind: Kind,
+    /// Name or alias of the command.
+    pub command: String,
+    /// Keyword arguments the function accepts, 'None' if any.
+    pub parameters: Option<Vec<String>>,
+    pub function: PyObjectRef,
+}
+
+impl Pythonic {
+    /// Name of the function for messages.
+    pub fn name(&self, vm: &VirtualMachine) -> String {
+        self.function
+            .get_attr("__name__", vm)
+            .and_then(|n| n.str(vm))
+            .map(|n| n.to_string())
+            .unwrap_or_else(|_| String::from("<unknown>"))
+    }
+
+    /// Calls the function with the keyword arguments it accepts.
+    pub fn call(&self, kwargs: &[(String, PyObjectRef)], vm: &VirtualMachine) -> PyResult {
+        let kwargs: KwArgs = kwargs
+            .iter()
+            .filter(|(k, _)| self.parameters.as_ref().is_none_or(|p| p.contains(k)))
+            .cloned()
+            .collect();
+        self.function
+            .call(FuncArgs::new(Vec::<PyObjectRef>::new(), kwargs), vm)
+    }
+}
@@ -1,8 +1,24 @@
 mod argument;
+mod capture;
 mod class;
 mod command;
 mod consts;
+mod hook;
 mod option;
 mod plugin;
+mod rule;
+mod target;
 
+pub use argument::Cacheable as ArgumentInfo;
+pub use class::Class;
+pub use command::Cacheable as CommandInfo;
+pub use command::Registry as Commands;
+pub use command::TEMPLATE as HELP_TEMPLATE;
+pub use command::categories;
+pub use consts::SUBCOMMAND;
+pub use option::Cacheable as OptionInfo;
 pub use plugin::Instance;
+pub use plugin::commands;
+pub use rule::Cacheable as RuleInfo;
+pub use rule::Registry as Rules;
+pub use rule::makes;
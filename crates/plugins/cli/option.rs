@@ -1,7 +1,6 @@
-use crate::class::Class;
+use crate::class::{self, Class};
 use crate::{command, consts};
 use builder_pattern::Builder;
-use const_format::concatcp;
 use rustpython_vm::builtins::{PyModule, PyTypeRef};
 use rustpython_vm::convert::{ToPyObject, ToPyResult};
 use rustpython_vm::function::{FuncArgs, PyMethodDef, PyMethodFlags};
@@ -29,7 +28,7 @@ pub struct Pythonic {
     pub short: Option<String>,
 
     #[pyarg(any, optional, default = "None")]
-    pub fault: Option<PyObjectRef>,
+    pub default: Option<PyObjectRef>,
 
     #[pyarg(any, optional, default = "None")]
     pub help: Option<String>,
@@ -45,8 +44,14 @@ pub struct Decorator {}
 
 impl Function for Decorator {
     const NAME: &'static str = consts::DECORATOR_OPT;
-    const METHOD: PyMethodDef =
-        PyMethodDef::new_const(Self::NAME, Self::decorate, PyMethodFlags::empty(), None);
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "opt(type, name, short=None, default=None, help=None, var=None, required=False)\n--\n\nDeclare the CLI command option.",
+        ),
+    );
 }
 
 impl Decorator {
@@ -54,11 +59,14 @@ impl Decorator {
         let inputs = Arc::new(py::args_to::<Pythonic>(inputs.clone(), vm)?);
         // TODO validate short name, long name and var
         let inner = vm.new_function(
-            concatcp!("cli::", Decorator::NAME),
+            consts::DECORATOR_OPT_INNER,
             move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
-                let func = args.args.get(0).unwrap().clone();
+                let func = args.args.first().unwrap().clone();
                 let mut cmd = command::Pythonic::get(&func, vm)?;
-                cmd.option(inputs.deref().clone(), vm)?;
+                cmd.option(inputs.deref().clone(), vm).map_err(|e| {
+                    let decorator = format!("@cli.opt(\"{}\")", inputs.name);
+                    py::decorator_note(vm, e, &decorator, &func)
+                })?;
                 command::Pythonic::set(&func, cmd, vm)?;
                 func.to_pyresult(vm)
             },
@@ -73,6 +81,24 @@ impl py::Registerable for Decorator {
     }
 }
 
+impl Pythonic {
+    /// Name of the python function keyword argument.
+    pub fn key(&self) -> String {
+        match &self.variable {
+            Some(v) => v.clone(),
+            None => self.name.replace('-', "_"),
+        }
+    }
+
+    pub fn value(&self, matches: &clap::ArgMatches, vm: &VirtualMachine) -> Option<PyObjectRef> {
+        let class = Class::from(self.class.clone());
+        match class.extract(matches, &self.name, vm) {
+            Some(v) => Some(v),
+            None => self.default.clone(),
+        }
+    }
+}
+
 //////////////////////////////////////////////////////////////////
 // Cache
 //////////////////////////////////////////////////////////////////
@@ -85,6 +111,8 @@ pub struct Cacheable {
     pub help: Option<String>,
     pub variable: Option<String>,
     pub required: bool,
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
 }
 
 impl From<Pythonic> for Cacheable {
@@ -95,6 +123,7 @@ impl From<Pythonic> for Cacheable {
             short: None,
             help: value.help.clone(),
             variable: value.variable.clone(),
+            default: value.default.as_ref().map(class::literal),
             required: value.required,
         };
         if let Some(short) = value.short {
@@ -103,3 +132,20 @@ impl From<Pythonic> for Cacheable {
         result
     }
 }
+
+impl From<&Cacheable> for clap::Arg {
+    fn from(value: &Cacheable) -> Self {
+        let mut result = clap::Arg::new(value.name.clone())
+            .long(value.name.clone())
+            .required(value.required)
+            .help(value.help.clone().unwrap_or_default())
+            .value_parser(value.class.parser());
+        if let Class::Boolean = value.class {
+            result = result.action(clap::ArgAction::SetTrue);
+        }
+        if let Some(short) = value.short {
+            result = result.short(short);
+        }
+        result
+    }
+}
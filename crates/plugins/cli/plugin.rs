@@ -1,4 +1,4 @@
-use crate::{argument, command, option};
+use crate::{argument, capture, command, consts, hook, option, rule, target};
 use clap::Command;
 use plugin::Interface as PluginInterface;
 use rustpython::vm::pymodule;
@@ -7,45 +7,141 @@ use rustpython_vm::class::PyClassImpl;
 use rustpython_vm::{PyRef, VirtualMachine};
 use sdk::py;
 use sdk::py::{Registerable, Registerer};
+use std::collections::HashMap;
+
+type Cache = HashMap<String, command::Cacheable>;
 
 #[derive(Default)]
 pub struct Instance {}
 
-impl Drop for Instance {
-    fn drop(&mut self) {}
-}
-
 impl Registerable for Instance {
     fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
         module.register::<option::Decorator>(vm);
         module.register::<argument::Decorator>(vm);
         module.register::<command::Decorator>(vm);
+        module.register::<rule::Decorator>(vm);
+        module.register::<hook::Before>(vm);
+        module.register::<hook::After>(vm);
     }
 }
 
 impl PluginInterface for Instance {
-    fn initialize(&self, vm: &VirtualMachine) {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result {
         command::Binding::make_class(&vm.ctx);
+        capture::Stream::make_class(&vm.ctx);
+        Ok(())
     }
 
     fn info(&self) -> plugin::Info {
         plugin::Info {
-            name: "CLI".to_string(),
-            version: "0.1.0".to_string(),
-            description: "Allows users to declare its own command line interface".to_string(),
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
         }
     }
 
     fn examples(&self) -> Vec<String> {
-        todo!()
+        vec![
+            String::from(
+                r#"from umk import cli
+
+
+@cli.cmd("copy", help="Copy file")
+@cli.opt(str, "src", default="a.txt", help="Source file")
+@cli.opt(str, "dst", default="b.txt", help="Destination file")
+def copy(src: str, dst: str):
+    print(f"Copy {src} to {dst}")
+"#,
+            ),
+            String::from(
+                r#"import os
+import subprocess
+from umk import cli
+
+
+# Outputs are restored from the cache while sources and options are the same
+@cli.cmd("build", inputs=["src/*.c"], outputs=["build/app"])
+@cli.opt(bool, "release", default=False)
+def build(release: bool):
+    flags = ["-O2"] if release else ["-g"]
+    os.makedirs("build", exist_ok=True)
+    subprocess.run(["cc", *flags, "-o", "build/app", "src/main.c"], check=True)
+"#,
+            ),
+        ]
     }
 
-    fn cli(&self, driver: &Box<dyn sdk::CacheDriver>) -> Option<Command> {
-        todo!()
+    fn cli(&self, cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        let Some(commands) = cached(cache)? else {
+            return Ok(None);
+        };
+        let mut commands: Vec<&command::Cacheable> = commands.values().collect();
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        let commands: Vec<Command> = commands.into_iter().map(Command::from).collect();
+        let listing = command::categories(&commands.iter().collect::<Vec<_>>());
+        let grouped = listing.is_some();
+        let mut result = Command::new(consts::SUBCOMMAND)
+            .about("Commands declared in the workspace")
+            .after_help(
+                "Files of the pattern rules are made by their paths, e.g. 'cli build/main.o'",
+            )
+            .arg_required_else_help(true)
+            .disable_help_subcommand(true)
+            .allow_external_subcommands(true)
+            .external_subcommand_value_parser(clap::value_parser!(String))
+            .subcommands(commands.into_iter().map(|c| {
+                let hidden = grouped || c.is_hide_set();
+                c.hide(hidden)
+            }));
+        if let Some(listing) = listing {
+            result = result.help_template(command::TEMPLATE).before_help(listing);
+        }
+        Ok(Some(result))
     }
 
-    fn cache(&self, driver: &Box<dyn sdk::CacheDriver>) {
-        todo!()
+    fn cache(&self, cache: &sdk::Cache, vm: &VirtualMachine) -> sdk::Result {
+        let commands = command::list(vm).map_err(|e| sdk::Error::python(e, vm))?;
+        let value: Cache = commands
+            .iter()
+            .map(|(k, v)| (k.clone(), command::Cacheable::from(v)))
+            .collect();
+        sdk::CacheEntry::<Cache>::new(consts::CACHE_KEY, cache.clone())
+            .with_schema(consts::CACHE_SCHEMA)
+            .set(value)
+    }
+
+    fn call(&self, parent: &clap::ArgMatches, vm: &VirtualMachine) -> sdk::Result {
+        let Some((name, matches)) = parent.subcommand() else {
+            return Ok(());
+        };
+        let python = |e| sdk::Error::python(e, vm);
+        let commands = command::list(vm).map_err(python)?;
+        let rules = rule::list(vm).map_err(python)?;
+        let targets = target::Targets {
+            commands: &commands,
+            rules: &rules,
+        };
+        if commands.contains_key(name) {
+            return target::Runner::new(targets, matches, vm)?
+                .run(&[name.to_string()], Some(matches));
+        }
+        // Unknown subcommands are paths of the files made by the rules.
+        let mut names = vec![name.to_string()];
+        names.extend(
+            matches
+                .get_many::<String>("")
+                .into_iter()
+                .flatten()
+                .cloned(),
+        );
+        if let Some(option) = names.iter().find(|n| n.starts_with('-')) {
+            let message = format!(
+                "Unexpected '{}' after the file paths, pass options before them",
+                option
+            );
+            return Err(sdk::Error::new(sdk::ErrorKind::Generic, message));
+        }
+        target::Runner::new(targets, parent, vm)?.run(&names, None)
     }
 
     fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
@@ -53,9 +149,71 @@ impl PluginInterface for Instance {
         module.register::<option::Decorator>(vm);
         module.register::<argument::Decorator>(vm);
         module.register::<command::Decorator>(vm);
+        module.register::<rule::Decorator>(vm);
+        module.register::<hook::Before>(vm);
+        module.register::<hook::After>(vm);
         py::register::submodule(vm, root, module);
     }
+
+    /// The script may be loaded again (e.g. in the watch mode), its
+    /// previous commands must not clash with the new ones.
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        let python = |e| sdk::Error::python(e, vm);
+        command::forget(global::workspace::SCRIPT, vm).map_err(python)?;
+        rule::forget(global::workspace::SCRIPT, vm).map_err(python)?;
+        hook::forget(global::workspace::SCRIPT, vm).map_err(python)
+    }
+
+    /// Hooks and the 'deps' and 'finally_' names may be declared before
+    /// their commands, so they are checked once the script is loaded.
+    fn after_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        let python = |e| sdk::Error::python(e, vm);
+        let commands = command::list(vm).map_err(python)?;
+        let rules = rule::list(vm).map_err(python)?;
+        target::validate(&target::Targets {
+            commands: &commands,
+            rules: &rules,
+        })?;
+        for hook in hook::list(vm).map_err(python)? {
+            let known = commands
+                .values()
+                .any(|c| c.name == hook.command || c.aliases.contains(&hook.command));
+            if !known {
+                return Err(sdk::Error::new(
+                    sdk::ErrorKind::Registration,
+                    format!(
+                        "Hook '{}' refers to unknown CLI command '{}'",
+                        hook.name(vm),
+                        hook.command
+                    ),
+                )
+                .with_note(format!(
+                    "while applying '@cli.{}(\"{}\")'",
+                    hook.kind, hook.command
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
+fn cached(cache: &sdk::Cache) -> sdk::Result<Option<Cache>> {
+    sdk::CacheEntry::<Cache>::new(consts::CACHE_KEY, cache.clone())
+        .with_schema(consts::CACHE_SCHEMA)
+        .get()
+}
+
+/// Cached workspace commands sorted by their names.
+pub fn commands(cache: &sdk::Cache) -> sdk::Result<Vec<command::Cacheable>> {
+    let mut result: Vec<command::Cacheable> =
+        cached(cache)?.unwrap_or_default().into_values().collect();
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Help
+//////////////////////////////////////////////////////////////////
+
 #[pymodule(name = "cli")]
 mod _module {}
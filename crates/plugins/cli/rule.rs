@@ -0,0 +1,272 @@
+use crate::{command, consts};
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::convert::{ToPyObject, ToPyResult};
+use rustpython_vm::function::{FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::{FromArgs, PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Keyword arguments the rule function may accept.
+const TARGET: &str = "target";
+const STEM: &str = "stem";
+const INPUTS: &str = "inputs";
+
+//////////////////////////////////////////////////////////////////
+// Access
+//////////////////////////////////////////////////////////////////
+
+/// Declared pattern rules in the declaration order.
+#[derive(Debug, Clone, Default)]
+pub struct Registry(pub Vec<Pythonic>);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_RULES;
+}
+
+pub fn list(vm: &VirtualMachine) -> PyResult<Vec<Pythonic>> {
+    match py::service::<Registry>(vm)? {
+        None => Ok(Default::default()),
+        Some(v) => Ok(v.0.clone()),
+    }
+}
+
+/// Removes the rules declared by the module, so it can be loaded again.
+pub fn forget(module: &str, vm: &VirtualMachine) -> PyResult<()> {
+    let mut rules = list(vm)?;
+    rules.retain(|rule| {
+        let owner = rule
+            .function
+            .get_attr("__module__", vm)
+            .ok()
+            .and_then(|m| m.str(vm).ok());
+        owner.is_none_or(|m| m.as_str() != module)
+    });
+    py::provide(vm, Registry(rules))
+}
+
+//////////////////////////////////////////////////////////////////
+// Decorator
+//////////////////////////////////////////////////////////////////
+
+#[derive(FromArgs, Debug, Clone)]
+pub struct DecoArgs {
+    #[pyarg(positional)]
+    pub pattern: String,
+
+    #[pyarg(any, optional, default = "None")]
+    pub inputs: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub deps: Option<Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub help: Option<String>,
+}
+
+pub struct Decorator {}
+
+impl Function for Decorator {
+    const NAME: &'static str = consts::DECORATOR_RULE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::decorate,
+        PyMethodFlags::empty(),
+        Some(
+            "rule(pattern, inputs=None, deps=None, help=None)\n--\n\nDeclare the pattern rule making files like 'build/%.o' from 'inputs' like 'src/%.c', the '%' is the stem. The file is requested by its path ('unimake build/main.o', or 'unimake cli build/main.o' if the path is taken by the command) or listed in 'deps' of commands and rules. The function may accept the 'target', 'stem' and 'inputs' keyword arguments.",
+        ),
+    );
+}
+
+impl Decorator {
+    fn decorate(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult {
+        let inputs = Arc::new(py::args_to::<DecoArgs>(inputs.clone(), vm)?);
+        let inner = vm.new_function(
+            consts::DECORATOR_RULE_INNER,
+            move |args: FuncArgs, vm: &VirtualMachine| -> PyResult {
+                let func = args.args.first().unwrap().clone();
+                let decorator = format!("@cli.rule(\"{}\")", inputs.pattern);
+                register(&inputs, &func, vm)
+                    .map_err(|e| py::decorator_note(vm, e, &decorator, &func))?;
+                func.to_pyresult(vm)
+            },
+        );
+        inner.to_pyobject(vm).to_pyresult(vm)
+    }
+}
+
+impl py::Registerable for Decorator {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+fn register(inputs: &DecoArgs, func: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    if inputs.pattern.matches('%').count() != 1 {
+        let msg = format!(
+            "Pattern rule target must contain one '%': '{}'",
+            inputs.pattern
+        );
+        return Err(py::registration_error(vm, msg));
+    }
+    let mut rules = list(vm)?;
+    if rules.iter().any(|r| r.pattern == inputs.pattern) {
+        let msg = format!("Pattern rule already exists: '{}'", inputs.pattern);
+        return Err(py::registration_error(vm, msg));
+    }
+    let signature = py::Signature::of(func.clone(), vm).map_err(|e| {
+        let msg = format!("Pattern rule function is not inspectable: {}", e.message());
+        py::registration_error(vm, msg)
+    })?;
+    let any = signature.variadic_keyword().is_some();
+    let parameters = [TARGET, STEM, INPUTS]
+        .into_iter()
+        .filter(|p| any || signature.iter().any(|a| a.name() == *p))
+        .map(String::from)
+        .collect();
+    rules.push(Pythonic {
+        pattern: inputs.pattern.clone(),
+        inputs: inputs.inputs.clone().unwrap_or_default(),
+        deps: inputs.deps.clone().unwrap_or_default(),
+        help: inputs.help.clone(),
+        parameters,
+        function: func.clone(),
+    });
+    py::provide(vm, Registry(rules))
+}
+
+//////////////////////////////////////////////////////////////////
+// Rule
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone)]
+pub struct Pythonic {
+    /// Path of the files the rule makes with one '%'.
+    pub pattern: String,
+    /// Input paths, the '%' is replaced with the stem.
+    pub inputs: Vec<String>,
+    /// Commands or files which are made before, the '%' is replaced too.
+    pub deps: Vec<String>,
+    pub help: Option<String>,
+    /// Keyword arguments the function accepts.
+    pub parameters: Vec<String>,
+    pub function: PyObjectRef,
+}
+
+impl Pythonic {
+    /// Part of the path matched by the '%', it is never empty.
+    pub fn stem<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let (prefix, suffix) = self.pattern.split_once('%')?;
+        let stem = path.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        (!stem.is_empty()).then_some(stem)
+    }
+
+    /// Command which makes the target, its inputs are not checked.
+    pub fn instance(&self, target: &str, stem: &str, vm: &VirtualMachine) -> command::Pythonic {
+        let inputs: Vec<String> = self.inputs.iter().map(|i| i.replace('%', stem)).collect();
+        let mut kwargs = Vec::new();
+        for parameter in self.parameters.iter() {
+            let value = match parameter.as_str() {
+                TARGET => vm.new_pyobj(target),
+                STEM => vm.new_pyobj(stem),
+                _ => vm.new_pyobj(
+                    inputs
+                        .iter()
+                        .map(|i| vm.new_pyobj(i.as_str()))
+                        .collect::<Vec<_>>(),
+                ),
+            };
+            kwargs.push((parameter.clone(), value));
+        }
+        command::Pythonic {
+            name: target.to_string(),
+            help: self.help.clone(),
+            outputs: vec![target.to_string()],
+            deps: self.deps.iter().map(|d| d.replace('%', stem)).collect(),
+            inputs,
+            bound: kwargs,
+            function: Some(self.function.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Finds the rule which makes the file. Rules with the shortest stem are
+/// preferred, then the ones declared first. Rules whose inputs neither
+/// exist nor can be made are skipped, unless there is no other rule:
+/// then the missing input is reported when the target is planned.
+pub fn find<'a>(rules: &'a [Pythonic], path: &str) -> Option<(&'a Pythonic, String)> {
+    let candidates = candidates(rules, path);
+    let viable = candidates
+        .iter()
+        .find(|(rule, stem)| {
+            rule.inputs
+                .iter()
+                .all(|i| available(rules, &i.replace('%', stem), &mut vec![path.to_string()]))
+        })
+        .or(candidates.first());
+    viable.map(|(rule, stem)| (*rule, stem.clone()))
+}
+
+/// Whether the declared rules make the file, e.g. the path given at the
+/// top level of the command line.
+pub fn makes(vm: &VirtualMachine, path: &str) -> PyResult<bool> {
+    Ok(find(&list(vm)?, path).is_some())
+}
+
+/// Whether some rule can make the file (the file itself may exist).
+pub fn makeable(rules: &[Pythonic], path: &str) -> bool {
+    makeable_inner(rules, path, &mut Vec::new())
+}
+
+fn candidates<'a>(rules: &'a [Pythonic], path: &str) -> Vec<(&'a Pythonic, String)> {
+    let mut result: Vec<(&Pythonic, String)> = rules
+        .iter()
+        .filter_map(|r| r.stem(path).map(|s| (r, s.to_string())))
+        .collect();
+    result.sort_by_key(|(_, stem)| stem.len());
+    result
+}
+
+fn available(rules: &[Pythonic], path: &str, visiting: &mut Vec<String>) -> bool {
+    Path::new(path).exists() || makeable_inner(rules, path, visiting)
+}
+
+fn makeable_inner(rules: &[Pythonic], path: &str, visiting: &mut Vec<String>) -> bool {
+    if visiting.iter().any(|p| p == path) {
+        return false;
+    }
+    visiting.push(path.to_string());
+    let result = candidates(rules, path).iter().any(|(rule, stem)| {
+        rule.inputs
+            .iter()
+            .all(|i| available(rules, &i.replace('%', stem), visiting))
+    });
+    visiting.pop();
+    result
+}
+
+//////////////////////////////////////////////////////////////////
+// Cache
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cacheable {
+    pub pattern: String,
+    pub inputs: Vec<String>,
+    pub deps: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl From<&Pythonic> for Cacheable {
+    fn from(value: &Pythonic) -> Self {
+        Self {
+            pattern: value.pattern.clone(),
+            inputs: value.inputs.clone(),
+            deps: value.deps.clone(),
+            help: value.help.clone(),
+        }
+    }
+}
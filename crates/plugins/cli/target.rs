@@ -0,0 +1,1070 @@
+use crate::capture::Capture;
+use crate::command::{Cacheable, Container, Pythonic};
+use crate::{consts, hook, rule};
+use rustpython_vm::{PyObjectRef, PyResult, VirtualMachine};
+use sdk::py;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//////////////////////////////////////////////////////////////////
+// Fingerprint
+//////////////////////////////////////////////////////////////////
+
+/// Everything the outputs of the command depend on, the artifact key
+/// is its hash. The last fingerprint of each command is kept to explain
+/// why the outputs are not restored.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Fingerprint {
+    /// Hash of the command declaration.
+    pub definition: String,
+    /// Hash of the function source.
+    pub source: String,
+    /// Representations of the option and argument values.
+    pub options: BTreeMap<String, String>,
+    /// Hashes of the input files.
+    pub inputs: BTreeMap<String, String>,
+    /// Overrides of the workspace settings, the command may read any.
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+type History = BTreeMap<String, Fingerprint>;
+
+impl Fingerprint {
+    pub fn new(
+        cmd: &Pythonic,
+        kwargs: &[(String, PyObjectRef)],
+        artifacts: &sdk::Artifacts,
+        vm: &VirtualMachine,
+    ) -> sdk::Result<Self> {
+        let definition = serde_json::to_value(Cacheable::from(cmd))?.to_string();
+        let function = cmd.function(vm).map_err(|e| sdk::Error::python(e, vm))?;
+        let source = vm
+            .import("inspect", 0)
+            .and_then(|inspect| vm.call_method(&inspect, "getsource", (function.clone(),)))
+            .or_else(|_| {
+                let code = function.get_attr("__code__", vm)?.get_attr("co_code", vm)?;
+                code.repr(vm).map(|v| v.into())
+            })
+            .and_then(|v| v.str(vm))
+            .map_err(|e| sdk::Error::python(e, vm))?;
+        Ok(Self {
+            definition: sdk::digest(definition.as_bytes()),
+            source: sdk::digest(source.as_str().as_bytes()),
+            options: represent(kwargs, vm)?,
+            inputs: artifacts.hashes(&cmd.inputs)?,
+            settings: settings(vm)?,
+        })
+    }
+
+    pub fn key(&self) -> sdk::Result<String> {
+        let mut key = sdk::ArtifactKey::new();
+        key.update(serde_json::to_string(self)?);
+        Ok(key.finish())
+    }
+
+    /// Differences from the previous fingerprint.
+    pub fn changes(&self, previous: &Fingerprint) -> Vec<String> {
+        let mut result = Vec::new();
+        if self.definition != previous.definition {
+            result.push("command declaration changed".to_string());
+        }
+        if self.source != previous.source {
+            result.push("function source changed".to_string());
+        }
+        for (name, value) in self.options.iter() {
+            match previous.options.get(name) {
+                Some(old) if old == value => {}
+                Some(old) => {
+                    result.push(format!("option '{}' changed: {} -> {}", name, old, value))
+                }
+                None => result.push(format!("option '{}' is set: {}", name, value)),
+            }
+        }
+        for name in previous.options.keys() {
+            if !self.options.contains_key(name) {
+                result.push(format!("option '{}' is not set", name));
+            }
+        }
+        for (name, value) in self.settings.iter() {
+            match previous.settings.get(name) {
+                Some(old) if old == value => {}
+                Some(old) => {
+                    result.push(format!("setting '{}' changed: {} -> {}", name, old, value))
+                }
+                None => result.push(format!("setting '{}' is set: {}", name, value)),
+            }
+        }
+        for name in previous.settings.keys() {
+            if !self.settings.contains_key(name) {
+                result.push(format!("setting '{}' is not set", name));
+            }
+        }
+        for (file, hash) in self.inputs.iter() {
+            match previous.inputs.get(file) {
+                Some(old) if old == hash => {}
+                Some(_) => result.push(format!("input '{}' changed", file)),
+                None => result.push(format!("input '{}' added", file)),
+            }
+        }
+        for file in previous.inputs.keys() {
+            if !self.inputs.contains_key(file) {
+                result.push(format!("input '{}' removed", file));
+            }
+        }
+        result
+    }
+}
+
+/// Strings are taken as is, so '--set jobs=8' and 'jobs = 8' in the
+/// config file give the same fingerprint.
+fn settings(vm: &VirtualMachine) -> sdk::Result<BTreeMap<String, String>> {
+    let settings = py::service::<sdk::Settings>(vm).map_err(|e| sdk::Error::python(e, vm))?;
+    Ok(settings
+        .map(|s| s.values())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(k, v)| match v {
+            serde_json::Value::String(v) => (k, v),
+            v => (k, v.to_string()),
+        })
+        .collect())
+}
+
+fn represent(
+    kwargs: &[(String, PyObjectRef)],
+    vm: &VirtualMachine,
+) -> sdk::Result<BTreeMap<String, String>> {
+    let mut result = BTreeMap::new();
+    for (name, value) in kwargs {
+        let value = value.repr(vm).map_err(|e| sdk::Error::python(e, vm))?;
+        result.insert(name.clone(), value.as_str().to_string());
+    }
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Plan
+//////////////////////////////////////////////////////////////////
+
+/// Commands declared in the workspace and files made by the pattern rules.
+pub struct Targets<'a> {
+    pub commands: &'a Container,
+    pub rules: &'a [rule::Pythonic],
+}
+
+impl Targets<'_> {
+    /// The command or the instance of the rule which makes the file.
+    /// Inputs which some rule can make become its dependencies.
+    fn resolve(
+        &self,
+        name: &str,
+        parent: Option<&str>,
+        vm: &VirtualMachine,
+    ) -> sdk::Result<Pythonic> {
+        if let Some(cmd) = self.commands.get(name) {
+            return Ok(cmd.clone());
+        }
+        let Some((rule, stem)) = rule::find(self.rules, name) else {
+            let message = match parent {
+                Some(parent) => format!("CLI command '{}' depends on unknown '{}'", parent, name),
+                None => format!("Neither CLI command nor pattern rule matches '{}'", name),
+            };
+            return Err(sdk::Error::new(sdk::ErrorKind::Registration, message));
+        };
+        let mut result = rule.instance(name, &stem, vm);
+        for input in result.inputs.clone() {
+            if rule::makeable(self.rules, &input) {
+                result.deps.push(input);
+            } else if !Path::new(&input).exists() {
+                return Err(sdk::Error::new(
+                    sdk::ErrorKind::Registration,
+                    format!("No rule to make '{}' needed by '{}'", input, name),
+                ));
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Commands to run in order: dependencies go before the dependents,
+/// each command is run once.
+pub fn plan(
+    names: &[String],
+    targets: &Targets,
+    vm: &VirtualMachine,
+) -> sdk::Result<Vec<Pythonic>> {
+    let mut result = Vec::new();
+    for name in names {
+        visit(name, targets, vm, &mut Vec::new(), &mut result)?;
+    }
+    Ok(result)
+}
+
+fn visit(
+    name: &str,
+    targets: &Targets,
+    vm: &VirtualMachine,
+    path: &mut Vec<String>,
+    result: &mut Vec<Pythonic>,
+) -> sdk::Result {
+    if result.iter().any(|c| c.name == name) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|n| n == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name.to_string());
+        return Err(sdk::Error::new(
+            sdk::ErrorKind::Registration,
+            format!("CLI command dependency cycle: {}", cycle.join(" -> ")),
+        ));
+    }
+    let cmd = targets.resolve(name, path.last().map(String::as_str), vm)?;
+    path.push(name.to_string());
+    for dependency in cmd.deps.iter() {
+        visit(dependency, targets, vm, path, result)?;
+    }
+    path.pop();
+    result.push(cmd);
+    Ok(())
+}
+
+/// Checks the 'deps' and 'finally_' of the commands once the script is
+/// loaded: each name must be a command or a file of some pattern rule,
+/// and they must not form a cycle, since the 'finally' commands are run
+/// with their own dependencies.
+pub fn validate(targets: &Targets) -> sdk::Result {
+    let mut names: Vec<&String> = targets.commands.keys().collect();
+    names.sort();
+    for name in names.iter() {
+        let cmd = &targets.commands[*name];
+        let fields = [("deps", &cmd.deps), ("finally_", &cmd.finally)];
+        for (field, target) in fields
+            .iter()
+            .flat_map(|(f, v)| v.iter().map(move |t| (f, t)))
+        {
+            if targets.commands.contains_key(target) || rule::find(targets.rules, target).is_some()
+            {
+                continue;
+            }
+            return Err(sdk::Error::new(
+                sdk::ErrorKind::Registration,
+                format!(
+                    "CLI command '{}' lists unknown '{}' in '{}'",
+                    name, target, field
+                ),
+            )
+            .with_note(format!("while applying '@cli.cmd(\"{}\")'", name)));
+        }
+    }
+    let mut done = Vec::new();
+    for name in names {
+        walk(name, targets.commands, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+fn walk<'a>(
+    name: &'a String,
+    commands: &'a Container,
+    path: &mut Vec<&'a String>,
+    done: &mut Vec<&'a String>,
+) -> sdk::Result {
+    if done.contains(&name) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|n| *n == name) {
+        let mut cycle: Vec<&str> = path[start..].iter().map(|n| n.as_str()).collect();
+        cycle.push(name);
+        return Err(sdk::Error::new(
+            sdk::ErrorKind::Registration,
+            format!(
+                "CLI command cycle through 'deps' and 'finally_': {}",
+                cycle.join(" -> ")
+            ),
+        ));
+    }
+    // Files of the rules are checked when they are planned.
+    let Some(cmd) = commands.get(name) else {
+        return Ok(());
+    };
+    path.push(name);
+    for target in cmd.deps.iter().chain(cmd.finally.iter()) {
+        walk(target, commands, path, done)?;
+    }
+    path.pop();
+    done.push(name);
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////////
+// Runner
+//////////////////////////////////////////////////////////////////
+
+/// Interruptions keep their kind, other errors fail the command.
+fn failure(name: &str, error: sdk::Error, message: String) -> sdk::Error {
+    match error.kind() {
+        sdk::ErrorKind::Interrupted => error.wrap(
+            sdk::ErrorKind::Interrupted,
+            format!("CLI command '{}' is interrupted", name),
+        ),
+        _ => error.wrap(sdk::ErrorKind::CommandFailed, message),
+    }
+}
+
+/// Runs the planned commands. Commands with outputs are restored from the
+/// artifact cache if possible. The dry run prints the plan instead.
+/// The output of each command is captured into the log of the run.
+pub struct Runner<'a> {
+    targets: Targets<'a>,
+    artifacts: Option<Arc<sdk::Artifacts>>,
+    cache: Option<Arc<sdk::Cache>>,
+    interrupter: Option<Arc<sdk::Interrupter>>,
+    logs: Option<Arc<sdk::Logs>>,
+    timings: Option<Arc<sdk::Timings>>,
+    recorder: RefCell<Option<sdk::LogRecorder>>,
+    /// Commands whose 'finally' commands are running.
+    finishing: RefCell<Vec<String>>,
+    dry_run: bool,
+    explain: bool,
+    vm: &'a VirtualMachine,
+}
+
+/// What happens with the command.
+struct Decision {
+    fingerprint: Option<Fingerprint>,
+    restore: Option<String>,
+    reasons: Vec<String>,
+}
+
+impl<'a> Runner<'a> {
+    pub fn new(
+        targets: Targets<'a>,
+        matches: &clap::ArgMatches,
+        vm: &'a VirtualMachine,
+    ) -> sdk::Result<Self> {
+        let flag = |id: &str| matches!(matches.try_get_one::<bool>(id), Ok(Some(true)));
+        let python = |e| sdk::Error::python(e, vm);
+        Ok(Self {
+            targets,
+            artifacts: py::service::<sdk::Artifacts>(vm).map_err(python)?,
+            cache: py::service::<sdk::Cache>(vm).map_err(python)?,
+            interrupter: py::service::<sdk::Interrupter>(vm).map_err(python)?,
+            logs: py::service::<sdk::Logs>(vm).map_err(python)?,
+            timings: py::service::<sdk::Timings>(vm).map_err(python)?,
+            recorder: RefCell::new(None),
+            finishing: RefCell::new(Vec::new()),
+            dry_run: flag(global::flags::DRY_RUN),
+            explain: flag(global::flags::EXPLAIN),
+            vm,
+        })
+    }
+
+    /// Runs the commands (or makes the files) and their dependencies. The
+    /// first command is called with the given values, others with the
+    /// default ones.
+    pub fn run(&self, names: &[String], matches: Option<&clap::ArgMatches>) -> sdk::Result {
+        let order = plan(names, &self.targets, self.vm)?;
+        let Some(logs) = self.logs.as_ref().filter(|_| !self.dry_run) else {
+            return self.make(names, &order, matches);
+        };
+        match logs.start(std::env::args().skip(1).collect()) {
+            Ok(recorder) => *self.recorder.borrow_mut() = Some(recorder),
+            Err(e) => sdk::warning(format!("Failed to start the run log: {}", e)),
+        }
+        let result = self.make(names, &order, matches);
+        if let Some(recorder) = self.recorder.borrow_mut().take()
+            && let Err(e) = recorder.close(sdk::LogStatus::from(&result))
+        {
+            sdk::warning(format!("Failed to record the run log: {}", e));
+        }
+        result
+    }
+
+    fn make(
+        &self,
+        names: &[String],
+        order: &[Pythonic],
+        matches: Option<&clap::ArgMatches>,
+    ) -> sdk::Result {
+        let mut pending = BTreeSet::new();
+        if self.dry_run {
+            println!("Plan of '{}':", names.join("', '"));
+        }
+        for (index, cmd) in order.iter().enumerate() {
+            let target = &cmd.name;
+            let defaults;
+            let matches = match matches {
+                Some(matches) if names.first() == Some(target) => matches,
+                _ => {
+                    defaults = self.defaults(cmd)?;
+                    &defaults
+                }
+            };
+            let kwargs = cmd.kwargs(matches, self.vm);
+            // The up-to-date check hashes the inputs, it is a part of the
+            // target time.
+            let started = Instant::now();
+            let previous = self.history().remove(target);
+            let mut decision = self.decide(target, cmd, &kwargs, previous.as_ref());
+            let waits: Vec<&String> = cmd.deps.iter().filter(|d| pending.contains(*d)).collect();
+            // The dry run skips the dependencies, so the inputs they produce
+            // are not up to date yet and the planned restore may not happen.
+            if self.dry_run && decision.restore.is_some() && !waits.is_empty() {
+                let names: Vec<&str> = waits.iter().map(|d| d.as_str()).collect();
+                decision.reasons.push(format!(
+                    "inputs may change, dependencies run first: {}",
+                    names.join(", ")
+                ));
+            }
+            if decision.restore.is_some() {
+                let hooks = self.hooks(cmd)?;
+                if !hooks.is_empty() {
+                    let names: Vec<String> = hooks.iter().map(|h| h.name(self.vm)).collect();
+                    decision
+                        .reasons
+                        .push(format!("hooks are not called: {}", names.join(", ")));
+                }
+            }
+            if decision.restore.is_none() {
+                pending.insert(target.clone());
+            }
+            if self.dry_run {
+                self.print(index, cmd, &kwargs, &decision)?;
+                continue;
+            }
+            if let Some(message) = &cmd.deprecated {
+                sdk::warning(format!(
+                    "CLI command '{}' is deprecated: {}",
+                    target, message
+                ));
+            }
+            if self.explain {
+                let action = if decision.restore.is_some() {
+                    "restore"
+                } else {
+                    "run"
+                };
+                eprintln!("{} '{}': {}", action, target, decision.reasons.join("; "));
+            }
+            let result = self.execute(target, cmd, kwargs, &decision);
+            let status = match result {
+                Ok(true) => sdk::LogStatus::Restored,
+                _ => sdk::LogStatus::from(&result),
+            };
+            if let Some(timings) = &self.timings {
+                timings.record(target, consts::TIMING_TARGET, started, Some(status.name()));
+            }
+            if let Some(recorder) = self.recorder.borrow_mut().as_mut() {
+                recorder.finish(target, status, started.elapsed().as_secs_f64());
+            }
+            self.finally(target, cmd, result.map(|_| ()))?;
+            // The 'finally' commands may record their fingerprints too.
+            if let Some(fingerprint) = decision.fingerprint {
+                let mut history = self.history();
+                history.insert(target.clone(), fingerprint);
+                self.save(&history);
+            }
+        }
+        Ok(())
+    }
+
+    fn decide(
+        &self,
+        name: &str,
+        cmd: &Pythonic,
+        kwargs: &[(String, PyObjectRef)],
+        previous: Option<&Fingerprint>,
+    ) -> Decision {
+        let mut result = Decision {
+            fingerprint: None,
+            restore: None,
+            reasons: Vec::new(),
+        };
+        let artifacts = match (&self.artifacts, cmd.outputs.is_empty()) {
+            (_, true) => {
+                result.reasons.push("no outputs declared".into());
+                return result;
+            }
+            (None, false) => {
+                result
+                    .reasons
+                    .push("artifact cache is not available".into());
+                return result;
+            }
+            (Some(v), false) => v,
+        };
+        let fingerprint =
+            Fingerprint::new(cmd, kwargs, artifacts, self.vm).and_then(|f| f.key().map(|k| (f, k)));
+        let (fingerprint, key) = match fingerprint {
+            Ok(v) => v,
+            Err(e) => {
+                sdk::warning(format!("Outputs of '{}' are not cached: {}", name, e));
+                result.reasons.push("outputs can't be cached".into());
+                return result;
+            }
+        };
+        match artifacts.manifest(&key) {
+            Ok(Some(_)) => {
+                result
+                    .reasons
+                    .push("outputs are stored for the same inputs".into());
+                result.restore = Some(key);
+            }
+            Ok(None) => {
+                let changes = previous.map(|p| fingerprint.changes(p)).unwrap_or_default();
+                match (previous, changes.is_empty()) {
+                    (None, _) => result.reasons.push("no previous run recorded".into()),
+                    (Some(_), true) => result.reasons.push("stored outputs are removed".into()),
+                    (Some(_), false) => result.reasons.extend(changes),
+                }
+            }
+            Err(e) => {
+                sdk::warning(format!("Failed to read outputs of '{}': {}", name, e));
+                result.reasons.push("stored outputs are unreadable".into());
+            }
+        }
+        result.fingerprint = Some(fingerprint);
+        result
+    }
+
+    /// Restores the outputs or calls the command, returns whether the
+    /// outputs are restored.
+    fn execute(
+        &self,
+        name: &str,
+        cmd: &Pythonic,
+        kwargs: Vec<(String, PyObjectRef)>,
+        decision: &Decision,
+    ) -> sdk::Result<bool> {
+        if let (Some(artifacts), Some(key)) = (&self.artifacts, &decision.restore) {
+            match artifacts.restore(key) {
+                Ok(Some(manifest)) => {
+                    eprintln!(
+                        "Restored {} outputs of '{}' from the artifact cache",
+                        manifest.outputs.len(),
+                        name
+                    );
+                    return Ok(true);
+                }
+                Ok(None) => {}
+                Err(e) => sdk::warning(format!("Failed to restore outputs of '{}': {}", name, e)),
+            }
+        }
+        let mut log = self.log(name);
+        let capture = log
+            .as_ref()
+            .and_then(|file| Capture::install(file, self.vm).ok());
+        let previous = py::service::<py::Invocation>(self.vm).ok().flatten();
+        let invocation = py::Invocation {
+            command: name.to_string(),
+            options: kwargs.clone(),
+        };
+        let _ = py::provide(self.vm, invocation);
+        let result = self.invoke(name, cmd, kwargs, decision);
+        if let Some(previous) = previous {
+            let _ = py::provide(self.vm, previous.as_ref().clone());
+        }
+        drop(capture);
+        if let (Some(file), Err(error)) = (log.as_mut(), &result) {
+            let _ = file.write_all(self.describe(error).as_bytes());
+        }
+        result.map(|_| false)
+    }
+
+    /// Calls the command with its hooks and stores its outputs.
+    fn invoke(
+        &self,
+        name: &str,
+        cmd: &Pythonic,
+        kwargs: Vec<(String, PyObjectRef)>,
+        decision: &Decision,
+    ) -> sdk::Result {
+        let vm = self.vm;
+        let hooks = self.hooks(cmd)?;
+        let mut context = kwargs.clone();
+        context.push((hook::COMMAND.to_string(), vm.new_pyobj(name)));
+        for hook in hooks.iter().filter(|h| h.kind == hook::Kind::Before) {
+            let result = hook.call(&context, vm);
+            py::flush(vm);
+            if let Err(e) = result {
+                let message = format!(
+                    "Before hook '{}' of CLI command '{}' failed",
+                    hook.name(vm),
+                    name
+                );
+                return Err(failure(name, sdk::Error::python(e, vm), message));
+            }
+        }
+        let result = self.call(name, cmd, kwargs);
+        let after: Vec<&hook::Pythonic> = hooks
+            .iter()
+            .filter(|h| h.kind == hook::Kind::After)
+            .collect();
+        self.after(name, &after, context, result)?;
+        let (Some(artifacts), Some(fingerprint)) = (&self.artifacts, &decision.fingerprint) else {
+            return Ok(());
+        };
+        match fingerprint
+            .key()
+            .and_then(|key| artifacts.save(&key, name, &cmd.outputs))
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => sdk::warning(format!("Command '{}' produced no outputs", name)),
+            Err(e) => sdk::warning(format!("Failed to store outputs of '{}': {}", name, e)),
+        }
+        Ok(())
+    }
+
+    /// Hooks of the command, declared by its name or aliases.
+    fn hooks(&self, cmd: &Pythonic) -> sdk::Result<Vec<hook::Pythonic>> {
+        let hooks = hook::list(self.vm).map_err(|e| sdk::Error::python(e, self.vm))?;
+        Ok(hooks
+            .into_iter()
+            .filter(|h| h.command == cmd.name || cmd.aliases.contains(&h.command))
+            .collect())
+    }
+
+    /// Calls the function, again on failures if the command has retries.
+    /// Each attempt is limited by the timeout, interruptions (Ctrl+C)
+    /// are never retried.
+    fn call(
+        &self,
+        name: &str,
+        cmd: &Pythonic,
+        kwargs: Vec<(String, PyObjectRef)>,
+    ) -> sdk::Result<PyObjectRef> {
+        let vm = self.vm;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let deadline = match (cmd.timeout, &self.interrupter) {
+                (Some(seconds), Some(interrupter)) => Some(interrupter.deadline(
+                    Duration::from_secs_f64(seconds),
+                    format!("CLI command '{}' timed out after {}s", name, seconds),
+                )),
+                _ => None,
+            };
+            let result = cmd.call(kwargs.clone(), vm);
+            drop(deadline);
+            py::flush(vm);
+            let error = match result {
+                Ok(v) => return Ok(v),
+                Err(e) => {
+                    py::add_note(vm, &e, format!("while running CLI command '{}'", name));
+                    sdk::Error::python(e, vm)
+                }
+            };
+            if error.kind() == sdk::ErrorKind::Interrupted || attempt > cmd.retries {
+                let error = failure(name, error, format!("CLI command '{}' failed", name));
+                return match error.kind() {
+                    sdk::ErrorKind::CommandFailed if attempt > 1 => {
+                        Err(error.with_note(format!("failed {} times in a row", attempt)))
+                    }
+                    _ => Err(error),
+                };
+            }
+            sdk::warning(format!(
+                "CLI command '{}' failed ({}), retrying in {}s ({} of {})",
+                name,
+                error.innermost().message(),
+                cmd.retry_delay,
+                attempt,
+                cmd.retries
+            ));
+            self.sleep(Duration::from_secs_f64(cmd.retry_delay))
+                .map_err(|e| sdk::Error::python(e, vm))?;
+        }
+    }
+
+    /// Calls the 'after' hooks with the result or the exception. The hook
+    /// failure fails the succeeded command, but the failure of the failed
+    /// command is kept and the hook failure is reported as a warning.
+    fn after(
+        &self,
+        name: &str,
+        hooks: &[&hook::Pythonic],
+        mut context: Vec<(String, PyObjectRef)>,
+        result: sdk::Result<PyObjectRef>,
+    ) -> sdk::Result {
+        let vm = self.vm;
+        let (value, exception) = match &result {
+            Err(e) if e.kind() == sdk::ErrorKind::Interrupted => return result.map(|_| ()),
+            Ok(value) => (value.clone(), vm.ctx.none()),
+            Err(e) => (
+                vm.ctx.none(),
+                e.exception()
+                    .map(|e| e.clone().into())
+                    .unwrap_or_else(|| vm.ctx.none()),
+            ),
+        };
+        context.push((hook::RESULT.to_string(), value));
+        context.push((hook::EXCEPTION.to_string(), exception));
+        let mut result = result.map(|_| ());
+        for hook in hooks {
+            let outcome = hook.call(&context, vm);
+            py::flush(vm);
+            let Err(e) = outcome else {
+                continue;
+            };
+            let message = format!(
+                "After hook '{}' of CLI command '{}' failed",
+                hook.name(vm),
+                name
+            );
+            let error = failure(name, sdk::Error::python(e, vm), message.clone());
+            match &result {
+                _ if error.kind() == sdk::ErrorKind::Interrupted => return Err(error),
+                Ok(_) => result = Err(error),
+                Err(_) => sdk::warning(format!("{}: {}", message, error.innermost().message())),
+            }
+        }
+        result
+    }
+
+    /// Runs the 'finally' commands whatever the result of the command is,
+    /// unless it is interrupted. Their failures are handled like the ones
+    /// of the 'after' hooks.
+    fn finally(&self, name: &str, cmd: &Pythonic, result: sdk::Result) -> sdk::Result {
+        let result = match result {
+            Err(e) if e.kind() == sdk::ErrorKind::Interrupted => return Err(e),
+            other => other,
+        };
+        if cmd.finally.is_empty() {
+            return result;
+        }
+        // Rules may still lead back to the command, which is not checked
+        // on loading.
+        if self.finishing.borrow().iter().any(|n| n == name) {
+            return Err(sdk::Error::new(
+                sdk::ErrorKind::Registration,
+                format!(
+                    "CLI command '{}' is run again by its 'finally' commands",
+                    name
+                ),
+            ));
+        }
+        self.finishing.borrow_mut().push(name.to_string());
+        let result = self.finish(name, cmd, result);
+        self.finishing.borrow_mut().pop();
+        result
+    }
+
+    fn finish(&self, name: &str, cmd: &Pythonic, mut result: sdk::Result) -> sdk::Result {
+        for target in cmd.finally.iter() {
+            let names = std::slice::from_ref(target);
+            let made = plan(names, &self.targets, self.vm).and_then(|o| self.make(names, &o, None));
+            let Err(error) = made else {
+                continue;
+            };
+            let message = format!(
+                "Finally command '{}' of CLI command '{}' failed",
+                target, name
+            );
+            match &result {
+                _ if error.kind() == sdk::ErrorKind::Interrupted => return Err(error),
+                Ok(_) => result = Err(error.wrap(sdk::ErrorKind::CommandFailed, message)),
+                Err(_) => sdk::warning(format!("{}: {}", message, error.innermost().message())),
+            }
+        }
+        result
+    }
+
+    /// Creates the log file of the command, if the run is recorded.
+    fn log(&self, name: &str) -> Option<File> {
+        let mut recorder = self.recorder.borrow_mut();
+        match recorder.as_mut()?.open(name) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                sdk::warning(format!("Failed to create the log of '{}': {}", name, e));
+                None
+            }
+        }
+    }
+
+    /// Failure of the command for its log: the messages of the causes
+    /// and the python traceback.
+    fn describe(&self, error: &sdk::Error) -> String {
+        let mut result = format!("error: {}\n", error.message());
+        for cause in error.causes() {
+            result += &format!("caused by: {}\n", cause);
+        }
+        let Some(exception) = error.exception() else {
+            return result;
+        };
+        let traceback = self.vm.import("traceback", 0).and_then(|module| {
+            let lines = self
+                .vm
+                .call_method(&module, "format_exception", (exception.clone(),))?;
+            let separator = self.vm.new_pyobj("");
+            let text = self.vm.call_method(&separator, "join", (lines,))?;
+            text.str(self.vm)
+        });
+        if let Ok(text) = traceback {
+            result += "\n";
+            result += text.as_str();
+        }
+        result
+    }
+
+    /// Sleeps, but lets Ctrl+C interrupt the sleep.
+    fn sleep(&self, duration: Duration) -> PyResult<()> {
+        let end = Instant::now() + duration;
+        while let Some(left) = end.checked_duration_since(Instant::now()) {
+            self.vm.check_signals()?;
+            std::thread::sleep(left.min(Duration::from_millis(50)));
+        }
+        self.vm.check_signals()
+    }
+
+    fn print(
+        &self,
+        index: usize,
+        cmd: &Pythonic,
+        kwargs: &[(String, PyObjectRef)],
+        decision: &Decision,
+    ) -> sdk::Result {
+        let action = if decision.restore.is_some() {
+            "restore"
+        } else {
+            "run"
+        };
+        println!("  {}. {} [{}]", index + 1, cmd.name, action);
+        if !cmd.finally.is_empty() {
+            println!("       finally: {}", cmd.finally.join(", "));
+        }
+        for (option, value) in represent(kwargs, self.vm)? {
+            println!("       {} = {}", option, value);
+        }
+        if self.explain {
+            for reason in decision.reasons.iter() {
+                println!("       - {}", reason);
+            }
+        }
+        Ok(())
+    }
+
+    /// Matches of the command line without arguments.
+    fn defaults(&self, cmd: &Pythonic) -> sdk::Result<clap::ArgMatches> {
+        clap::Command::from(&Cacheable::from(cmd))
+            .no_binary_name(true)
+            .try_get_matches_from(Vec::<String>::new())
+            .map_err(|e| {
+                sdk::Error::new(
+                    sdk::ErrorKind::Registration,
+                    format!(
+                        "CLI command '{}' can't be a dependency: {}",
+                        cmd.name,
+                        e.kind()
+                    ),
+                )
+            })
+    }
+
+    fn history(&self) -> History {
+        self.cache
+            .as_ref()
+            .and_then(|cache| self.entry(cache).get().ok().flatten())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, history: &History) {
+        if let Some(cache) = &self.cache
+            && let Err(e) = self.entry(cache).set(history.clone())
+        {
+            sdk::warning(format!("Failed to record CLI command fingerprints: {}", e));
+        }
+    }
+
+    fn entry(&self, cache: &sdk::Cache) -> sdk::CacheEntry<History> {
+        sdk::CacheEntry::<History>::new(consts::CACHE_KEY_HISTORY, cache.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_vm::Interpreter;
+    use rustpython_vm::class::PyClassImpl;
+    use rustpython_vm::convert::ToPyObject;
+
+    fn cmd(name: &str, deps: &[&str], finally: &[&str]) -> (String, Pythonic) {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+        let cmd = Pythonic {
+            name: name.to_string(),
+            deps: strings(deps),
+            finally: strings(finally),
+            ..Default::default()
+        };
+        (name.to_string(), cmd)
+    }
+
+    /// Command declarations use the python types, which exist once the
+    /// interpreter is created.
+    fn enter<R>(f: impl FnOnce(&VirtualMachine) -> R) -> R {
+        Interpreter::without_stdlib(Default::default()).enter(f)
+    }
+
+    fn validated(commands: impl FnOnce() -> Vec<(String, Pythonic)>) -> Result<(), String> {
+        let commands = enter(|_| Container::from_iter(commands()));
+        let targets = Targets {
+            commands: &commands,
+            rules: &[],
+        };
+        validate(&targets).map_err(|e| e.message().to_string())
+    }
+
+    #[test]
+    fn finally_of_itself() {
+        assert_eq!(
+            validated(|| vec![cmd("loop", &[], &["loop"])]).unwrap_err(),
+            "CLI command cycle through 'deps' and 'finally_': loop -> loop"
+        );
+    }
+
+    #[test]
+    fn mutual_finally() {
+        assert_eq!(
+            validated(|| vec![cmd("a", &[], &["b"]), cmd("b", &[], &["a"])]).unwrap_err(),
+            "CLI command cycle through 'deps' and 'finally_': a -> b -> a"
+        );
+        assert_eq!(
+            validated(|| vec![cmd("a", &["b"], &[]), cmd("b", &[], &["a"])]).unwrap_err(),
+            "CLI command cycle through 'deps' and 'finally_': a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn unknown_targets() {
+        assert_eq!(
+            validated(|| vec![cmd("a", &[], &["clean"])]).unwrap_err(),
+            "CLI command 'a' lists unknown 'clean' in 'finally_'"
+        );
+        assert_eq!(
+            validated(|| vec![cmd("a", &["build"], &[])]).unwrap_err(),
+            "CLI command 'a' lists unknown 'build' in 'deps'"
+        );
+    }
+
+    #[test]
+    fn shared_targets() {
+        let commands = || {
+            vec![
+                cmd("test", &["build", "fetch"], &["clean"]),
+                cmd("build", &["fetch"], &["clean"]),
+                cmd("fetch", &[], &[]),
+                cmd("clean", &[], &[]),
+            ]
+        };
+        assert!(validated(commands).is_ok());
+    }
+
+    #[test]
+    fn plan_order() {
+        let names = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        enter(|vm| {
+            let commands = Container::from_iter([
+                cmd("test", &["build", "fetch"], &[]),
+                cmd("build", &["fetch"], &[]),
+                cmd("fetch", &[], &[]),
+                cmd("a", &["b"], &[]),
+                cmd("b", &["a"], &[]),
+            ]);
+            let targets = Targets {
+                commands: &commands,
+                rules: &[],
+            };
+            let order = plan(&names(&["test", "build"]), &targets, vm).unwrap();
+            let order: Vec<&str> = order.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(order, ["fetch", "build", "test"]);
+            let error = plan(&names(&["a"]), &targets, vm).unwrap_err();
+            assert_eq!(error.message(), "CLI command dependency cycle: a -> b -> a");
+            let error = plan(&names(&["missing"]), &targets, vm).unwrap_err();
+            assert_eq!(
+                error.message(),
+                "Neither CLI command nor pattern rule matches 'missing'"
+            );
+        });
+    }
+
+    /// Calls the command function by the runner with the interrupter, the
+    /// function is defined by the python source.
+    fn called(source: &str, timeout: Option<f64>, retries: u32) -> (sdk::Result, i64) {
+        let (sender, signals) = rustpython_vm::signal::user_signal_channel();
+        let interpreter = Interpreter::with_init(Default::default(), |vm| {
+            vm.set_user_signal_channel(signals);
+        });
+        interpreter.enter(|vm| {
+            py::Container::make_class(&vm.ctx);
+            let container = py::Container::default().to_pyobject(vm);
+            vm.builtins
+                .set_attr(global::kit::CONTAINER, container, vm)
+                .unwrap();
+            py::provide(vm, sdk::Interrupter::new(sender)).unwrap();
+            let scope = vm.new_scope_with_builtins();
+            vm.run_code_string(scope.clone(), source, "<test>".to_string())
+                .unwrap();
+            let (name, mut cmd) = cmd("cmd", &[], &[]);
+            cmd.function = Some(scope.globals.get_item("cmd", vm).unwrap());
+            cmd.timeout = timeout;
+            cmd.retries = retries;
+            let commands = Container::from_iter([(name, cmd.clone())]);
+            let targets = Targets {
+                commands: &commands,
+                rules: &[],
+            };
+            let matches = clap::Command::new("test").get_matches_from(["test"]);
+            let runner = Runner::new(targets, &matches, vm).unwrap();
+            let result = runner.call("cmd", &cmd, Vec::new()).map(|_| ());
+            let calls = scope.globals.get_item("calls", vm).unwrap();
+            (result, calls.try_into_value::<i64>(vm).unwrap())
+        })
+    }
+
+    #[test]
+    fn timeout_interrupts_command() {
+        let source = r#"
+calls = 0
+def cmd():
+    global calls
+    calls += 1
+    while True:
+        pass
+"#;
+        let (result, calls) = called(source, Some(0.1), 1);
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), sdk::ErrorKind::CommandFailed);
+        assert!(
+            error
+                .innermost()
+                .message()
+                .contains("CLI command 'cmd' timed out after 0.1s")
+        );
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn retries_until_success() {
+        let source = r#"
+calls = 0
+def cmd():
+    global calls
+    calls += 1
+    if calls < 3:
+        raise ValueError(calls)
+"#;
+        let (result, calls) = called(source, None, 2);
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+        let (result, calls) = called(source, None, 1);
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), sdk::ErrorKind::CommandFailed);
+        assert!(error.notes().iter().any(|n| n == "failed 2 times in a row"));
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,12 @@
+pub const FUNCTION_SETTING: &str = "setting";
+pub const FUNCTION_GET: &str = "get";
+pub const FUNCTION_PROFILE: &str = "profile";
+
+pub const SUBCOMMAND: &str = "config";
+pub const SUBCOMMAND_SHOW: &str = "show";
+pub const SERVICE_SETTINGS: &str = "config/settings";
+
+pub const PLUGIN_NAME: &str = "Config";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str =
+    "Allows users to declare workspace settings overridden by config files and the command line";
@@ -0,0 +1,5 @@
+mod consts;
+mod plugin;
+mod setting;
+
+pub use plugin::Instance;
@@ -0,0 +1,198 @@
+use crate::{consts, setting};
+use clap::Command;
+use plugin::Interface as PluginInterface;
+use rustpython::vm::pymodule;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::{PyRef, VirtualMachine};
+use sdk::py;
+use sdk::py::{Registerable, Registerer};
+use serde_json::json;
+use std::io::Write;
+
+const JSON: &str = "json";
+
+#[derive(Default)]
+pub struct Instance {}
+
+impl Registerable for Instance {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        module.register::<setting::Declare>(vm);
+        module.register::<setting::Get>(vm);
+        module.register::<setting::Profile>(vm);
+    }
+}
+
+impl PluginInterface for Instance {
+    fn initialize(&self, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        plugin::Info {
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
+        }
+    }
+
+    fn examples(&self) -> Vec<String> {
+        vec![
+            String::from(
+                r#"from umk import cli, config
+
+jobs = config.setting(int, "jobs", default=4, help="Parallel compile jobs")
+optimize = config.setting(bool, "optimize", default=False)
+
+
+@cli.cmd("build")
+def build():
+    print(f"Building with {jobs} jobs, optimize={optimize}")
+"#,
+            ),
+            String::from(
+                r#"# .unimake/config.toml (or ~/.config/unimake/config.toml)
+[settings]
+jobs = 8
+
+# Selected by 'unimake --profile release ...'
+[profiles.release]
+optimize = true
+
+# Other overrides: UNIMAKE_CONFIG_JOBS=2 or '--set jobs=2'
+"#,
+            ),
+        ]
+    }
+
+    fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        let show = Command::new(consts::SUBCOMMAND_SHOW)
+            .about("Print the effective values of the settings and their sources")
+            .arg(
+                clap::Arg::new(JSON)
+                    .long(JSON)
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Print the output as JSON"),
+            );
+        let result = Command::new(consts::SUBCOMMAND)
+            .about("Inspect the workspace settings")
+            .arg_required_else_help(true)
+            .disable_help_subcommand(true)
+            .subcommand(show);
+        Ok(Some(result))
+    }
+
+    fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, matches: &clap::ArgMatches, vm: &VirtualMachine) -> sdk::Result {
+        let Some((_, matches)) = matches.subcommand() else {
+            return Ok(());
+        };
+        let python = |e| sdk::Error::python(e, vm);
+        let settings = setting::list(vm).map_err(python)?;
+        let profile = setting::overrides(vm).map_err(python)?.profile;
+        match matches.get_flag(JSON) {
+            true => show_json(&settings, profile),
+            false => show(&settings, profile),
+        }
+    }
+
+    fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
+        let module = _module::make_module(vm);
+        module.register::<setting::Declare>(vm);
+        module.register::<setting::Get>(vm);
+        module.register::<setting::Profile>(vm);
+        py::register::submodule(vm, root, module);
+    }
+
+    /// The script may be loaded again (e.g. in the watch mode).
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        setting::forget(vm).map_err(|e| sdk::Error::python(e, vm))
+    }
+
+    /// Overrides of undeclared settings are likely typos.
+    fn after_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        let python = |e| sdk::Error::python(e, vm);
+        let settings = setting::list(vm).map_err(python)?;
+        for layer in setting::overrides(vm).map_err(python)?.layers {
+            for name in layer.values.keys() {
+                if !settings.contains_key(name) {
+                    sdk::warning(format!("Unknown setting '{}' in {}", name, layer.source));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[pymodule(name = "config")]
+mod _module {}
+
+//////////////////////////////////////////////////////////////////
+// Show
+//////////////////////////////////////////////////////////////////
+
+fn show(settings: &setting::Container, profile: Option<String>) -> sdk::Result {
+    if let Some(profile) = profile {
+        println!("Profile: {}", profile);
+    }
+    if settings.is_empty() {
+        eprintln!("Workspace declares no settings");
+        return Ok(());
+    }
+    let rows: Vec<[String; 4]> = settings
+        .values()
+        .map(|s| {
+            [
+                s.name.clone(),
+                s.class.name().to_string(),
+                s.value.to_string(),
+                s.source.clone(),
+            ]
+        })
+        .collect();
+    let header = ["NAME", "TYPE", "VALUE", "SOURCE"].map(String::from);
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        println!(
+            "{:w0$}  {:w1$}  {:w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+    }
+    Ok(())
+}
+
+fn show_json(settings: &setting::Container, profile: Option<String>) -> sdk::Result {
+    let settings: Vec<serde_json::Value> = settings
+        .values()
+        .map(|s| {
+            json!({
+                "name": s.name,
+                "type": s.class.name(),
+                "value": s.value,
+                "default": s.default,
+                "source": s.source,
+                "help": s.help,
+            })
+        })
+        .collect();
+    let value = json!({ "profile": profile, "settings": settings });
+    let text = serde_json::to_string_pretty(&value)?;
+    // JSON is usually piped to other tools, which may close the pipe early.
+    match writeln!(std::io::stdout().lock(), "{}", text) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,340 @@
+use crate::consts;
+use rustpython_vm::builtins::{PyBool, PyFloat, PyInt, PyModule, PyStr, PyTypeRef};
+use rustpython_vm::class::StaticType;
+use rustpython_vm::function::{FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::{FromArgs, PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+//////////////////////////////////////////////////////////////////
+// Class
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+impl Class {
+    fn of(class: &PyTypeRef) -> Option<Self> {
+        // The 'bool' is a subclass of the 'int', so it must be checked first.
+        if class.fast_issubclass(PyBool::static_type()) {
+            Some(Class::Boolean)
+        } else if class.fast_issubclass(PyStr::static_type()) {
+            Some(Class::String)
+        } else if class.fast_issubclass(PyInt::static_type()) {
+            Some(Class::Integer)
+        } else if class.fast_issubclass(PyFloat::static_type()) {
+            Some(Class::Float)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::String => "str",
+            Class::Integer => "int",
+            Class::Float => "float",
+            Class::Boolean => "bool",
+        }
+    }
+
+    /// Converts the value from the config file, the environment or the
+    /// command line. Strings are parsed, since the latter two have no types.
+    fn coerce(&self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (_, Value::Null) => Some(Value::Null),
+            (Class::String, Value::String(_)) => Some(value.clone()),
+            (Class::Integer, Value::Number(v)) => v.as_i64().map(Value::from),
+            (Class::Integer, Value::String(v)) => v.trim().parse::<i64>().ok().map(Value::from),
+            (Class::Float, Value::Number(v)) => v.as_f64().map(Value::from),
+            (Class::Float, Value::String(v)) => v.trim().parse::<f64>().ok().map(Value::from),
+            (Class::Boolean, Value::Bool(_)) => Some(value.clone()),
+            (Class::Boolean, Value::String(v)) => match v.trim().to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Some(Value::Bool(true)),
+                "0" | "false" | "no" | "off" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Converts the python default value, 'None' if it has other type.
+    fn extract(&self, value: &PyObjectRef, vm: &VirtualMachine) -> Option<Value> {
+        let class = value.class();
+        let boolean = class.fast_issubclass(PyBool::static_type());
+        let integer = class.fast_issubclass(PyInt::static_type()) && !boolean;
+        match self {
+            Class::String => value
+                .downcast_ref::<PyStr>()
+                .map(|v| Value::from(v.as_str())),
+            Class::Boolean if boolean => value
+                .clone()
+                .try_into_value::<bool>(vm)
+                .ok()
+                .map(Value::from),
+            Class::Integer if integer => value
+                .clone()
+                .try_into_value::<i64>(vm)
+                .ok()
+                .map(Value::from),
+            Class::Float if integer || class.fast_issubclass(PyFloat::static_type()) => {
+                value.try_float(vm).ok().map(|v| Value::from(v.to_f64()))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn to_python(value: &Value, vm: &VirtualMachine) -> PyObjectRef {
+    match value {
+        Value::Bool(v) => vm.new_pyobj(*v),
+        Value::Number(v) => match v.as_i64() {
+            Some(v) => vm.new_pyobj(v),
+            None => vm.new_pyobj(v.as_f64().unwrap_or_default()),
+        },
+        Value::String(v) => vm.new_pyobj(v.clone()),
+        _ => vm.ctx.none(),
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Registry
+//////////////////////////////////////////////////////////////////
+
+/// Setting declared by the script with its effective value.
+#[derive(Debug, Clone)]
+pub struct Setting {
+    pub name: String,
+    pub class: Class,
+    pub help: Option<String>,
+    pub default: Value,
+    pub value: Value,
+    /// Where the value comes from, 'default' if it is not overridden.
+    pub source: String,
+}
+
+pub type Container = BTreeMap<String, Setting>;
+
+#[derive(Default)]
+pub struct Registry(pub Container);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_SETTINGS;
+}
+
+pub fn list(vm: &VirtualMachine) -> PyResult<Container> {
+    match py::service::<Registry>(vm)? {
+        None => Ok(Default::default()),
+        Some(v) => Ok(v.0.clone()),
+    }
+}
+
+/// Removes the declared settings, so the script can be loaded again.
+pub fn forget(vm: &VirtualMachine) -> PyResult<()> {
+    py::provide(vm, Registry::default())
+}
+
+/// Overrides collected by the application.
+pub fn overrides(vm: &VirtualMachine) -> PyResult<sdk::Settings> {
+    Ok(py::service::<sdk::Settings>(vm)?
+        .map(|v| v.as_ref().clone())
+        .unwrap_or_default())
+}
+
+/// Settings names are parts of the environment variable names.
+fn valid(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+//////////////////////////////////////////////////////////////////
+// Functions
+//////////////////////////////////////////////////////////////////
+
+#[derive(FromArgs, Debug)]
+pub struct Args {
+    #[pyarg(positional)]
+    pub class: PyTypeRef,
+
+    #[pyarg(positional)]
+    pub name: String,
+
+    #[pyarg(any, optional, default = "None")]
+    pub default: Option<PyObjectRef>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub help: Option<String>,
+}
+
+pub struct Declare {}
+
+impl Function for Declare {
+    const NAME: &'static str = consts::FUNCTION_SETTING;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::declare,
+        PyMethodFlags::empty(),
+        Some(
+            "setting(type, name, default=None, help=None)\n--\n\nDeclare the workspace setting and return its value. The default is overridden by the 'settings' of '.unimake/config.toml', then of '~/.config/unimake/config.toml' (each followed by the selected '--profile'), then by the 'UNIMAKE_CONFIG_<NAME>' variables and '--set name=value' options.",
+        ),
+    );
+}
+
+impl Declare {
+    fn declare(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult {
+        let args = py::args_to::<Args>(inputs, vm)?;
+        let name = args.name;
+        if !valid(&name) {
+            let msg = format!(
+                "Setting name must consist of lowercase letters, digits and '_': '{}'",
+                name
+            );
+            return Err(py::registration_error(vm, msg));
+        }
+        let Some(class) = Class::of(&args.class) else {
+            let msg = format!("Setting has unsupported type '{}'", args.class.name());
+            return Err(py::registration_error(vm, msg));
+        };
+        let default = match args.default {
+            Some(v) if !vm.is_none(&v) => class.extract(&v, vm).ok_or_else(|| {
+                let msg = format!(
+                    "Default value of setting '{}' must be '{}'",
+                    name,
+                    class.name()
+                );
+                py::registration_error(vm, msg)
+            })?,
+            _ => Value::Null,
+        };
+        let overrides = overrides(vm)?;
+        let (value, source) = match overrides.find(&name) {
+            None => (default.clone(), String::from("default")),
+            Some((layer, raw)) => match class.coerce(raw) {
+                Some(v) => (v, layer.source.clone()),
+                None => {
+                    let msg = format!(
+                        "Invalid value of setting '{}' in {}: expected '{}', got {}",
+                        name,
+                        layer.source,
+                        class.name(),
+                        raw
+                    );
+                    return Err(py::config_error(vm, msg));
+                }
+            },
+        };
+
+        let mut container = list(vm)?;
+        if container.contains_key(&name) {
+            let msg = format!("Setting already exists: '{}'", name);
+            return Err(py::registration_error(vm, msg));
+        }
+        let result = to_python(&value, vm);
+        let setting = Setting {
+            name: name.clone(),
+            class,
+            help: args.help,
+            default,
+            value,
+            source,
+        };
+        container.insert(name, setting);
+        py::provide(vm, Registry(container))?;
+        Ok(result)
+    }
+}
+
+impl py::Registerable for Declare {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Get {}
+
+impl Function for Get {
+    const NAME: &'static str = consts::FUNCTION_GET;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::get,
+        PyMethodFlags::empty(),
+        Some("get(name)\n--\n\nReturn the value of the declared setting."),
+    );
+}
+
+impl Get {
+    fn get(name: String, vm: &VirtualMachine) -> PyResult {
+        match list(vm)?.get(&name) {
+            Some(setting) => Ok(to_python(&setting.value, vm)),
+            None => {
+                Err(vm.new_key_error(vm.new_pyobj(format!("Setting is not declared: '{}'", name))))
+            }
+        }
+    }
+}
+
+impl py::Registerable for Get {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Profile {}
+
+impl Function for Profile {
+    const NAME: &'static str = consts::FUNCTION_PROFILE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::profile,
+        PyMethodFlags::empty(),
+        Some("profile()\n--\n\nReturn the name of the selected profile or None."),
+    );
+}
+
+impl Profile {
+    fn profile(vm: &VirtualMachine) -> PyResult<Option<String>> {
+        Ok(overrides(vm)?.profile)
+    }
+}
+
+impl py::Registerable for Profile {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerce_parses_strings() {
+        let text = |v: &str| Value::String(v.to_string());
+        assert_eq!(Class::Integer.coerce(&text(" 8 ")), Some(Value::from(8)));
+        assert_eq!(Class::Float.coerce(&text("1.5")), Some(Value::from(1.5)));
+        assert_eq!(
+            Class::Boolean.coerce(&text("Off")),
+            Some(Value::Bool(false))
+        );
+        assert_eq!(Class::String.coerce(&text("x")), Some(text("x")));
+        assert_eq!(Class::Integer.coerce(&Value::Null), Some(Value::Null));
+    }
+
+    #[test]
+    fn coerce_rejects_other_types() {
+        assert_eq!(Class::Integer.coerce(&Value::String("8x".into())), None);
+        assert_eq!(Class::Integer.coerce(&Value::from(1.5)), None);
+        assert_eq!(Class::Boolean.coerce(&Value::String("maybe".into())), None);
+        assert_eq!(Class::String.coerce(&Value::from(1)), None);
+        assert_eq!(Class::Float.coerce(&Value::Array(Vec::new())), None);
+    }
+}
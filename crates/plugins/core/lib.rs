@@ -1,17 +1,109 @@
 use rustpython_vm::builtins::PyModule;
 use rustpython_vm::{PyRef, VirtualMachine};
+use std::ffi::c_char;
 
-pub trait Interface: Drop + Send + Sync {
-    fn initialize(&self, vm: &VirtualMachine);
+pub trait Interface: Send + Sync {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result;
     fn info(&self) -> Info;
+    /// Names of plugins which must be registered and initialized before this one.
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
     fn examples(&self) -> Vec<String>;
-    fn cli(&self, driver: &Box<dyn sdk::CacheDriver>) -> Option<clap::Command>;
-    fn cache(&self, driver: &Box<dyn sdk::CacheDriver>);
+    fn cli(&self, cache: &sdk::Cache) -> sdk::Result<Option<clap::Command>>;
+    fn cache(&self, cache: &sdk::Cache, vm: &VirtualMachine) -> sdk::Result;
+    fn call(&self, matches: &clap::ArgMatches, vm: &VirtualMachine) -> sdk::Result;
     fn register(&self, module: &PyRef<PyModule>, vm: &VirtualMachine);
+
+    //////////////////////////////////////////////////////////////////
+    // Lifecycle hooks
+    //////////////////////////////////////////////////////////////////
+
+    /// Called before the workspace script is executed.
+    fn before_load(&self, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    /// Called after the workspace script is executed successfully.
+    fn after_load(&self, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    /// Called before the command is run, 'command' is the subcommand
+    /// path (e.g. ["cli", "build"]).
+    fn before_command(&self, _command: &[String], _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    /// Called after the command is finished, even if it has failed.
+    fn after_command(
+        &self,
+        _command: &[String],
+        _result: &sdk::Result,
+        _vm: &VirtualMachine,
+    ) -> sdk::Result {
+        Ok(())
+    }
+
+    /// Called once before the application exits with the final result.
+    fn on_exit(&self, _result: &sdk::Result, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct Info {
     pub name: String,
     pub version: String,
     pub description: String,
 }
+
+//////////////////////////////////////////////////////////////////
+// Dynamic plugins ABI
+//////////////////////////////////////////////////////////////////
+
+/// Must be increased on every change of the 'Interface' or 'Declaration'.
+pub const ABI_VERSION: u32 = 3;
+
+/// Plugin API version, plugins built against an other version are rejected.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Null terminated 'VERSION' to pass it through the C ABI.
+pub const VERSION_C: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Name of the function which shared library plugins must export (see 'export' macro).
+pub const ENTRY_POINT: &[u8] = b"unimake_plugin_declaration\0";
+
+/// Returned by the shared library entry point. The Rust ABI is not stable,
+/// so the plugin must be built by the same toolchain as the application.
+#[repr(C)]
+pub struct Declaration {
+    pub abi: u32,
+    pub version: *const c_char,
+    pub create: unsafe extern "C" fn() -> *mut Box<dyn Interface>,
+}
+
+pub type EntryPoint = unsafe extern "C" fn() -> Declaration;
+
+/// Exports the plugin from the 'cdylib' crate.
+///
+/// ```ignore
+/// plugin::export!(Instance::default());
+/// ```
+#[macro_export]
+macro_rules! export {
+    ($constructor:expr) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn unimake_plugin_declaration() -> $crate::Declaration {
+            unsafe extern "C" fn create() -> *mut Box<dyn $crate::Interface> {
+                let instance: Box<dyn $crate::Interface> = Box::new($constructor);
+                Box::into_raw(Box::new(instance))
+            }
+            $crate::Declaration {
+                abi: $crate::ABI_VERSION,
+                version: $crate::VERSION_C.as_ptr() as *const ::std::ffi::c_char,
+                create,
+            }
+        }
+    };
+}
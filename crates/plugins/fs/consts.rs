@@ -0,0 +1,16 @@
+pub const FUNCTION_GLOB: &str = "glob";
+pub const FUNCTION_COPY: &str = "copy";
+pub const FUNCTION_COPYTREE: &str = "copytree";
+pub const FUNCTION_MOVE: &str = "move";
+pub const FUNCTION_REMOVE: &str = "remove";
+pub const FUNCTION_REMOVETREE: &str = "removetree";
+pub const FUNCTION_MKDIR: &str = "mkdir";
+pub const FUNCTION_TOUCH: &str = "touch";
+pub const FUNCTION_READ_TEXT: &str = "read_text";
+pub const FUNCTION_WRITE_TEXT: &str = "write_text";
+pub const FUNCTION_NEWER: &str = "newer";
+
+pub const PLUGIN_NAME: &str = "Fs";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str =
+    "Filesystem helpers resolving relative paths against the workspace root";
@@ -0,0 +1,615 @@
+use crate::consts;
+use rustpython_vm::builtins::{PyListRef, PyModule};
+use rustpython_vm::function::{Either, FsPath, FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::{FromArgs, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+//////////////////////////////////////////////////////////////////
+// Paths
+//////////////////////////////////////////////////////////////////
+
+/// Path for the script: relative to the root if it is inside.
+fn show(layout: &sdk::Layout, path: &Path) -> String {
+    layout.relative(path).to_string_lossy().into_owned()
+}
+
+/// Deletes outside of the workspace are refused unless forced, so a
+/// wrong variable can't wipe the home directory.
+fn guard(layout: &sdk::Layout, path: &Path, force: bool, vm: &VirtualMachine) -> PyResult<()> {
+    if force || layout.contains(path) {
+        return Ok(());
+    }
+    let message = match path == layout.root() {
+        true => format!(
+            "Refusing to delete the workspace root '{}', pass force=True to allow it",
+            path.display()
+        ),
+        false => format!(
+            "Refusing to delete '{}' outside of the workspace '{}', pass force=True to allow it",
+            path.display(),
+            layout.root().display()
+        ),
+    };
+    Err(vm.new_exception_msg(vm.ctx.exceptions.permission_error.to_owned(), message))
+}
+
+fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+/// Absolute paths matching the pattern, sorted.
+fn expand(layout: &sdk::Layout, pattern: &str, vm: &VirtualMachine) -> PyResult<Vec<PathBuf>> {
+    let full = layout.resolve(pattern);
+    let paths = glob::glob(&full.to_string_lossy()).map_err(|e| {
+        vm.new_value_error(format!("Invalid file pattern '{}': {}", pattern, e.msg))
+    })?;
+    let mut result = Vec::new();
+    for path in paths {
+        let error = |e: glob::GlobError| {
+            let path = e.path().to_path_buf();
+            py::os_error(vm, e.into(), &path)
+        };
+        result.push(path.map_err(error)?);
+    }
+    Ok(result)
+}
+
+fn patterns(value: Either<FsPath, Vec<FsPath>>, vm: &VirtualMachine) -> PyResult<Vec<String>> {
+    let values = match value {
+        Either::A(v) => vec![v],
+        Either::B(v) => v,
+    };
+    values
+        .iter()
+        .map(|v| v.to_path_buf(vm).map(|p| p.to_string_lossy().into_owned()))
+        .collect()
+}
+
+fn modified(path: &Path, vm: &VirtualMachine) -> PyResult<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| py::os_error(vm, e, path))
+}
+
+/// Creates the parent directories of the file.
+fn parents(path: &Path, vm: &VirtualMachine) -> PyResult<()> {
+    match path.parent() {
+        Some(parent) => std::fs::create_dir_all(parent).map_err(|e| py::os_error(vm, e, parent)),
+        None => Ok(()),
+    }
+}
+
+/// The destination directory means the file with the source name in it.
+fn destination(src: &Path, dst: PathBuf) -> PathBuf {
+    match (dst.is_dir(), src.file_name()) {
+        (true, Some(name)) => dst.join(name),
+        _ => dst,
+    }
+}
+
+/// Copying or moving the file onto itself would truncate or delete it,
+/// links and '..' are resolved, so the other spellings are found too.
+fn distinct(src: &Path, dst: &Path, vm: &VirtualMachine) -> PyResult<()> {
+    let (Ok(from), Ok(to)) = (src.canonicalize(), dst.canonicalize()) else {
+        return Ok(());
+    };
+    if from != to {
+        return Ok(());
+    }
+    Err(vm.new_value_error(format!(
+        "'{}' and '{}' are the same file",
+        src.display(),
+        dst.display()
+    )))
+}
+
+fn copy_tree(src: &Path, dst: &Path, vm: &VirtualMachine) -> PyResult<usize> {
+    std::fs::create_dir_all(dst).map_err(|e| py::os_error(vm, e, dst))?;
+    let mut result = 0;
+    for entry in std::fs::read_dir(src).map_err(|e| py::os_error(vm, e, src))? {
+        let entry = entry.map_err(|e| py::os_error(vm, e, src))?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let kind = entry.file_type().map_err(|e| py::os_error(vm, e, &from))?;
+        if kind.is_dir() {
+            result += copy_tree(&from, &to, vm)?;
+            continue;
+        }
+        #[cfg(unix)]
+        if kind.is_symlink() {
+            let target = std::fs::read_link(&from).map_err(|e| py::os_error(vm, e, &from))?;
+            let _ = std::fs::remove_file(&to);
+            std::os::unix::fs::symlink(target, &to).map_err(|e| py::os_error(vm, e, &to))?;
+            result += 1;
+            continue;
+        }
+        std::fs::copy(&from, &to).map_err(|e| py::os_error(vm, e, &from))?;
+        result += 1;
+    }
+    Ok(result)
+}
+
+/// Removes the file, the link or the directory (with its content if the
+/// tree is set). Returns 'false' if the path does not exist.
+fn delete(path: &Path, tree: bool, vm: &VirtualMachine) -> PyResult<bool> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(py::os_error(vm, e, path)),
+    };
+    let result = match (metadata.is_dir(), tree) {
+        (true, true) => std::fs::remove_dir_all(path),
+        (true, false) => std::fs::remove_dir(path),
+        (false, _) => std::fs::remove_file(path),
+    };
+    result.map_err(|e| py::os_error(vm, e, path))?;
+    Ok(true)
+}
+
+//////////////////////////////////////////////////////////////////
+// Arguments
+//////////////////////////////////////////////////////////////////
+
+#[derive(FromArgs)]
+pub struct Remove {
+    #[pyarg(positional)]
+    pub path: FsPath,
+
+    /// Allows deleting outside of the workspace.
+    #[pyarg(any, optional, default = "false")]
+    pub force: bool,
+}
+
+#[derive(FromArgs)]
+pub struct Move {
+    #[pyarg(positional)]
+    pub src: FsPath,
+
+    #[pyarg(positional)]
+    pub dst: FsPath,
+
+    /// Allows moving from outside of the workspace.
+    #[pyarg(any, optional, default = "false")]
+    pub force: bool,
+}
+
+//////////////////////////////////////////////////////////////////
+// Search
+//////////////////////////////////////////////////////////////////
+
+pub struct Glob {}
+
+impl Function for Glob {
+    const NAME: &'static str = consts::FUNCTION_GLOB;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::glob,
+        PyMethodFlags::empty(),
+        Some(
+            "glob(patterns)\n--\n\nReturn the sorted paths (files and directories) matching the pattern or the list of patterns, '**' matches any number of directories. Paths inside of the workspace are relative to its root.",
+        ),
+    );
+}
+
+impl Glob {
+    fn glob(patterns: Either<FsPath, Vec<FsPath>>, vm: &VirtualMachine) -> PyResult<PyListRef> {
+        let layout = sdk::Layout::current(vm)?;
+        let mut result = BTreeSet::new();
+        for pattern in self::patterns(patterns, vm)? {
+            for path in expand(&layout, &pattern, vm)? {
+                result.insert(show(&layout, &path));
+            }
+        }
+        let items = result.into_iter().map(|p| vm.new_pyobj(p)).collect();
+        Ok(vm.ctx.new_list(items))
+    }
+}
+
+impl py::Registerable for Glob {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Newer {}
+
+impl Function for Newer {
+    const NAME: &'static str = consts::FUNCTION_NEWER;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::newer,
+        PyMethodFlags::empty(),
+        Some(
+            "newer(outputs, inputs)\n--\n\nReturn True if the outputs must be made again: some output is missing or some input is modified after the oldest output. Both may be the path, the pattern or the list of them. Missing input raises 'FileNotFoundError'.",
+        ),
+    );
+}
+
+impl Newer {
+    fn newer(
+        outputs: Either<FsPath, Vec<FsPath>>,
+        inputs: Either<FsPath, Vec<FsPath>>,
+        vm: &VirtualMachine,
+    ) -> PyResult<bool> {
+        let layout = sdk::Layout::current(vm)?;
+        let mut oldest: Option<SystemTime> = None;
+        for output in patterns(outputs, vm)? {
+            let paths = match is_pattern(&output) {
+                true => expand(&layout, &output, vm)?,
+                false => vec![layout.resolve(&output)],
+            };
+            if paths.is_empty() || paths.iter().any(|p| !p.exists()) {
+                return Ok(true);
+            }
+            for path in paths {
+                let time = modified(&path, vm)?;
+                oldest = Some(oldest.map_or(time, |o| o.min(time)));
+            }
+        }
+        let Some(oldest) = oldest else {
+            return Ok(true);
+        };
+        for input in patterns(inputs, vm)? {
+            let paths = match is_pattern(&input) {
+                true => expand(&layout, &input, vm)?,
+                false => vec![layout.resolve(&input)],
+            };
+            for path in paths {
+                if modified(&path, vm)? > oldest {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl py::Registerable for Newer {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Copy and move
+//////////////////////////////////////////////////////////////////
+
+pub struct Copy {}
+
+impl Function for Copy {
+    const NAME: &'static str = consts::FUNCTION_COPY;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::copy,
+        PyMethodFlags::empty(),
+        Some(
+            "copy(src, dst)\n--\n\nCopy the file, into the directory if 'dst' is the existing one. Missing parent directories are created, copying the file onto itself raises 'ValueError'. Return the destination path.",
+        ),
+    );
+}
+
+impl Copy {
+    fn copy(src: FsPath, dst: FsPath, vm: &VirtualMachine) -> PyResult<String> {
+        let layout = sdk::Layout::current(vm)?;
+        let src = layout.resolve(src.to_path_buf(vm)?);
+        if src.is_dir() {
+            let message = format!("Is a directory, use 'copytree': '{}'", src.display());
+            return Err(
+                vm.new_exception_msg(vm.ctx.exceptions.is_a_directory_error.to_owned(), message)
+            );
+        }
+        let dst = destination(&src, layout.resolve(dst.to_path_buf(vm)?));
+        distinct(&src, &dst, vm)?;
+        parents(&dst, vm)?;
+        std::fs::copy(&src, &dst).map_err(|e| py::os_error(vm, e, &src))?;
+        Ok(show(&layout, &dst))
+    }
+}
+
+impl py::Registerable for Copy {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct CopyTree {}
+
+impl Function for CopyTree {
+    const NAME: &'static str = consts::FUNCTION_COPYTREE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::copytree,
+        PyMethodFlags::empty(),
+        Some(
+            "copytree(src, dst)\n--\n\nCopy the directory content into 'dst', which is created if missing. Existing files are overwritten, symbolic links are copied as links. Return the number of copied files.",
+        ),
+    );
+}
+
+impl CopyTree {
+    fn copytree(src: FsPath, dst: FsPath, vm: &VirtualMachine) -> PyResult<usize> {
+        let layout = sdk::Layout::current(vm)?;
+        let src = layout.resolve(src.to_path_buf(vm)?);
+        let dst = layout.resolve(dst.to_path_buf(vm)?);
+        if !src.is_dir() {
+            let message = format!("Not a directory: '{}'", src.display());
+            return Err(
+                vm.new_exception_msg(vm.ctx.exceptions.not_a_directory_error.to_owned(), message)
+            );
+        }
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        if dst.starts_with(&src) || canonical(&dst).starts_with(canonical(&src)) {
+            let message = format!(
+                "Can't copy '{}' into itself: '{}'",
+                src.display(),
+                dst.display()
+            );
+            return Err(vm.new_value_error(message));
+        }
+        copy_tree(&src, &dst, vm)
+    }
+}
+
+impl py::Registerable for CopyTree {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Rename {}
+
+impl Function for Rename {
+    const NAME: &'static str = consts::FUNCTION_MOVE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::rename,
+        PyMethodFlags::empty(),
+        Some(
+            "move(src, dst, force=False)\n--\n\nMove the file or the directory, into the directory if 'dst' is the existing one. Missing parent directories are created, other filesystems are supported. Moving from outside of the workspace raises 'PermissionError' unless 'force' is set. Return the destination path.",
+        ),
+    );
+}
+
+impl Rename {
+    fn rename(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<String> {
+        let args = py::args_to::<Move>(inputs, vm)?;
+        let layout = sdk::Layout::current(vm)?;
+        let src = layout.resolve(args.src.to_path_buf(vm)?);
+        guard(&layout, &src, args.force, vm)?;
+        std::fs::symlink_metadata(&src).map_err(|e| py::os_error(vm, e, &src))?;
+        let dst = destination(&src, layout.resolve(args.dst.to_path_buf(vm)?));
+        distinct(&src, &dst, vm)?;
+        if src.is_dir() && dst.starts_with(&src) {
+            let message = format!(
+                "Can't move '{}' into itself: '{}'",
+                src.display(),
+                dst.display()
+            );
+            return Err(vm.new_value_error(message));
+        }
+        parents(&dst, vm)?;
+        match std::fs::rename(&src, &dst) {
+            Ok(()) => {}
+            // Rename works within the filesystem only.
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                match src.is_dir() {
+                    true => copy_tree(&src, &dst, vm).map(|_| ())?,
+                    false => std::fs::copy(&src, &dst)
+                        .map(|_| ())
+                        .map_err(|e| py::os_error(vm, e, &src))?,
+                }
+                delete(&src, true, vm)?;
+            }
+            Err(e) => return Err(py::os_error(vm, e, &src)),
+        }
+        Ok(show(&layout, &dst))
+    }
+}
+
+impl py::Registerable for Rename {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Remove
+//////////////////////////////////////////////////////////////////
+
+pub struct Delete {}
+
+impl Function for Delete {
+    const NAME: &'static str = consts::FUNCTION_REMOVE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::remove,
+        PyMethodFlags::empty(),
+        Some(
+            "remove(path, force=False)\n--\n\nRemove the file, the symbolic link or the empty directory. Return False if the path does not exist. Paths outside of the workspace raise 'PermissionError' unless 'force' is set.",
+        ),
+    );
+}
+
+impl Delete {
+    fn remove(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        let args = py::args_to::<Remove>(inputs, vm)?;
+        let layout = sdk::Layout::current(vm)?;
+        let path = layout.resolve(args.path.to_path_buf(vm)?);
+        guard(&layout, &path, args.force, vm)?;
+        delete(&path, false, vm)
+    }
+}
+
+impl py::Registerable for Delete {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct DeleteTree {}
+
+impl Function for DeleteTree {
+    const NAME: &'static str = consts::FUNCTION_REMOVETREE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::removetree,
+        PyMethodFlags::empty(),
+        Some(
+            "removetree(path, force=False)\n--\n\nRemove the directory with its content (or the file). Symbolic links are removed, not their targets. Return False if the path does not exist. Paths outside of the workspace raise 'PermissionError' unless 'force' is set.",
+        ),
+    );
+}
+
+impl DeleteTree {
+    fn removetree(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        let args = py::args_to::<Remove>(inputs, vm)?;
+        let layout = sdk::Layout::current(vm)?;
+        let path = layout.resolve(args.path.to_path_buf(vm)?);
+        guard(&layout, &path, args.force, vm)?;
+        delete(&path, true, vm)
+    }
+}
+
+impl py::Registerable for DeleteTree {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Create
+//////////////////////////////////////////////////////////////////
+
+pub struct Mkdir {}
+
+impl Function for Mkdir {
+    const NAME: &'static str = consts::FUNCTION_MKDIR;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::mkdir,
+        PyMethodFlags::empty(),
+        Some(
+            "mkdir(path)\n--\n\nCreate the directory with its parents, like 'mkdir -p' does. The existing directory is fine. Return the path.",
+        ),
+    );
+}
+
+impl Mkdir {
+    fn mkdir(path: FsPath, vm: &VirtualMachine) -> PyResult<String> {
+        let layout = sdk::Layout::current(vm)?;
+        let path = layout.resolve(path.to_path_buf(vm)?);
+        std::fs::create_dir_all(&path).map_err(|e| py::os_error(vm, e, &path))?;
+        Ok(show(&layout, &path))
+    }
+}
+
+impl py::Registerable for Mkdir {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Touch {}
+
+impl Function for Touch {
+    const NAME: &'static str = consts::FUNCTION_TOUCH;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::touch,
+        PyMethodFlags::empty(),
+        Some(
+            "touch(path)\n--\n\nCreate the empty file (and its parent directories) or set the modification time of the existing one to now. Return the path.",
+        ),
+    );
+}
+
+impl Touch {
+    fn touch(path: FsPath, vm: &VirtualMachine) -> PyResult<String> {
+        let layout = sdk::Layout::current(vm)?;
+        let path = layout.resolve(path.to_path_buf(vm)?);
+        parents(&path, vm)?;
+        File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .map_err(|e| py::os_error(vm, e, &path))?;
+        Ok(show(&layout, &path))
+    }
+}
+
+impl py::Registerable for Touch {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Text
+//////////////////////////////////////////////////////////////////
+
+pub struct ReadText {}
+
+impl Function for ReadText {
+    const NAME: &'static str = consts::FUNCTION_READ_TEXT;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::read_text,
+        PyMethodFlags::empty(),
+        Some("read_text(path)\n--\n\nReturn the content of the UTF-8 file."),
+    );
+}
+
+impl ReadText {
+    fn read_text(path: FsPath, vm: &VirtualMachine) -> PyResult<String> {
+        let path = sdk::Layout::current(vm)?.resolve(path.to_path_buf(vm)?);
+        let data = std::fs::read(&path).map_err(|e| py::os_error(vm, e, &path))?;
+        String::from_utf8(data).map_err(|e| {
+            vm.new_unicode_decode_error(format!(
+                "File is not UTF-8 ({}): '{}'",
+                e.utf8_error(),
+                path.display()
+            ))
+        })
+    }
+}
+
+impl py::Registerable for ReadText {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct WriteText {}
+
+impl Function for WriteText {
+    const NAME: &'static str = consts::FUNCTION_WRITE_TEXT;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::write_text,
+        PyMethodFlags::empty(),
+        Some(
+            "write_text(path, text)\n--\n\nWrite the text to the file as UTF-8, missing parent directories are created. The file is replaced at once, so readers never see it partially written. Return the path.",
+        ),
+    );
+}
+
+impl WriteText {
+    fn write_text(path: FsPath, text: String, vm: &VirtualMachine) -> PyResult<String> {
+        let layout = sdk::Layout::current(vm)?;
+        let path = layout.resolve(path.to_path_buf(vm)?);
+        sdk::write_atomic(&path, text.as_bytes())
+            .map_err(|e| vm.new_os_error(format!("Failed to write '{}': {}", path.display(), e)))?;
+        Ok(show(&layout, &path))
+    }
+}
+
+impl py::Registerable for WriteText {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
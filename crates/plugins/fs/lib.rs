@@ -0,0 +1,5 @@
+mod consts;
+mod fs;
+mod plugin;
+
+pub use plugin::Instance;
@@ -0,0 +1,91 @@
+use crate::{consts, fs};
+use clap::Command;
+use plugin::Interface as PluginInterface;
+use rustpython::vm::pymodule;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::{PyRef, VirtualMachine};
+use sdk::py;
+use sdk::py::{Registerable, Registerer};
+
+#[derive(Default)]
+pub struct Instance {}
+
+impl Registerable for Instance {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        module.register::<fs::Glob>(vm);
+        module.register::<fs::Newer>(vm);
+        module.register::<fs::Copy>(vm);
+        module.register::<fs::CopyTree>(vm);
+        module.register::<fs::Rename>(vm);
+        module.register::<fs::Delete>(vm);
+        module.register::<fs::DeleteTree>(vm);
+        module.register::<fs::Mkdir>(vm);
+        module.register::<fs::Touch>(vm);
+        module.register::<fs::ReadText>(vm);
+        module.register::<fs::WriteText>(vm);
+    }
+}
+
+impl PluginInterface for Instance {
+    fn initialize(&self, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        plugin::Info {
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
+        }
+    }
+
+    fn examples(&self) -> Vec<String> {
+        vec![String::from(
+            r#"from umk import cli, fs, process
+
+
+@cli.cmd("docs")
+def docs():
+    # Paths are relative to the workspace root, wherever the script is run
+    if not fs.newer("build/docs/index.html", ["docs/**/*.md", "mkdocs.yml"]):
+        print("Docs are up to date")
+        return
+    fs.removetree("build/docs")
+    process.run(["mkdocs", "build", "--site-dir", "build/docs"])
+    fs.copytree("assets", "build/docs/assets")
+    fs.write_text("build/docs/VERSION", fs.read_text("VERSION").strip())
+"#,
+        )]
+    }
+
+    fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        Ok(None)
+    }
+
+    fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, _matches: &clap::ArgMatches, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
+        let module = _module::make_module(vm);
+        module.register::<fs::Glob>(vm);
+        module.register::<fs::Newer>(vm);
+        module.register::<fs::Copy>(vm);
+        module.register::<fs::CopyTree>(vm);
+        module.register::<fs::Rename>(vm);
+        module.register::<fs::Delete>(vm);
+        module.register::<fs::DeleteTree>(vm);
+        module.register::<fs::Mkdir>(vm);
+        module.register::<fs::Touch>(vm);
+        module.register::<fs::ReadText>(vm);
+        module.register::<fs::WriteText>(vm);
+        py::register::submodule(vm, root, module);
+    }
+}
+
+#[pymodule(name = "fs")]
+mod _module {}
@@ -0,0 +1,88 @@
+use crate::consts;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::function::{PyMethodDef, PyMethodFlags};
+use rustpython_vm::{PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+
+//////////////////////////////////////////////////////////////////
+// Registry
+//////////////////////////////////////////////////////////////////
+
+/// Cleanup handlers in the registration order.
+#[derive(Debug, Clone, Default)]
+pub struct Registry(pub Vec<PyObjectRef>);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_CLEANUP;
+}
+
+pub fn list(vm: &VirtualMachine) -> PyResult<Vec<PyObjectRef>> {
+    match py::service::<Registry>(vm)? {
+        None => Ok(Default::default()),
+        Some(v) => Ok(v.0.clone()),
+    }
+}
+
+/// Removes the handlers, so the script can be loaded again.
+pub fn forget(vm: &VirtualMachine) -> PyResult<()> {
+    py::provide(vm, Registry::default())
+}
+
+/// Calls the handlers in the reverse order, failures are reported only.
+pub fn run(vm: &VirtualMachine) -> PyResult<()> {
+    for handler in list(vm)?.iter().rev() {
+        if let Err(e) = handler.call((), vm) {
+            let name = handler
+                .get_attr("__name__", vm)
+                .and_then(|n| n.str(vm))
+                .map(|n| n.to_string())
+                .unwrap_or_else(|_| String::from("<unknown>"));
+            let error = sdk::Error::python(e, vm);
+            sdk::warning(format!(
+                "Cleanup handler '{}' failed: {}",
+                name,
+                error.message()
+            ));
+        }
+    }
+    py::flush(vm);
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////////
+// Function
+//////////////////////////////////////////////////////////////////
+
+pub struct Cleanup {}
+
+impl Function for Cleanup {
+    const NAME: &'static str = consts::FUNCTION_CLEANUP;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::cleanup,
+        PyMethodFlags::empty(),
+        Some(
+            "cleanup(function)\n--\n\nRegister the function called without arguments when the run is interrupted (Ctrl+C or SIGTERM), after the running process is killed. Handlers are called in the reverse order. Returns the function, so it may be used as a decorator.",
+        ),
+    );
+}
+
+impl Cleanup {
+    fn cleanup(function: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if !function.is_callable() {
+            let msg = String::from("Cleanup handler must be callable");
+            return Err(py::registration_error(vm, msg));
+        }
+        let mut handlers = list(vm)?;
+        handlers.push(function.clone());
+        py::provide(vm, Registry(handlers))?;
+        Ok(function)
+    }
+}
+
+impl py::Registerable for Cleanup {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
@@ -0,0 +1,10 @@
+pub const FUNCTION_RUN: &str = "run";
+pub const FUNCTION_OUTPUT: &str = "output";
+pub const FUNCTION_CLEANUP: &str = "cleanup";
+
+pub const SERVICE_CLEANUP: &str = "process/cleanup";
+
+pub const PLUGIN_NAME: &str = "Process";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str =
+    "Runs child processes which are killed when the command is interrupted or timed out";
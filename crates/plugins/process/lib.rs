@@ -0,0 +1,6 @@
+mod cleanup;
+mod consts;
+mod plugin;
+mod process;
+
+pub use plugin::Instance;
@@ -0,0 +1,90 @@
+use crate::{cleanup, consts, process};
+use clap::Command;
+use plugin::Interface as PluginInterface;
+use rustpython::vm::pymodule;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::{PyRef, VirtualMachine};
+use sdk::py;
+use sdk::py::{Registerable, Registerer};
+
+#[derive(Default)]
+pub struct Instance {}
+
+impl Registerable for Instance {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        module.register::<process::Run>(vm);
+        module.register::<process::Output>(vm);
+        module.register::<cleanup::Cleanup>(vm);
+    }
+}
+
+impl PluginInterface for Instance {
+    fn initialize(&self, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        plugin::Info {
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
+        }
+    }
+
+    fn examples(&self) -> Vec<String> {
+        vec![String::from(
+            r#"from umk import cli, process
+
+
+@process.cleanup
+def unlock():
+    print("Removing the deploy lock")
+
+
+# Killed after 10 minutes, tried 3 times at most
+@cli.cmd("deploy", timeout=600, retries=2, retry_delay=5)
+def deploy():
+    process.run(["rsync", "-a", "build/", "server:/srv/app"])
+    revision = process.output("git rev-parse HEAD").strip()
+    print(f"Deployed {revision}")
+"#,
+        )]
+    }
+
+    fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        Ok(None)
+    }
+
+    fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, _matches: &clap::ArgMatches, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
+        let module = _module::make_module(vm);
+        module.register::<process::Run>(vm);
+        module.register::<process::Output>(vm);
+        module.register::<cleanup::Cleanup>(vm);
+        py::register::submodule(vm, root, module);
+    }
+
+    /// The script may be loaded again (e.g. in the watch mode).
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        cleanup::forget(vm).map_err(|e| sdk::Error::python(e, vm))
+    }
+
+    fn on_exit(&self, result: &sdk::Result, vm: &VirtualMachine) -> sdk::Result {
+        match result {
+            Err(e) if e.kind() == sdk::ErrorKind::Interrupted => {
+                cleanup::run(vm).map_err(|e| sdk::Error::python(e, vm))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[pymodule(name = "process")]
+mod _module {}
@@ -0,0 +1,328 @@
+use crate::consts;
+use rustpython_vm::builtins::{PyBaseException, PyDictRef, PyModule};
+use rustpython_vm::function::{Either, FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::{FromArgs, PyObjectRef, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How often the interruptions are checked while the child is running.
+const POLL: Duration = Duration::from_millis(50);
+
+//////////////////////////////////////////////////////////////////
+// Arguments
+//////////////////////////////////////////////////////////////////
+
+#[derive(FromArgs)]
+pub struct Args {
+    /// The string is run by the shell.
+    #[pyarg(positional)]
+    pub args: Either<String, Vec<String>>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub cwd: Option<String>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub env: Option<PyDictRef>,
+
+    #[pyarg(any, optional, default = "true")]
+    pub check: bool,
+}
+
+impl Args {
+    fn command(&self, vm: &VirtualMachine) -> PyResult<Command> {
+        let mut result = match &self.args {
+            Either::A(line) if cfg!(windows) => {
+                let mut command = Command::new("cmd");
+                command.arg("/C").arg(line);
+                command
+            }
+            Either::A(line) => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(line);
+                command
+            }
+            Either::B(args) => {
+                let Some((program, args)) = args.split_first() else {
+                    return Err(vm.new_value_error("Process arguments are empty".to_string()));
+                };
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+        };
+        if let Some(cwd) = &self.cwd {
+            result.current_dir(cwd);
+        }
+        // Values are converted like 'str()' does, e.g. numbers are allowed.
+        for (name, value) in self.env.iter().flat_map(|e| e.into_iter()) {
+            result.env(name.str(vm)?.as_str(), value.str(vm)?.as_str());
+        }
+        // The own group lets the kill reach the processes started by the
+        // child too, e.g. by the shell.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut result, 0);
+        result
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        Ok(result)
+    }
+
+    fn program(&self) -> String {
+        match &self.args {
+            Either::A(line) => line.clone(),
+            Either::B(args) => args.join(" "),
+        }
+    }
+
+    fn object(&self, vm: &VirtualMachine) -> PyObjectRef {
+        match &self.args {
+            Either::A(line) => vm.new_pyobj(line.clone()),
+            Either::B(args) => vm.new_pyobj(
+                args.iter()
+                    .map(|a| vm.new_pyobj(a.clone()))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Functions
+//////////////////////////////////////////////////////////////////
+
+pub struct Run {}
+
+impl Function for Run {
+    const NAME: &'static str = consts::FUNCTION_RUN;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::run,
+        PyMethodFlags::empty(),
+        Some(
+            "run(args, cwd=None, env=None, check=True)\n--\n\nRun the program (the string is run by the shell) and return its exit code. The output goes to 'sys.stdout' and 'sys.stderr'. The process runs in its own process group, the whole group is killed when the command is interrupted or timed out. The non-zero exit code raises 'subprocess.CalledProcessError' if 'check' is set.",
+        ),
+    );
+}
+
+impl Run {
+    fn run(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<i32> {
+        let args = py::args_to::<Args>(inputs, vm)?;
+        let (code, _) = execute(&args, false, vm)?;
+        Ok(code)
+    }
+}
+
+impl py::Registerable for Run {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Output {}
+
+impl Function for Output {
+    const NAME: &'static str = consts::FUNCTION_OUTPUT;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::output,
+        PyMethodFlags::empty(),
+        Some(
+            "output(args, cwd=None, env=None, check=True)\n--\n\nRun the program like 'run' does, but return its standard output as a string.",
+        ),
+    );
+}
+
+impl Output {
+    fn output(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<String> {
+        let args = py::args_to::<Args>(inputs, vm)?;
+        let (_, output) = execute(&args, true, vm)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+}
+
+impl py::Registerable for Output {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Execution
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn name(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Runs the process and forwards its output to the python streams, so
+/// the output redirection of the python code applies to it too. The
+/// signals are checked while waiting, the process is killed if some
+/// of them raises (e.g. Ctrl+C or the command timeout).
+fn execute(args: &Args, capture: bool, vm: &VirtualMachine) -> PyResult<(i32, Vec<u8>)> {
+    let mut child = args
+        .command(vm)?
+        .spawn()
+        .map_err(|e| vm.new_os_error(format!("Failed to run '{}': {}", args.program(), e)))?;
+    let (sender, chunks) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        read(Stream::Stdout, stdout, sender.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        read(Stream::Stderr, stderr, sender);
+    }
+    let mut output = Vec::new();
+    let mut pending = [Vec::new(), Vec::new()];
+    let mut status: Option<ExitStatus> = None;
+    let mut closed = false;
+    let result = loop {
+        match chunks.recv_timeout(POLL) {
+            Ok((Stream::Stdout, chunk)) if capture => output.extend(chunk),
+            Ok((stream, chunk)) => {
+                let buffer = &mut pending[stream as usize];
+                buffer.extend(chunk);
+                if let Err(e) = write(stream, &decode(buffer), vm) {
+                    break Err(e);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                if !closed {
+                    closed = true;
+                } else if status.is_none() {
+                    thread::sleep(POLL);
+                }
+            }
+        }
+        if status.is_none() {
+            match child.try_wait() {
+                Ok(v) => status = v,
+                Err(e) => break Err(vm.new_os_error(e.to_string())),
+            }
+        }
+        if let Err(e) = vm.check_signals() {
+            break Err(e);
+        }
+        if closed && let Some(status) = status {
+            break Ok(status);
+        }
+    };
+    let status = match result {
+        Ok(v) => v,
+        Err(e) => {
+            kill(&mut child);
+            return Err(e);
+        }
+    };
+    for (stream, rest) in [Stream::Stdout, Stream::Stderr].into_iter().zip(pending) {
+        write(stream, &String::from_utf8_lossy(&rest), vm)?;
+    }
+    py::flush(vm);
+    let code = code(status);
+    if args.check && code != 0 {
+        return Err(failed(args, code, &output, vm));
+    }
+    Ok((code, output))
+}
+
+fn read(stream: Stream, mut source: impl Read + Send + 'static, sender: Sender) {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match source.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => {
+                    if sender.send((stream, buffer[..size].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+type Sender = mpsc::Sender<(Stream, Vec<u8>)>;
+
+/// Takes the valid text from the buffer, the incomplete character at
+/// the end is left for the next chunk.
+fn decode(buffer: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(buffer) {
+        Ok(_) => buffer.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => buffer.len(),
+    };
+    let rest = buffer.split_off(valid);
+    let text = String::from_utf8_lossy(buffer).into_owned();
+    *buffer = rest;
+    text
+}
+
+fn write(stream: Stream, text: &str, vm: &VirtualMachine) -> PyResult<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let target = vm.sys_module.get_attr(stream.name(), vm)?;
+    vm.call_method(&target, "write", (text.to_string(),))?;
+    Ok(())
+}
+
+/// Kills the process group of the child, its own process is the last
+/// resort if the group is gone.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(group) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: the call has no memory arguments.
+        unsafe {
+            libc::killpg(group, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Exit code like the 'subprocess' module gives: the negative signal
+/// number if the process is terminated by the signal.
+fn code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return -signal;
+    }
+    status.code().unwrap_or(-1)
+}
+
+fn failed(
+    args: &Args,
+    code: i32,
+    output: &[u8],
+    vm: &VirtualMachine,
+) -> rustpython_vm::builtins::PyBaseExceptionRef {
+    let error = vm.import("subprocess", 0).and_then(|module| {
+        let class = module.get_attr("CalledProcessError", vm)?;
+        let output = vm.new_pyobj(String::from_utf8_lossy(output).into_owned());
+        class.call((code, args.object(vm), output), vm)
+    });
+    match error.map(|e| e.downcast::<PyBaseException>()) {
+        Ok(Ok(e)) => e,
+        Ok(Err(_)) | Err(_) => vm.new_runtime_error(format!(
+            "Command '{}' returned non-zero exit status {}",
+            args.program(),
+            code
+        )),
+    }
+}
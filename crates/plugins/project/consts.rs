@@ -0,0 +1,9 @@
+pub const FUNCTION_INIT: &str = "init";
+pub const FUNCTION_GET: &str = "get";
+
+pub const SERVICE_INSTANCE: &str = "project/instance";
+
+pub const PLUGIN_NAME: &str = "Project";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str =
+    "Allows users to declare the project info used by other plugins, e.g. in templates";
@@ -0,0 +1,258 @@
+use rustpython_vm::builtins::{PyDict, PyDictRef, PyListRef, PyModule, PyStr};
+use rustpython_vm::common::lock::PyRwLock;
+use rustpython_vm::convert::ToPyObject;
+use rustpython_vm::types::{Constructor, DefaultConstructor, Initializer, Representable};
+use rustpython_vm::{FromArgs, Py, PyPayload, PyRef, PyResult, VirtualMachine, pyclass};
+use sdk::py;
+use std::collections::BTreeMap;
+
+//////////////////////////////////////////////////////////////////
+// Contributor
+//////////////////////////////////////////////////////////////////
+
+#[pyclass(module = false, name = "Contributor")]
+#[derive(Debug, Default, PyPayload)]
+pub struct Contributor {
+    core: PyRwLock<sdk::ProjectContributor>,
+}
+
+impl py::Registerable for Contributor {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::class::<Self>(vm, module)
+    }
+}
+
+impl DefaultConstructor for Contributor {}
+
+impl Representable for Contributor {
+    fn repr_str(zelf: &Py<Self>, _vm: &VirtualMachine) -> PyResult<String> {
+        let core = zelf.core.read();
+        Ok(format!("Contributor(name='{}')", core.name))
+    }
+}
+
+#[derive(FromArgs, Debug)]
+pub struct ContributorArgs {
+    #[pyarg(any, optional, default = "String::new()")]
+    pub name: String,
+
+    #[pyarg(any, optional, default = "Vec::new()")]
+    pub emails: Vec<String>,
+
+    #[pyarg(any, optional, default = "None")]
+    pub socials: Option<PyDictRef>,
+}
+
+impl Initializer for Contributor {
+    type Args = ContributorArgs;
+
+    fn init(zelf: PyRef<Self>, args: Self::Args, vm: &VirtualMachine) -> PyResult<()> {
+        let socials = match args.socials {
+            Some(v) => socials(v, vm)?,
+            None => BTreeMap::new(),
+        };
+        *zelf.core.write() = sdk::ProjectContributor {
+            name: args.name,
+            emails: args.emails,
+            socials,
+        };
+        Ok(())
+    }
+}
+
+#[pyclass(with(Constructor, Initializer, Representable))]
+impl Contributor {
+    #[pygetset]
+    fn name(&self) -> String {
+        self.core.read().name.clone()
+    }
+
+    #[pygetset(setter)]
+    fn set_name(&self, value: String) {
+        self.core.write().name = value;
+    }
+
+    #[pygetset]
+    fn emails(&self, vm: &VirtualMachine) -> PyListRef {
+        let items = self
+            .core
+            .read()
+            .emails
+            .iter()
+            .map(|e| vm.new_pyobj(e.as_str()))
+            .collect();
+        vm.ctx.new_list(items)
+    }
+
+    #[pygetset(setter)]
+    fn set_emails(&self, value: Vec<String>) {
+        self.core.write().emails = value;
+    }
+
+    #[pygetset]
+    fn socials(&self, vm: &VirtualMachine) -> PyResult<PyDictRef> {
+        let result = PyDict::new_ref(&vm.ctx);
+        for (key, value) in self.core.read().socials.iter() {
+            result.set_item(key.as_str(), vm.new_pyobj(value.as_str()), vm)?;
+        }
+        Ok(result)
+    }
+
+    #[pygetset(setter)]
+    fn set_socials(&self, value: PyDictRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.core.write().socials = socials(value, vm)?;
+        Ok(())
+    }
+}
+
+fn socials(value: PyDictRef, vm: &VirtualMachine) -> PyResult<BTreeMap<String, String>> {
+    let mut result = BTreeMap::new();
+    for (key, value) in value {
+        let key = key.downcast::<PyStr>();
+        let value = value.downcast::<PyStr>();
+        match (key, value) {
+            (Ok(k), Ok(v)) => result.insert(k.as_str().to_string(), v.as_str().to_string()),
+            _ => return Err(vm.new_type_error("Expect dict[str, str]".to_string())),
+        };
+    }
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Info
+//////////////////////////////////////////////////////////////////
+
+#[pyclass(module = false, name = "Info")]
+#[derive(Debug, Default, PyPayload)]
+pub struct Info {
+    core: PyRwLock<sdk::ProjectInfo>,
+}
+
+impl py::Registerable for Info {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::class::<Self>(vm, module)
+    }
+}
+
+impl DefaultConstructor for Info {}
+
+impl Representable for Info {
+    fn repr_str(zelf: &Py<Self>, _vm: &VirtualMachine) -> PyResult<String> {
+        let core = zelf.core.read();
+        Ok(format!(
+            "Info(name='{}', version='{}')",
+            core.name, core.version
+        ))
+    }
+}
+
+#[derive(FromArgs, Debug)]
+pub struct InfoArgs {
+    #[pyarg(any, optional, default = "String::new()")]
+    pub name: String,
+
+    #[pyarg(any, optional, default = "String::new()")]
+    pub version: String,
+
+    #[pyarg(any, optional, default = "String::new()")]
+    pub title: String,
+
+    #[pyarg(any, optional, default = "String::new()")]
+    pub description: String,
+}
+
+impl Initializer for Info {
+    type Args = InfoArgs;
+
+    fn init(zelf: PyRef<Self>, args: Self::Args, _vm: &VirtualMachine) -> PyResult<()> {
+        *zelf.core.write() = sdk::ProjectInfo {
+            name: args.name,
+            version: args.version,
+            title: args.title,
+            description: args.description,
+            contributors: Vec::new(),
+        };
+        Ok(())
+    }
+}
+
+#[pyclass(flags(BASETYPE), with(Constructor, Initializer, Representable))]
+impl Info {
+    /// Values for other plugins.
+    pub fn core(&self) -> sdk::ProjectInfo {
+        self.core.read().clone()
+    }
+
+    pub fn set_core(&self, value: sdk::ProjectInfo) {
+        *self.core.write() = value;
+    }
+
+    #[pygetset]
+    fn name(&self) -> String {
+        self.core.read().name.clone()
+    }
+
+    #[pygetset(setter)]
+    fn set_name(&self, value: String) {
+        self.core.write().name = value;
+    }
+
+    #[pygetset]
+    fn version(&self) -> String {
+        self.core.read().version.clone()
+    }
+
+    #[pygetset(setter)]
+    fn set_version(&self, value: String) {
+        self.core.write().version = value;
+    }
+
+    #[pygetset]
+    fn title(&self) -> String {
+        self.core.read().title.clone()
+    }
+
+    #[pygetset(setter)]
+    fn set_title(&self, value: String) {
+        self.core.write().title = value;
+    }
+
+    #[pygetset]
+    fn description(&self) -> String {
+        self.core.read().description.clone()
+    }
+
+    #[pygetset(setter)]
+    fn set_description(&self, value: String) {
+        self.core.write().description = value;
+    }
+
+    /// Copies of the contributors, the list is replaced as a whole.
+    #[pygetset]
+    fn contributors(&self, vm: &VirtualMachine) -> PyListRef {
+        let items = self
+            .core
+            .read()
+            .contributors
+            .iter()
+            .map(|c| {
+                Contributor {
+                    core: PyRwLock::new(c.clone()),
+                }
+                .to_pyobject(vm)
+            })
+            .collect();
+        vm.ctx.new_list(items)
+    }
+
+    #[pygetset(setter)]
+    fn set_contributors(&self, value: PyListRef, vm: &VirtualMachine) -> PyResult<()> {
+        let mut result = Vec::new();
+        for item in value.borrow_vec().iter() {
+            let item = py::to::<Contributor>(vm, item, "Expect list[Contributor]")?;
+            result.push(item.core.read().clone());
+        }
+        self.core.write().contributors = result;
+        Ok(())
+    }
+}
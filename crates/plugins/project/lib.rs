@@ -0,0 +1,6 @@
+mod consts;
+mod info;
+mod plugin;
+mod project;
+
+pub use plugin::Instance;
@@ -0,0 +1,94 @@
+use crate::{consts, info, project};
+use clap::Command;
+use plugin::Interface as PluginInterface;
+use rustpython::vm::pymodule;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::class::PyClassImpl;
+use rustpython_vm::{PyRef, VirtualMachine};
+use sdk::py;
+use sdk::py::{Registerable, Registerer};
+
+#[derive(Default)]
+pub struct Instance {}
+
+impl Registerable for Instance {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        module.register::<info::Info>(vm);
+        module.register::<info::Contributor>(vm);
+        module.register::<project::Init>(vm);
+        module.register::<project::Get>(vm);
+    }
+}
+
+impl PluginInterface for Instance {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result {
+        info::Info::make_class(&vm.ctx);
+        info::Contributor::make_class(&vm.ctx);
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        plugin::Info {
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
+        }
+    }
+
+    fn examples(&self) -> Vec<String> {
+        vec![String::from(
+            r#"from umk import cli, project
+
+
+@project.init
+def _(p: project.Info):
+    p.name = "app"
+    p.version = "1.4.2"
+    p.description = "Example application"
+    p.contributors = [project.Contributor("Jane Doe", ["jane@example.com"])]
+
+
+@cli.cmd("version")
+def version():
+    print(project.get().version)
+"#,
+        )]
+    }
+
+    fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        Ok(None)
+    }
+
+    fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, _matches: &clap::ArgMatches, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
+        let module = _module::make_module(vm);
+        module.register::<info::Info>(vm);
+        module.register::<info::Contributor>(vm);
+        module.register::<project::Init>(vm);
+        module.register::<project::Get>(vm);
+        py::register::submodule(vm, root, module);
+    }
+
+    /// The script may be loaded again (e.g. in the watch mode), so it
+    /// may declare the project again.
+    fn before_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        py::provide(vm, project::Registry(None)).map_err(|e| sdk::Error::python(e, vm))
+    }
+
+    /// The values are published once the script is loaded, so it may
+    /// change them after 'init'.
+    fn after_load(&self, vm: &VirtualMachine) -> sdk::Result {
+        let info = project::info(vm).map_err(|e| sdk::Error::python(e, vm))?;
+        py::provide(vm, info).map_err(|e| sdk::Error::python(e, vm))
+    }
+}
+
+#[pymodule(name = "project")]
+mod _module {}
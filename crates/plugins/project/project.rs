@@ -0,0 +1,115 @@
+use crate::consts;
+use crate::info::Info;
+use rustpython_vm::builtins::{PyModule, PyType};
+use rustpython_vm::function::{PyMethodDef, PyMethodFlags};
+use rustpython_vm::{PyObjectRef, PyPayload, PyRef, PyResult, VirtualMachine};
+use sdk::py;
+use sdk::py::Function;
+
+//////////////////////////////////////////////////////////////////
+// Access
+//////////////////////////////////////////////////////////////////
+
+/// The instance made by 'init', if it is used.
+#[derive(Debug, Clone, Default)]
+pub struct Registry(pub Option<PyRef<Info>>);
+
+impl py::Service for Registry {
+    const NAME: &'static str = consts::SERVICE_INSTANCE;
+}
+
+/// Values of the declared project, the name defaults to the workspace
+/// directory name.
+pub fn info(vm: &VirtualMachine) -> PyResult<sdk::ProjectInfo> {
+    let mut result = match py::service::<Registry>(vm)?.and_then(|r| r.0.clone()) {
+        Some(instance) => instance.core(),
+        None => sdk::ProjectInfo::default(),
+    };
+    if result.name.is_empty() {
+        let layout = sdk::Layout::current(vm)?;
+        if let Some(name) = layout.root().file_name() {
+            result.name = name.to_string_lossy().into_owned();
+        }
+    }
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Functions
+//////////////////////////////////////////////////////////////////
+
+pub struct Init {}
+
+impl Function for Init {
+    const NAME: &'static str = consts::FUNCTION_INIT;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::init,
+        PyMethodFlags::empty(),
+        Some(
+            "init(func)\n--\n\nDeclare the project: the function is called with the 'Info' to fill, the class (derived from 'Info') is instantiated without arguments. The values are taken once the script is loaded, the name defaults to the workspace directory name. Other plugins use them, e.g. 'project' of 'umk.template'.",
+        ),
+    );
+}
+
+impl Init {
+    fn init(func: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        Self::declare(&func, vm).map_err(|e| py::decorator_note(vm, e, "@project.init", &func))?;
+        Ok(func)
+    }
+
+    fn declare(func: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if py::service::<Registry>(vm)?.is_some_and(|r| r.0.is_some()) {
+            let msg = "Project 'init' must be used once";
+            return Err(py::registration_error(vm, msg));
+        }
+        let instance = match func.downcast_ref::<PyType>() {
+            Some(_) => func.call((), vm)?,
+            None => {
+                let instance = Info::default().into_ref(&vm.ctx);
+                func.call((instance.clone(),), vm)?;
+                instance.into()
+            }
+        };
+        let message = "Project class must derive from 'Info'";
+        let instance = py::to::<Info>(vm, &instance, message)?;
+        py::provide(vm, Registry(Some(instance)))
+    }
+}
+
+impl py::Registerable for Init {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct Get {}
+
+impl Function for Get {
+    const NAME: &'static str = consts::FUNCTION_GET;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::get,
+        PyMethodFlags::empty(),
+        Some(
+            "get()\n--\n\nReturn the project 'Info' declared by 'init', or the one with the workspace directory name if 'init' is not used.",
+        ),
+    );
+}
+
+impl Get {
+    fn get(vm: &VirtualMachine) -> PyResult<PyRef<Info>> {
+        if let Some(instance) = py::service::<Registry>(vm)?.and_then(|r| r.0.clone()) {
+            return Ok(instance);
+        }
+        let instance = Info::default().into_ref(&vm.ctx);
+        instance.set_core(info(vm)?);
+        Ok(instance)
+    }
+}
+
+impl py::Registerable for Get {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
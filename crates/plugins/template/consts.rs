@@ -0,0 +1,10 @@
+pub const FUNCTION_RENDER: &str = "render";
+pub const FUNCTION_RENDER_FILE: &str = "render_file";
+
+/// Suffixes stripped from the template name when the output is omitted.
+pub const SUFFIXES: [&str; 3] = [".in", ".j2", ".jinja"];
+
+pub const PLUGIN_NAME: &str = "Template";
+pub const PLUGIN_VERSION: &str = "0.1.0";
+pub const PLUGIN_DESCRIPTION: &str =
+    "Renders Jinja-like templates with the project, config and option values";
@@ -0,0 +1,1021 @@
+use rustpython_vm::builtins::{PyBaseExceptionRef, PyDict};
+use rustpython_vm::{AsObject, PyObjectRef, PyResult, VirtualMachine};
+
+//////////////////////////////////////////////////////////////////
+// Error
+//////////////////////////////////////////////////////////////////
+
+/// Mistake in the template text, e.g. the unclosed tag.
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Error {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Scope
+//////////////////////////////////////////////////////////////////
+
+/// Variables the template sees, the loop variables shadow them.
+pub trait Scope {
+    /// Value of the variable, 'None' if it is undefined.
+    fn get(&self, name: &str, vm: &VirtualMachine) -> PyResult<Option<PyObjectRef>>;
+}
+
+//////////////////////////////////////////////////////////////////
+// Lexer
+//////////////////////////////////////////////////////////////////
+
+enum Token {
+    Text(String),
+    Print(String, usize),
+    Statement(String, usize),
+}
+
+fn line_of(text: &str, position: usize) -> usize {
+    text[..position].matches('\n').count() + 1
+}
+
+/// Splits the text into the plain text, '{{ expression }}' and
+/// '{% statement %}', the '{# comments #}' are dropped. The '-' next to
+/// the delimiter strips the whitespace on its side, e.g. '{%- if x -%}'.
+fn lex(text: &str) -> Result<Vec<Token>, Error> {
+    let mut result = Vec::new();
+    let mut position = 0;
+    let mut strip = false;
+    let push = |result: &mut Vec<Token>, value: &str, strip: bool| {
+        let value = match strip {
+            true => value.trim_start(),
+            false => value,
+        };
+        if !value.is_empty() {
+            result.push(Token::Text(value.to_string()));
+        }
+    };
+    while let Some(offset) = ["{{", "{%", "{#"]
+        .iter()
+        .filter_map(|o| text[position..].find(o))
+        .min()
+    {
+        let start = position + offset;
+        let line = line_of(text, start);
+        let close = match &text[start..start + 2] {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let Some(length) = text[start + 2..].find(close) else {
+            return Err(Error::new(line, format!("Missing '{}'", close)));
+        };
+        let mut inner = &text[start + 2..start + 2 + length];
+        let mut before = &text[position..start];
+        if let Some(rest) = inner.strip_prefix('-') {
+            before = before.trim_end();
+            inner = rest;
+        }
+        push(&mut result, before, strip);
+        strip = false;
+        if let Some(rest) = inner.strip_suffix('-') {
+            strip = true;
+            inner = rest;
+        }
+        let inner = inner.trim().to_string();
+        match close {
+            "}}" => result.push(Token::Print(inner, line)),
+            "%}" => result.push(Token::Statement(inner, line)),
+            _ => {}
+        }
+        position = start + 2 + length + 2;
+    }
+    push(&mut result, &text[position..], strip);
+    Ok(result)
+}
+
+//////////////////////////////////////////////////////////////////
+// Expression
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lexeme {
+    Name(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 9] = ["==", "!=", "|", "(", ")", ",", ".", "[", "]"];
+
+fn words(text: &str, line: usize) -> Result<Vec<Lexeme>, Error> {
+    let mut result = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(Error::new(line, "Unterminated string")),
+                    Some(v) if *v == c => break,
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(v) => value.push(*v),
+                            None => return Err(Error::new(line, "Unterminated string")),
+                        }
+                        i += 1;
+                    }
+                    Some(v) => value.push(*v),
+                }
+                i += 1;
+            }
+            i += 1;
+            result.push(Lexeme::Str(value));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                // The dot is the attribute access unless a digit follows.
+                if chars[i] == '.' && !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    break;
+                }
+                i += 1;
+            }
+            let value: String = chars[start..i].iter().collect();
+            let invalid = || Error::new(line, format!("Invalid number '{}'", value));
+            match value.contains('.') {
+                true => result.push(Lexeme::Float(value.parse().map_err(|_| invalid())?)),
+                false => result.push(Lexeme::Int(value.parse().map_err(|_| invalid())?)),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            result.push(Lexeme::Name(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) else {
+                return Err(Error::new(line, format!("Unexpected character '{}'", c)));
+            };
+            result.push(Lexeme::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Name(String),
+    Attribute(Box<Expr>, String),
+    Item(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Filter(Box<Expr>, String, Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Equal(Box<Expr>, Box<Expr>, bool),
+}
+
+impl Expr {
+    /// Text of the expression for the error messages.
+    fn describe(&self) -> String {
+        match self {
+            Expr::Name(v) => v.clone(),
+            Expr::Attribute(v, name) => format!("{}.{}", v.describe(), name),
+            Expr::Item(v, _) => format!("{}[...]", v.describe()),
+            Expr::Call(v, _) => format!("{}(...)", v.describe()),
+            Expr::Filter(v, name, _) => format!("{} | {}", v.describe(), name),
+            _ => String::from("expression"),
+        }
+    }
+}
+
+/// Recursive descent parser, the precedence from low to high: 'or',
+/// 'and', 'not', comparison, filter, attribute and call.
+struct Parser {
+    words: Vec<Lexeme>,
+    position: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn parse(text: &str, line: usize) -> Result<Expr, Error> {
+        let mut parser = Self {
+            words: words(text, line)?,
+            position: 0,
+            line,
+        };
+        if parser.words.is_empty() {
+            return Err(Error::new(line, "Missing expression"));
+        }
+        let result = parser.or()?;
+        match parser.peek() {
+            None => Ok(result),
+            Some(v) => Err(parser.unexpected(v)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Lexeme> {
+        self.words.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let result = self.words.get(self.position).cloned();
+        self.position += 1;
+        result
+    }
+
+    fn unexpected(&self, word: &Lexeme) -> Error {
+        let text = match word {
+            Lexeme::Name(v) => v.clone(),
+            Lexeme::Str(v) => format!("{:?}", v),
+            Lexeme::Int(v) => v.to_string(),
+            Lexeme::Float(v) => v.to_string(),
+            Lexeme::Symbol(v) => v.to_string(),
+        };
+        Error::new(self.line, format!("Unexpected '{}'", text))
+    }
+
+    fn accept(&mut self, word: &Lexeme) -> bool {
+        match self.peek() == Some(word) {
+            true => {
+                self.position += 1;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), Error> {
+        match self.accept(&Lexeme::Symbol(symbol)) {
+            true => Ok(()),
+            false => Err(Error::new(self.line, format!("Missing '{}'", symbol))),
+        }
+    }
+
+    fn keyword(&mut self, name: &str) -> bool {
+        self.accept(&Lexeme::Name(name.to_string()))
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut result = self.and()?;
+        while self.keyword("or") {
+            result = Expr::Or(Box::new(result), Box::new(self.and()?));
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut result = self.not()?;
+        while self.keyword("and") {
+            result = Expr::And(Box::new(result), Box::new(self.not()?));
+        }
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        match self.keyword("not") {
+            true => Ok(Expr::Not(Box::new(self.not()?))),
+            false => self.compare(),
+        }
+    }
+
+    fn compare(&mut self) -> Result<Expr, Error> {
+        let left = self.filter()?;
+        for (symbol, equal) in [("==", true), ("!=", false)] {
+            if self.accept(&Lexeme::Symbol(symbol)) {
+                let right = self.filter()?;
+                return Ok(Expr::Equal(Box::new(left), Box::new(right), equal));
+            }
+        }
+        Ok(left)
+    }
+
+    fn filter(&mut self) -> Result<Expr, Error> {
+        let mut result = self.postfix()?;
+        while self.accept(&Lexeme::Symbol("|")) {
+            let Some(Lexeme::Name(name)) = self.next() else {
+                return Err(Error::new(self.line, "Missing filter name after '|'"));
+            };
+            let arguments = match self.accept(&Lexeme::Symbol("(")) {
+                true => self.arguments()?,
+                false => Vec::new(),
+            };
+            result = Expr::Filter(Box::new(result), name, arguments);
+        }
+        Ok(result)
+    }
+
+    /// Arguments till the closing parenthesis, the opening one is taken.
+    fn arguments(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut result = Vec::new();
+        if self.accept(&Lexeme::Symbol(")")) {
+            return Ok(result);
+        }
+        loop {
+            result.push(self.or()?);
+            if self.accept(&Lexeme::Symbol(")")) {
+                return Ok(result);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Error> {
+        let mut result = self.primary()?;
+        loop {
+            if self.accept(&Lexeme::Symbol(".")) {
+                result = match self.next() {
+                    Some(Lexeme::Name(v)) => Expr::Attribute(Box::new(result), v),
+                    Some(Lexeme::Int(v)) => Expr::Item(Box::new(result), Box::new(Expr::Int(v))),
+                    _ => return Err(Error::new(self.line, "Missing attribute name after '.'")),
+                };
+            } else if self.accept(&Lexeme::Symbol("[")) {
+                let key = self.or()?;
+                self.expect("]")?;
+                result = Expr::Item(Box::new(result), Box::new(key));
+            } else if self.accept(&Lexeme::Symbol("(")) {
+                result = Expr::Call(Box::new(result), self.arguments()?);
+            } else {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        match self.next() {
+            Some(Lexeme::Str(v)) => Ok(Expr::Str(v)),
+            Some(Lexeme::Int(v)) => Ok(Expr::Int(v)),
+            Some(Lexeme::Float(v)) => Ok(Expr::Float(v)),
+            Some(Lexeme::Symbol("(")) => {
+                let result = self.or()?;
+                self.expect(")")?;
+                Ok(result)
+            }
+            Some(Lexeme::Name(v)) => Ok(match v.as_str() {
+                "true" | "True" => Expr::Bool(true),
+                "false" | "False" => Expr::Bool(false),
+                "none" | "None" => Expr::None,
+                _ => Expr::Name(v),
+            }),
+            Some(v) => Err(self.unexpected(&v)),
+            None => Err(Error::new(self.line, "Unexpected end of expression")),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Template
+//////////////////////////////////////////////////////////////////
+
+enum Node {
+    Text(String),
+    Print(Expr, usize),
+    If(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+    For {
+        names: Vec<String>,
+        items: Expr,
+        body: Vec<Node>,
+        empty: Vec<Node>,
+        line: usize,
+    },
+}
+
+/// Statement which ends the block, e.g. 'else' or 'endfor'.
+struct End {
+    keyword: String,
+    rest: String,
+    line: usize,
+}
+
+/// Parsed template, which is rendered with different variables.
+pub struct Template {
+    name: String,
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// The name is shown in the errors, usually the file path.
+    pub fn parse(text: &str, name: &str) -> Result<Self, Error> {
+        let mut tokens = lex(text)?.into_iter();
+        let (nodes, end) = block(&mut tokens, &[])?;
+        if let Some(end) = end {
+            return Err(Error::new(
+                end.line,
+                format!("Unexpected '{{% {} %}}'", end.keyword),
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            nodes,
+        })
+    }
+
+    pub fn render(&self, scope: &dyn Scope, vm: &VirtualMachine) -> PyResult<String> {
+        let mut renderer = Renderer {
+            scope,
+            locals: Vec::new(),
+            name: &self.name,
+            vm,
+        };
+        let mut result = String::new();
+        renderer.nodes(&self.nodes, &mut result)?;
+        Ok(result)
+    }
+}
+
+fn block(
+    tokens: &mut std::vec::IntoIter<Token>,
+    ends: &[&str],
+) -> Result<(Vec<Node>, Option<End>), Error> {
+    let mut result = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (text, line) = match token {
+            Token::Text(v) => {
+                result.push(Node::Text(v));
+                continue;
+            }
+            Token::Print(v, line) => {
+                result.push(Node::Print(Parser::parse(&v, line)?, line));
+                continue;
+            }
+            Token::Statement(v, line) => (v, line),
+        };
+        let (keyword, rest) = match text.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (keyword.to_string(), rest.trim().to_string()),
+            None => (text.clone(), String::new()),
+        };
+        match keyword.as_str() {
+            "if" => result.push(condition(tokens, &rest, line)?),
+            "for" => result.push(each(tokens, &rest, line)?),
+            v if ends.contains(&v) || v.starts_with("end") || v == "else" || v == "elif" => {
+                let end = End {
+                    keyword,
+                    rest,
+                    line,
+                };
+                return Ok((result, Some(end)));
+            }
+            _ => {
+                return Err(Error::new(
+                    line,
+                    format!("Unknown statement '{{% {} %}}'", keyword),
+                ));
+            }
+        }
+    }
+    Ok((result, None))
+}
+
+/// The block which must be ended by one of the statements.
+fn expect(
+    tokens: &mut std::vec::IntoIter<Token>,
+    ends: &[&str],
+    opener: &str,
+    line: usize,
+) -> Result<(Vec<Node>, End), Error> {
+    let (nodes, end) = block(tokens, ends)?;
+    match end {
+        Some(end) if ends.contains(&end.keyword.as_str()) => Ok((nodes, end)),
+        Some(end) => Err(Error::new(
+            end.line,
+            format!("Unexpected '{{% {} %}}'", end.keyword),
+        )),
+        None => Err(Error::new(
+            line,
+            format!(
+                "Missing '{{% {} %}}' of '{{% {} %}}'",
+                ends[ends.len() - 1],
+                opener
+            ),
+        )),
+    }
+}
+
+fn condition(
+    tokens: &mut std::vec::IntoIter<Token>,
+    rest: &str,
+    line: usize,
+) -> Result<Node, Error> {
+    let mut branches = Vec::new();
+    let mut test = Parser::parse(rest, line)?;
+    loop {
+        let (body, end) = expect(tokens, &["elif", "else", "endif"], "if", line)?;
+        branches.push((test, body));
+        match end.keyword.as_str() {
+            "elif" => test = Parser::parse(&end.rest, end.line)?,
+            "else" => {
+                let (otherwise, _) = expect(tokens, &["endif"], "if", line)?;
+                return Ok(Node::If(branches, otherwise));
+            }
+            _ => return Ok(Node::If(branches, Vec::new())),
+        }
+    }
+}
+
+fn each(tokens: &mut std::vec::IntoIter<Token>, rest: &str, line: usize) -> Result<Node, Error> {
+    let Some((names, items)) = rest.split_once(" in ") else {
+        return Err(Error::new(line, "Expected '{% for name in items %}'"));
+    };
+    let names: Vec<String> = names.split(',').map(|n| n.trim().to_string()).collect();
+    if let Some(name) = names.iter().find(|n| {
+        n.is_empty()
+            || n.starts_with(|c: char| c.is_ascii_digit())
+            || !n.chars().all(|c| c.is_alphanumeric() || c == '_')
+    }) {
+        return Err(Error::new(
+            line,
+            format!("Invalid loop variable '{}'", name),
+        ));
+    }
+    let items = Parser::parse(items, line)?;
+    let (body, end) = expect(tokens, &["else", "endfor"], "for", line)?;
+    let empty = match end.keyword.as_str() {
+        "else" => expect(tokens, &["endfor"], "for", line)?.0,
+        _ => Vec::new(),
+    };
+    Ok(Node::For {
+        names,
+        items,
+        body,
+        empty,
+        line,
+    })
+}
+
+//////////////////////////////////////////////////////////////////
+// Renderer
+//////////////////////////////////////////////////////////////////
+
+struct Renderer<'a> {
+    scope: &'a dyn Scope,
+    locals: Vec<(String, PyObjectRef)>,
+    name: &'a str,
+    vm: &'a VirtualMachine,
+}
+
+impl Renderer<'_> {
+    /// Error of the template as 'ValueError' with the place.
+    fn error(&self, line: usize, message: impl AsRef<str>) -> PyBaseExceptionRef {
+        self.vm
+            .new_value_error(format!("{}:{}: {}", self.name, line, message.as_ref()))
+    }
+
+    fn nodes(&mut self, nodes: &[Node], output: &mut String) -> PyResult<()> {
+        for node in nodes {
+            match node {
+                Node::Text(v) => output.push_str(v),
+                Node::Print(expr, line) => {
+                    let Some(value) = self.eval(expr, *line)? else {
+                        return Err(self.error(*line, undefined(expr)));
+                    };
+                    output.push_str(value.str(self.vm)?.as_str());
+                }
+                Node::If(branches, otherwise) => {
+                    let mut chosen = otherwise;
+                    for (test, body) in branches {
+                        if self.truth(test, 0)? {
+                            chosen = body;
+                            break;
+                        }
+                    }
+                    self.nodes(chosen, output)?;
+                }
+                Node::For {
+                    names,
+                    items,
+                    body,
+                    empty,
+                    line,
+                } => self.each(names, items, body, empty, *line, output)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn each(
+        &mut self,
+        names: &[String],
+        items: &Expr,
+        body: &[Node],
+        empty: &[Node],
+        line: usize,
+        output: &mut String,
+    ) -> PyResult<()> {
+        let vm = self.vm;
+        let Some(value) = self.eval(items, line)? else {
+            return Err(self.error(line, undefined(items)));
+        };
+        let items: Vec<PyObjectRef> = value.try_into_value(vm)?;
+        if items.is_empty() {
+            return self.nodes(empty, output);
+        }
+        let depth = self.locals.len();
+        for (index, item) in items.iter().enumerate() {
+            let values: Vec<PyObjectRef> = match names.len() {
+                1 => vec![item.clone()],
+                n => {
+                    let values: Vec<PyObjectRef> = item.clone().try_into_value(vm)?;
+                    if values.len() != n {
+                        let message =
+                            format!("Expected {} values to unpack, got {}", n, values.len());
+                        return Err(self.error(line, message));
+                    }
+                    values
+                }
+            };
+            let state = PyDict::new_ref(&vm.ctx);
+            state.set_item("index", vm.new_pyobj(index + 1), vm)?;
+            state.set_item("index0", vm.new_pyobj(index), vm)?;
+            state.set_item("first", vm.new_pyobj(index == 0), vm)?;
+            state.set_item("last", vm.new_pyobj(index + 1 == items.len()), vm)?;
+            state.set_item("length", vm.new_pyobj(items.len()), vm)?;
+            self.locals.truncate(depth);
+            self.locals.push((String::from("loop"), state.into()));
+            for (name, value) in names.iter().zip(values) {
+                self.locals.push((name.clone(), value));
+            }
+            let result = self.nodes(body, output);
+            if result.is_err() {
+                self.locals.truncate(depth);
+                return result;
+            }
+        }
+        self.locals.truncate(depth);
+        Ok(())
+    }
+
+    /// Undefined values are false.
+    fn truth(&mut self, expr: &Expr, line: usize) -> PyResult<bool> {
+        match self.eval(expr, line)? {
+            Some(v) => v.try_to_bool(self.vm),
+            None => Ok(false),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, line: usize) -> PyResult<Option<PyObjectRef>> {
+        let vm = self.vm;
+        let value = match expr {
+            Expr::None => vm.ctx.none(),
+            Expr::Bool(v) => vm.new_pyobj(*v),
+            Expr::Int(v) => vm.new_pyobj(*v),
+            Expr::Float(v) => vm.new_pyobj(*v),
+            Expr::Str(v) => vm.new_pyobj(v.clone()),
+            Expr::Name(name) => {
+                if let Some((_, v)) = self.locals.iter().rev().find(|(n, _)| n == name) {
+                    return Ok(Some(v.clone()));
+                }
+                return self.scope.get(name, vm);
+            }
+            Expr::Attribute(object, name) => {
+                let Some(object) = self.eval(object, line)? else {
+                    return Ok(None);
+                };
+                return attribute(&object, name, vm);
+            }
+            Expr::Item(object, key) => {
+                let Some(object) = self.eval(object, line)? else {
+                    return Ok(None);
+                };
+                let Some(key) = self.eval(key, line)? else {
+                    return Ok(None);
+                };
+                return item(&object, key, vm);
+            }
+            Expr::Call(function, arguments) => {
+                let Some(function) = self.eval(function, line)? else {
+                    return Err(self.error(line, undefined(function)));
+                };
+                let mut values = Vec::new();
+                for argument in arguments {
+                    match self.eval(argument, line)? {
+                        Some(v) => values.push(v),
+                        None => return Err(self.error(line, undefined(argument))),
+                    }
+                }
+                function.call(values, vm)?
+            }
+            Expr::Filter(object, name, arguments) => {
+                let object = self.eval(object, line)?;
+                let mut values = Vec::new();
+                for argument in arguments {
+                    match self.eval(argument, line)? {
+                        Some(v) => values.push(v),
+                        None => return Err(self.error(line, undefined(argument))),
+                    }
+                }
+                return self.filter(expr, name, object, values, line).map(Some);
+            }
+            Expr::Not(v) => vm.new_pyobj(!self.truth(v, line)?),
+            Expr::And(left, right) => {
+                vm.new_pyobj(self.truth(left, line)? && self.truth(right, line)?)
+            }
+            Expr::Or(left, right) => {
+                vm.new_pyobj(self.truth(left, line)? || self.truth(right, line)?)
+            }
+            Expr::Equal(left, right, equal) => {
+                let left = self.eval(left, line)?.unwrap_or_else(|| vm.ctx.none());
+                let right = self.eval(right, line)?.unwrap_or_else(|| vm.ctx.none());
+                vm.new_pyobj(vm.bool_eq(&left, &right)? == *equal)
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn filter(
+        &self,
+        expr: &Expr,
+        name: &str,
+        value: Option<PyObjectRef>,
+        arguments: Vec<PyObjectRef>,
+        line: usize,
+    ) -> PyResult {
+        let vm = self.vm;
+        let count = |n: usize| match arguments.len() == n {
+            true => Ok(()),
+            false => Err(self.error(
+                line,
+                format!(
+                    "Filter '{}' takes {} arguments, got {}",
+                    name,
+                    n,
+                    arguments.len()
+                ),
+            )),
+        };
+        if name == "default" {
+            count(1)?;
+            return Ok(match value {
+                Some(v) if !vm.is_none(&v) => v,
+                _ => arguments[0].clone(),
+            });
+        }
+        let Some(value) = value else {
+            let Expr::Filter(object, _, _) = expr else {
+                unreachable!()
+            };
+            return Err(self.error(line, undefined(object)));
+        };
+        let text = || value.str(vm).map(|v| v.as_str().to_string());
+        match name {
+            "upper" => count(0).and_then(|_| Ok(vm.new_pyobj(text()?.to_uppercase()))),
+            "lower" => count(0).and_then(|_| Ok(vm.new_pyobj(text()?.to_lowercase()))),
+            "trim" => count(0).and_then(|_| Ok(vm.new_pyobj(text()?.trim().to_string()))),
+            "capitalize" => count(0).and_then(|_| {
+                let text = text()?.to_lowercase();
+                let mut chars = text.chars();
+                let result: String = match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                };
+                Ok(vm.new_pyobj(result))
+            }),
+            "length" => count(0).and_then(|_| Ok(vm.new_pyobj(value.length(vm)?))),
+            "string" => count(0).and_then(|_| Ok(vm.new_pyobj(text()?))),
+            "replace" => count(2).and_then(|_| {
+                let from = arguments[0].str(vm)?;
+                let to = arguments[1].str(vm)?;
+                Ok(vm.new_pyobj(text()?.replace(from.as_str(), to.as_str())))
+            }),
+            "join" => {
+                if arguments.len() > 1 {
+                    count(1)?;
+                }
+                let separator = match arguments.first() {
+                    Some(v) => v.str(vm)?.as_str().to_string(),
+                    None => String::new(),
+                };
+                let items: Vec<PyObjectRef> = value.try_into_value(vm)?;
+                let items = items
+                    .iter()
+                    .map(|v| v.str(vm).map(|v| v.as_str().to_string()))
+                    .collect::<PyResult<Vec<String>>>()?;
+                Ok(vm.new_pyobj(items.join(&separator)))
+            }
+            "first" | "last" => count(0).and_then(|_| {
+                let items: Vec<PyObjectRef> = value.try_into_value(vm)?;
+                let item = match name {
+                    "first" => items.first(),
+                    _ => items.last(),
+                };
+                Ok(item.cloned().unwrap_or_else(|| vm.ctx.none()))
+            }),
+            _ => Err(self.error(line, format!("Unknown filter '{}'", name))),
+        }
+    }
+}
+
+fn undefined(expr: &Expr) -> String {
+    format!("'{}' is undefined", expr.describe())
+}
+
+/// The attribute of the object, for dictionaries the key goes first.
+fn attribute(
+    object: &PyObjectRef,
+    name: &str,
+    vm: &VirtualMachine,
+) -> PyResult<Option<PyObjectRef>> {
+    let dict = object.downcast_ref::<PyDict>().is_some();
+    if dict && let Some(v) = item(object, vm.new_pyobj(name), vm)? {
+        return Ok(Some(v));
+    }
+    match object.get_attr(&vm.ctx.new_str(name), vm) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if !e.fast_isinstance(vm.ctx.exceptions.attribute_error) => Err(e),
+        Err(_) if dict => Ok(None),
+        Err(_) => item(object, vm.new_pyobj(name), vm),
+    }
+}
+
+/// Missing keys and indexes are undefined, as well as the items of the
+/// objects which have none.
+fn item(
+    object: &PyObjectRef,
+    key: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<Option<PyObjectRef>> {
+    match object.get_item(&*key, vm) {
+        Ok(v) => Ok(Some(v)),
+        Err(e)
+            if e.fast_isinstance(vm.ctx.exceptions.key_error)
+                || e.fast_isinstance(vm.ctx.exceptions.index_error)
+                || e.fast_isinstance(vm.ctx.exceptions.type_error) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_vm::Interpreter;
+
+    struct Variables(Vec<(String, PyObjectRef)>);
+
+    impl Scope for Variables {
+        fn get(&self, name: &str, _vm: &VirtualMachine) -> PyResult<Option<PyObjectRef>> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone()))
+        }
+    }
+
+    /// Renders the text with 'items' = [1, 2, 3], 'empty' = [], 'name' = "umk"
+    /// and 'flag' = True. Errors are returned as their messages.
+    fn render(text: &str) -> Result<String, String> {
+        Interpreter::without_stdlib(Default::default()).enter(|vm| {
+            let template =
+                Template::parse(text, "test").map_err(|e| format!("{}: {}", e.line, e.message))?;
+            let items = vm
+                .ctx
+                .new_list(vec![vm.new_pyobj(1), vm.new_pyobj(2), vm.new_pyobj(3)]);
+            let scope = Variables(vec![
+                (String::from("items"), items.into()),
+                (String::from("empty"), vm.ctx.new_list(Vec::new()).into()),
+                (String::from("name"), vm.new_pyobj("umk")),
+                (String::from("flag"), vm.new_pyobj(true)),
+            ]);
+            template.render(&scope, vm).map_err(|e| {
+                e.as_object()
+                    .str(vm)
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default()
+            })
+        })
+    }
+
+    #[test]
+    fn whitespace_control() {
+        assert_eq!(render("a  {{- name -}}  b").unwrap(), "aumkb");
+        assert_eq!(
+            render("a\n{%- if flag %}\nx\n{%- endif %}\nb").unwrap(),
+            "a\nx\nb"
+        );
+        assert_eq!(render("a {# note #} b").unwrap(), "a  b");
+        assert_eq!(render("a\n{#- note -#}\nb").unwrap(), "ab");
+    }
+
+    #[test]
+    fn conditions() {
+        let text = "{% if name == 'x' %}x{% elif not flag %}f{% elif flag and name != 'y' %}y{% else %}e{% endif %}";
+        assert_eq!(render(text).unwrap(), "y");
+        assert_eq!(
+            render("{% if missing %}m{% else %}e{% endif %}").unwrap(),
+            "e"
+        );
+        assert_eq!(render("{% if missing or flag %}t{% endif %}").unwrap(), "t");
+    }
+
+    #[test]
+    fn loops() {
+        let text = "{% for x in items %}{{ loop.index }}:{{ x }}{% if not loop.last %},{% endif %}{% endfor %}";
+        assert_eq!(render(text).unwrap(), "1:1,2:2,3:3");
+        assert_eq!(
+            render("{% for x in empty %}{{ x }}{% else %}none{% endfor %}").unwrap(),
+            "none"
+        );
+        assert_eq!(
+            render("{% for x in items %}{% endfor %}{{ x | default('-') }}").unwrap(),
+            "-"
+        );
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            render("{{ name | upper }} {{ items | join(', ') }} {{ items | length }}").unwrap(),
+            "UMK 1, 2, 3 3"
+        );
+        assert_eq!(
+            render("{{ missing | default('d') }} {{ items | first }}").unwrap(),
+            "d 1"
+        );
+        assert_eq!(
+            render("{{ name | replace('u', 'U') | capitalize }}").unwrap(),
+            "Umk"
+        );
+        assert_eq!(
+            render("{{ name | upper(1) }}").unwrap_err(),
+            "test:1: Filter 'upper' takes 0 arguments, got 1"
+        );
+        assert_eq!(
+            render("{{ name | default }}").unwrap_err(),
+            "test:1: Filter 'default' takes 1 arguments, got 0"
+        );
+        assert_eq!(
+            render("{{ name | replace('u') }}").unwrap_err(),
+            "test:1: Filter 'replace' takes 2 arguments, got 1"
+        );
+        assert_eq!(
+            render("{{ name | nope }}").unwrap_err(),
+            "test:1: Unknown filter 'nope'"
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(render("a\n{{ name").unwrap_err(), "2: Missing '}}'");
+        assert_eq!(render("a\nb {% if flag").unwrap_err(), "2: Missing '%}'");
+        assert_eq!(render("{# note").unwrap_err(), "1: Missing '#}'");
+        assert_eq!(
+            render("\n\n{{ 'abc }}").unwrap_err(),
+            "3: Unterminated string"
+        );
+        assert_eq!(
+            render("a\n{% if flag %}\nb").unwrap_err(),
+            "2: Missing '{% endif %}' of '{% if %}'"
+        );
+        assert_eq!(
+            render("{% for x in items %}\n{% endif %}").unwrap_err(),
+            "2: Unexpected '{% endif %}'"
+        );
+        assert_eq!(
+            render("\n{% endfor %}").unwrap_err(),
+            "2: Unexpected '{% endfor %}'"
+        );
+        assert_eq!(
+            render("{% while flag %}").unwrap_err(),
+            "1: Unknown statement '{% while %}'"
+        );
+        assert_eq!(
+            render("{{ name name }}").unwrap_err(),
+            "1: Unexpected 'name'"
+        );
+    }
+
+    #[test]
+    fn undefined_values() {
+        assert_eq!(
+            render("a\n\n{{ missing.value }}").unwrap_err(),
+            "test:3: 'missing.value' is undefined"
+        );
+        assert_eq!(
+            render("{% for x in missing %}{% endfor %}").unwrap_err(),
+            "test:1: 'missing' is undefined"
+        );
+    }
+}
@@ -0,0 +1,6 @@
+mod consts;
+mod engine;
+mod plugin;
+mod template;
+
+pub use plugin::Instance;
@@ -0,0 +1,86 @@
+use crate::{consts, template};
+use clap::Command;
+use plugin::Interface as PluginInterface;
+use rustpython::vm::pymodule;
+use rustpython_vm::builtins::PyModule;
+use rustpython_vm::class::PyClassImpl;
+use rustpython_vm::{PyRef, VirtualMachine};
+use sdk::py;
+use sdk::py::{Registerable, Registerer};
+
+#[derive(Default)]
+pub struct Instance {}
+
+impl Registerable for Instance {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        module.register::<template::Render>(vm);
+        module.register::<template::RenderFile>(vm);
+    }
+}
+
+impl PluginInterface for Instance {
+    fn initialize(&self, vm: &VirtualMachine) -> sdk::Result {
+        template::Config::make_class(&vm.ctx);
+        Ok(())
+    }
+
+    fn info(&self) -> plugin::Info {
+        plugin::Info {
+            name: consts::PLUGIN_NAME.to_string(),
+            version: consts::PLUGIN_VERSION.to_string(),
+            description: consts::PLUGIN_DESCRIPTION.to_string(),
+        }
+    }
+
+    fn examples(&self) -> Vec<String> {
+        vec![String::from(
+            r#"from umk import cli, config, project, template
+
+prefix = config.setting(str, "prefix", default="/usr/local")
+
+
+@project.init
+def _(p: project.Info):
+    p.name = "app"
+    p.version = "1.4.2"
+
+
+@cli.cmd("configure")
+@cli.opt(bool, "debug", default=False)
+def configure(debug: bool):
+    # config.h.in:
+    #   #define PROJECT "{{ project.name }}"
+    #   #define VERSION "{{ project.version }}"
+    #   #define PREFIX "{{ config.prefix }}"
+    #   {%- if options.debug %}
+    #   #define DEBUG 1
+    #   {%- endif %}
+    # Unchanged output is not written, so the targets using it stay up to date
+    template.render_file("src/config.h.in")
+    template.render_file("packaging/app.service.j2", "build/app.service", user="app")
+"#,
+        )]
+    }
+
+    fn cli(&self, _cache: &sdk::Cache) -> sdk::Result<Option<Command>> {
+        Ok(None)
+    }
+
+    fn cache(&self, _cache: &sdk::Cache, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn call(&self, _matches: &clap::ArgMatches, _vm: &VirtualMachine) -> sdk::Result {
+        Ok(())
+    }
+
+    fn register(&self, root: &PyRef<PyModule>, vm: &VirtualMachine) {
+        let module = _module::make_module(vm);
+        module.register::<template::Render>(vm);
+        module.register::<template::RenderFile>(vm);
+        py::register::submodule(vm, root, module);
+    }
+}
+
+#[pymodule(name = "template")]
+mod _module {}
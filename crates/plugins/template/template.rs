@@ -0,0 +1,280 @@
+use crate::consts;
+use crate::engine::{self, Scope, Template};
+use rustpython_vm::builtins::{PyBaseExceptionRef, PyDict, PyModule, PyStr, PyStrRef};
+use rustpython_vm::function::{FsPath, FuncArgs, PyMethodDef, PyMethodFlags};
+use rustpython_vm::types::GetAttr;
+use rustpython_vm::{
+    AsObject, Py, PyObjectRef, PyPayload, PyRef, PyResult, TryFromObject, VirtualMachine, pyclass,
+};
+use sdk::py;
+use sdk::py::Function;
+use std::path::{Path, PathBuf};
+
+//////////////////////////////////////////////////////////////////
+// Context
+//////////////////////////////////////////////////////////////////
+
+/// The 'config' variable: 'config.jobs' is 'umk.config.get("jobs")', so
+/// only the settings the template uses must be declared.
+#[pyclass(module = false, name = "TemplateConfig")]
+#[derive(Debug, PyPayload)]
+pub struct Config {}
+
+#[pyclass(with(GetAttr))]
+impl Config {}
+
+impl GetAttr for Config {
+    fn getattro(_zelf: &Py<Self>, name: &Py<PyStr>, vm: &VirtualMachine) -> PyResult {
+        let missing = || vm.new_attribute_error(format!("Setting is not declared: '{}'", name));
+        if name.as_str().starts_with("__") {
+            return Err(missing());
+        }
+        let get = vm
+            .import(global::kit::NAME, 0)?
+            .get_attr("config", vm)?
+            .get_attr("get", vm)?;
+        get.call((name.to_owned(),), vm).map_err(|e| {
+            match e.fast_isinstance(vm.ctx.exceptions.key_error) {
+                true => missing(),
+                false => e,
+            }
+        })
+    }
+}
+
+/// Variables passed to the function go first, then 'project' (values of
+/// 'umk.project' and the workspace root), 'options' (of the running CLI
+/// command) and 'config' (settings).
+struct Context {
+    values: Variables,
+    layout: sdk::Layout,
+}
+
+impl Scope for Context {
+    fn get(&self, name: &str, vm: &VirtualMachine) -> PyResult<Option<PyObjectRef>> {
+        if let Some((_, v)) = self.values.iter().find(|(n, _)| n == name) {
+            return Ok(Some(v.clone()));
+        }
+        let result = match name {
+            "project" => {
+                let root = self.layout.root();
+                let info = match py::service::<sdk::ProjectInfo>(vm)? {
+                    Some(v) => v.as_ref().clone(),
+                    None => sdk::ProjectInfo {
+                        name: root
+                            .file_name()
+                            .map(|v| v.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        ..Default::default()
+                    },
+                };
+                let project = PyDict::new_ref(&vm.ctx);
+                project.set_item("name", vm.new_pyobj(info.name), vm)?;
+                project.set_item("version", vm.new_pyobj(info.version), vm)?;
+                project.set_item("title", vm.new_pyobj(info.title), vm)?;
+                project.set_item("description", vm.new_pyobj(info.description), vm)?;
+                let root = root.to_string_lossy().into_owned();
+                project.set_item("root", vm.new_pyobj(root), vm)?;
+                project.into()
+            }
+            "options" => {
+                let options = PyDict::new_ref(&vm.ctx);
+                if let Some(invocation) = py::service::<py::Invocation>(vm)? {
+                    for (name, value) in invocation.options.iter() {
+                        options.set_item(name.as_str(), value.clone(), vm)?;
+                    }
+                }
+                options.into()
+            }
+            "config" => Config {}.into_ref(&vm.ctx).into(),
+            _ => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Helpers
+//////////////////////////////////////////////////////////////////
+
+fn invalid(error: engine::Error, name: &str, vm: &VirtualMachine) -> PyBaseExceptionRef {
+    vm.new_value_error(format!("{}:{}: {}", name, error.line, error.message))
+}
+
+/// Variables passed to the function as the keyword arguments.
+type Variables = Vec<(String, PyObjectRef)>;
+
+/// Positional arguments of the function, the keyword ones are variables.
+fn split(
+    function: &str,
+    inputs: FuncArgs,
+    required: usize,
+    optional: usize,
+    vm: &VirtualMachine,
+) -> PyResult<(Vec<PyObjectRef>, Variables)> {
+    let count = inputs.args.len();
+    if count < required || count > required + optional {
+        let expected = match optional {
+            0 => required.to_string(),
+            n => format!("from {} to {}", required, required + n),
+        };
+        return Err(vm.new_type_error(format!(
+            "{}() takes {} positional arguments but {} were given",
+            function, expected, count
+        )));
+    }
+    Ok((inputs.args, inputs.kwargs.into_iter().collect()))
+}
+
+fn render(text: &str, name: &str, values: Variables, vm: &VirtualMachine) -> PyResult<String> {
+    let template = Template::parse(text, name).map_err(|e| invalid(e, name, vm))?;
+    let context = Context {
+        values,
+        layout: sdk::Layout::current(vm)?,
+    };
+    template.render(&context, vm)
+}
+
+/// The template path without its suffix, e.g. 'config.h' for 'config.h.in'.
+fn output(src: &Path) -> Option<PathBuf> {
+    let name = src.file_name()?.to_str()?;
+    let stem = consts::SUFFIXES
+        .iter()
+        .find_map(|s| name.strip_suffix(s))
+        .filter(|s| !s.is_empty())?;
+    Some(src.with_file_name(stem))
+}
+
+//////////////////////////////////////////////////////////////////
+// Functions
+//////////////////////////////////////////////////////////////////
+
+pub struct Render {}
+
+impl Function for Render {
+    const NAME: &'static str = consts::FUNCTION_RENDER;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::render,
+        PyMethodFlags::empty(),
+        Some(
+            "render(text, /, **variables)\n--\n\nRender the template text and return the result. Besides the variables, the template sees 'project' (name, version, title and description declared by 'umk.project.init', and the workspace root), 'options' (of the running CLI command) and 'config' (declared settings).",
+        ),
+    );
+}
+
+impl Render {
+    fn render(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<String> {
+        let (args, values) = split(Self::NAME, inputs, 1, 0, vm)?;
+        let text = PyStrRef::try_from_object(vm, args[0].clone())?;
+        render(text.as_str(), "<template>", values, vm)
+    }
+}
+
+impl py::Registerable for Render {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+pub struct RenderFile {}
+
+impl Function for RenderFile {
+    const NAME: &'static str = consts::FUNCTION_RENDER_FILE;
+    const METHOD: PyMethodDef = PyMethodDef::new_const(
+        Self::NAME,
+        Self::render_file,
+        PyMethodFlags::empty(),
+        Some(
+            "render_file(src, dst=None, /, **variables)\n--\n\nRender the template file into 'dst', by default the template path without the '.in', '.j2' or '.jinja' suffix. The output is written only if its content changes, so its modification time stays for the up-to-date checks. Return True if the file is written.",
+        ),
+    );
+}
+
+impl RenderFile {
+    fn render_file(inputs: FuncArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        let (args, values) = split(Self::NAME, inputs, 1, 1, vm)?;
+        let layout = sdk::Layout::current(vm)?;
+        let src = FsPath::try_from_object(vm, args[0].clone())?.to_path_buf(vm)?;
+        let src = layout.resolve(src);
+        let dst = match args.get(1).filter(|v| !vm.is_none(v)) {
+            Some(v) => layout.resolve(FsPath::try_from_object(vm, v.clone())?.to_path_buf(vm)?),
+            None => output(&src).ok_or_else(|| {
+                vm.new_value_error(format!(
+                    "Output of the template '{}' is not known, pass 'dst' or use one of the suffixes: {}",
+                    layout.relative(&src).display(),
+                    consts::SUFFIXES.join(", ")
+                ))
+            })?,
+        };
+        let text = std::fs::read_to_string(&src).map_err(|e| py::os_error(vm, e, &src))?;
+        let name = layout.relative(&src).to_string_lossy().into_owned();
+        let result = render(&text, &name, values, vm)?;
+        if std::fs::read(&dst).is_ok_and(|v| v == result.as_bytes()) {
+            return Ok(false);
+        }
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| py::os_error(vm, e, parent))?;
+        }
+        sdk::write_atomic(&dst, result.as_bytes())
+            .map_err(|e| vm.new_os_error(format!("Failed to write '{}': {}", dst.display(), e)))?;
+        eprintln!("Rendered '{}'", layout.relative(&dst).display());
+        Ok(true)
+    }
+}
+
+impl py::Registerable for RenderFile {
+    fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+        py::register::function::<Self>(vm, module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_vm::Interpreter;
+    use rustpython_vm::class::PyClassImpl;
+    use rustpython_vm::convert::ToPyObject;
+    use rustpython_vm::function::KwArgs;
+
+    #[test]
+    fn unchanged_output_is_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::write(root.join("version.h.in"), "#define NAME \"{{ name }}\"\n").unwrap();
+        let output = root.join("version.h");
+        Interpreter::without_stdlib(Default::default()).enter(|vm| {
+            py::Container::make_class(&vm.ctx);
+            vm.builtins
+                .set_attr(
+                    global::kit::CONTAINER,
+                    py::Container::default().to_pyobject(vm),
+                    vm,
+                )
+                .unwrap();
+            py::provide(vm, sdk::Layout::new(&root)).unwrap();
+            let call = |name: &str| {
+                let args = FuncArgs::new(
+                    vec![vm.new_pyobj("version.h.in")],
+                    KwArgs::from_iter([(String::from("name"), vm.new_pyobj(name))]),
+                );
+                RenderFile::render_file(args, vm).map_err(|e| {
+                    vm.print_exception(e);
+                })
+            };
+            assert!(call("umk").unwrap());
+            let modified = std::fs::metadata(&output).unwrap().modified().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            assert!(!call("umk").unwrap());
+            assert_eq!(
+                std::fs::metadata(&output).unwrap().modified().unwrap(),
+                modified
+            );
+            assert!(call("other").unwrap());
+        });
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "#define NAME \"other\"\n"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{Error, ErrorKind, Result, py};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//////////////////////////////////////////////////////////////////
+// Blob IO driver trait
+//////////////////////////////////////////////////////////////////
+
+/// Namespace of the stored blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Space {
+    /// File contents, the key is the hash of the content.
+    Objects,
+    /// Target manifests, the key is the hash of the target inputs.
+    Targets,
+}
+
+impl Space {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Space::Objects => "objects",
+            Space::Targets => "targets",
+        }
+    }
+}
+
+/// Storage of the artifact blobs. Blobs under the same key are always
+/// equivalent, so concurrent writers don't need to coordinate.
+pub trait Driver: Send + Sync + 'static {
+    fn get(&self, space: Space, key: &str) -> Result<Option<Vec<u8>>>;
+    fn put(&self, space: Space, key: &str, data: &[u8]) -> Result;
+
+    fn has(&self, space: Space, key: &str) -> Result<bool> {
+        Ok(self.get(space, key)?.is_some())
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Manifest
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Output {
+    /// Path relative to the workspace root.
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+    #[serde(default)]
+    pub executable: bool,
+}
+
+/// Outputs the target produced for the particular key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub target: String,
+    /// Unix time (in seconds) when the outputs were stored.
+    pub time: u64,
+    pub outputs: Vec<Output>,
+}
+
+//////////////////////////////////////////////////////////////////
+// Key
+//////////////////////////////////////////////////////////////////
+
+/// Incremental hash of the target key parts. Each part is prefixed with
+/// its length, so ("ab", "c") and ("a", "bc") give different keys.
+#[derive(Clone, Default)]
+pub struct Key(Sha256);
+
+impl Key {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, part: impl AsRef<[u8]>) -> &mut Self {
+        let part = part.as_ref();
+        self.0.update((part.len() as u64).to_le_bytes());
+        self.0.update(part);
+        self
+    }
+
+    pub fn finish(self) -> String {
+        hex(&self.0.finalize())
+    }
+}
+
+/// Hash of the content, it is the object key.
+pub fn digest(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn is_hash(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+//////////////////////////////////////////////////////////////////
+// Store
+//////////////////////////////////////////////////////////////////
+
+/// Content-addressed store of the target outputs. Paths of the outputs
+/// are relative to the root (the workspace directory).
+pub struct Store {
+    root: PathBuf,
+    driver: Box<dyn Driver>,
+}
+
+impl py::Service for Store {
+    const NAME: &'static str = "unimake/artifacts";
+}
+
+impl Store {
+    pub fn new(root: impl Into<PathBuf>, driver: Box<dyn Driver>) -> Self {
+        Self {
+            root: root.into(),
+            driver,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Files matching the glob patterns (relative to the root), sorted
+    /// and without duplicates. Directories are skipped.
+    pub fn expand(&self, patterns: &[String]) -> Result<Vec<String>> {
+        let mut result = BTreeSet::new();
+        for pattern in patterns {
+            let full = self.root.join(pattern);
+            let paths = glob::glob(&full.to_string_lossy()).map_err(|e| {
+                Error::new(
+                    ErrorKind::Generic,
+                    format!("Invalid file pattern '{}': {}", pattern, e.msg),
+                )
+            })?;
+            for path in paths {
+                let path = path.map_err(|e| Error::from(std::io::Error::from(e)))?;
+                if path.is_file() {
+                    let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+                    result.insert(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        Ok(result.into_iter().collect())
+    }
+
+    /// Content hashes of the files matching the patterns.
+    pub fn hashes(&self, patterns: &[String]) -> Result<BTreeMap<String, String>> {
+        let mut result = BTreeMap::new();
+        for file in self.expand(patterns)? {
+            let data = std::fs::read(self.root.join(&file))?;
+            result.insert(file, digest(&data));
+        }
+        Ok(result)
+    }
+
+    /// Manifest of the outputs stored for the key.
+    pub fn manifest(&self, key: &str) -> Result<Option<Manifest>> {
+        match self.driver.get(Space::Targets, key)? {
+            None => Ok(None),
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+        }
+    }
+
+    /// Writes the stored outputs of the key into the workspace, the files
+    /// with the same content are not touched. Nothing is written and 'None'
+    /// is returned if some object is missing.
+    pub fn restore(&self, key: &str) -> Result<Option<Manifest>> {
+        let Some(manifest) = self.manifest(key)? else {
+            return Ok(None);
+        };
+        let mut files = Vec::new();
+        for output in manifest.outputs.iter() {
+            let path = self.output(&output.path)?;
+            if !is_hash(&output.hash) {
+                return Err(corrupt(key, format!("invalid hash '{}'", output.hash)));
+            }
+            // Unchanged outputs keep their modification time for the
+            // up-to-date checks of the dependents.
+            if std::fs::read(&path).is_ok_and(|v| digest(&v) == output.hash) {
+                set_executable(&path, output.executable)?;
+                continue;
+            }
+            let Some(data) = self.driver.get(Space::Objects, &output.hash)? else {
+                return Ok(None);
+            };
+            if digest(&data) != output.hash {
+                return Err(corrupt(
+                    key,
+                    format!("object of '{}' mismatch", output.path),
+                ));
+            }
+            files.push((path, data, output.executable));
+        }
+        for (path, data, executable) in files {
+            crate::write_atomic(&path, &data)?;
+            set_executable(&path, executable)?;
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Stores the files matching the patterns as outputs of the key.
+    /// Returns 'None' if there are no such files.
+    pub fn save(&self, key: &str, target: &str, patterns: &[String]) -> Result<Option<Manifest>> {
+        let files = self.expand(patterns)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+        let mut outputs = Vec::new();
+        for file in files {
+            let path = self.root.join(&file);
+            let data = std::fs::read(&path)?;
+            let hash = digest(&data);
+            if !self.driver.has(Space::Objects, &hash)? {
+                self.driver.put(Space::Objects, &hash, &data)?;
+            }
+            outputs.push(Output {
+                path: file,
+                hash,
+                size: data.len() as u64,
+                executable: is_executable(&path)?,
+            });
+        }
+        let manifest = Manifest {
+            target: target.to_string(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            outputs,
+        };
+        let data = serde_json::to_vec_pretty(&manifest)?;
+        self.driver.put(Space::Targets, key, &data)?;
+        Ok(Some(manifest))
+    }
+
+    /// Manifests may come from the shared storage, so paths leading
+    /// outside of the workspace are rejected.
+    fn output(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        let inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        match inside {
+            true => Ok(self.root.join(relative)),
+            false => Err(Error::new(
+                ErrorKind::CacheCorrupt,
+                format!("Artifact output is outside of the workspace: '{}'", path),
+            )),
+        }
+    }
+}
+
+fn corrupt(key: &str, reason: String) -> Error {
+    Error::new(
+        ErrorKind::CacheCorrupt,
+        format!("Corrupted artifact '{}': {}", key, reason),
+    )
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(std::fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path, executable: bool) -> Result {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    permissions.set_mode(match executable {
+        true => mode | (mode & 0o444) >> 2,
+        false => mode & !0o111,
+    });
+    Ok(std::fs::set_permissions(path, permissions)?)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_: &Path, _: bool) -> Result {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Memory(Mutex<HashMap<(String, String), Vec<u8>>>);
+
+    impl Driver for Memory {
+        fn get(&self, space: Space, key: &str) -> Result<Option<Vec<u8>>> {
+            let key = (space.name().to_string(), key.to_string());
+            Ok(self.0.lock().get(&key).cloned())
+        }
+
+        fn put(&self, space: Space, key: &str, data: &[u8]) -> Result {
+            let key = (space.name().to_string(), key.to_string());
+            self.0.lock().insert(key, data.to_vec());
+            Ok(())
+        }
+    }
+
+    fn modified(path: &Path) -> SystemTime {
+        std::fs::metadata(path).unwrap().modified().unwrap()
+    }
+
+    #[test]
+    fn restore_keeps_unchanged_outputs() {
+        let root = tempfile::tempdir().unwrap();
+        let store = Store::new(root.path(), Box::new(Memory::default()));
+        let output = root.path().join("out.txt");
+        std::fs::write(&output, "data").unwrap();
+        store
+            .save("key", "build", &["out.txt".into()])
+            .unwrap()
+            .unwrap();
+        let old = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&output)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert!(store.restore("key").unwrap().is_some());
+        assert_eq!(modified(&output), old);
+        std::fs::write(&output, "changed").unwrap();
+        assert!(store.restore("key").unwrap().is_some());
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "data");
+        std::fs::remove_file(&output).unwrap();
+        assert!(store.restore("key").unwrap().is_some());
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "data");
+    }
+}
@@ -1,9 +1,11 @@
-use crate::Result;
-use serde::Serialize;
+use crate::{Error, Result};
+use parking_lot::RwLock;
 use serde::de::DeserializeOwned;
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 //////////////////////////////////////////////////////////////////
 // Json IO driver trait
@@ -15,35 +17,204 @@ pub trait Driver: Send + Sync + 'static {
     fn get(&self, key: &str) -> Result<Option<JsonValue>>;
     fn set(&mut self, key: &str, value: JsonValue) -> Result;
     fn del(&mut self, key: &str) -> Result;
+    fn keys(&self) -> Result<Vec<String>>;
+}
+
+//////////////////////////////////////////////////////////////////
+// Memory driver
+//////////////////////////////////////////////////////////////////
+
+/// Keeps entries in memory only, useful for tests and one-shot runs.
+#[derive(Debug, Default)]
+pub struct Memory {
+    entries: BTreeMap<String, JsonValue>,
+}
+
+impl Driver for Memory {
+    fn get(&self, key: &str) -> Result<Option<JsonValue>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, value: JsonValue) -> Result {
+        self.entries.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn del(&mut self, key: &str) -> Result {
+        self.entries.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.entries.keys().cloned().collect())
+    }
 }
 
 //////////////////////////////////////////////////////////////////
 // Cache entry
 //////////////////////////////////////////////////////////////////
 
+/// Driver shared between entries, it may be used from several threads.
+pub type Shared = Arc<RwLock<dyn Driver>>;
+
+pub fn share<D: Driver>(driver: D) -> Shared {
+    Arc::new(RwLock::new(driver))
+}
+
+/// The workspace cache is published for plugins which keep
+/// their own state between runs.
+impl crate::py::Service for Shared {
+    const NAME: &'static str = "unimake/cache";
+}
+
+/// Versions which the entry was written with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamp {
+    pub unimake: String,
+    pub schema: u32,
+}
+
+impl Stamp {
+    fn current(schema: u32) -> Self {
+        Self {
+            unimake: global::project::VERSION.to_string(),
+            schema,
+        }
+    }
+}
+
+/// Converts the data written with the given stamp to the current schema,
+/// 'None' means the entry can't be migrated and must be invalidated.
+pub type Migration = fn(&Stamp, JsonValue) -> Option<JsonValue>;
+
+/// Stored form of the entry: the data and the versions it was written with.
+#[derive(Debug, Serialize, Deserialize)]
+struct Stamped {
+    #[serde(flatten)]
+    stamp: Stamp,
+    #[serde(default)]
+    time: u64,
+    data: JsonValue,
+}
+
+impl Stamped {
+    fn new(stamp: Stamp, data: JsonValue) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self { stamp, time, data }
+    }
+}
+
+/// Metadata of the stored entry, it can be read without knowing the entry type.
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub stamp: Stamp,
+    /// Unix time (in seconds) when the entry was written.
+    pub time: u64,
+}
+
+impl Header {
+    /// Returns 'None' if the value is not a stamped entry.
+    pub fn read(value: &JsonValue) -> Option<Self> {
+        let stamped = Stamped::deserialize(value).ok()?;
+        Some(Self {
+            stamp: stamped.stamp,
+            time: stamped.time,
+        })
+    }
+
+    /// Written by the running unimake version.
+    pub fn is_current(&self) -> bool {
+        self.stamp.unimake == global::project::VERSION
+    }
+}
+
+/// Typed access to the cache value. Entries written by other unimake
+/// versions or schemas are migrated (if possible) or treated as missing.
+/// Unreadable entries are treated as missing too, with a warning.
 pub struct Entry<T>
 where
     T: DeserializeOwned,
 {
     key: String,
-    driver: Rc<RefCell<dyn Driver>>,
-    _phantom: PhantomData<T>,
+    driver: Shared,
+    schema: u32,
+    migration: Option<Migration>,
+    // Entry doesn't own the 'T', so it doesn't affect Send and Sync.
+    _phantom: PhantomData<fn() -> T>,
 }
 
 impl<T: DeserializeOwned> Entry<T> {
+    pub fn new(key: impl Into<String>, driver: Shared) -> Self {
+        Self {
+            key: key.into(),
+            driver,
+            schema: 1,
+            migration: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Must be increased on every incompatible change of the 'T'.
+    pub fn with_schema(mut self, schema: u32) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    pub fn with_migration(mut self, migration: Migration) -> Self {
+        self.migration = Some(migration);
+        self
+    }
+
     pub fn get(&self) -> Result<Option<T>> {
-        match self.driver.borrow().get(self.key.as_str())? {
-            None => Ok(None),
-            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+        let value = match self.driver.read().get(self.key.as_str()) {
+            Ok(Some(value)) => value,
+            Ok(None) => return Ok(None),
+            Err(err) => return self.miss(err),
+        };
+        let stamped: Stamped = match serde_json::from_value(value) {
+            Ok(value) => value,
+            Err(err) => return self.miss(err.into()),
+        };
+        let current = Stamp::current(self.schema);
+        let data = if stamped.stamp == current {
+            stamped.data
+        } else {
+            let migrated = self
+                .migration
+                .and_then(|migrate| migrate(&stamped.stamp, stamped.data));
+            match migrated {
+                None => return Ok(None),
+                Some(data) => {
+                    self.driver.write().set(
+                        self.key.as_str(),
+                        serde_json::to_value(Stamped::new(current, data.clone()))?,
+                    )?;
+                    data
+                }
+            }
+        };
+        match serde_json::from_value(data) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => self.miss(err.into()),
         }
     }
 
     pub fn set<V: Serialize>(&self, value: V) -> Result {
-        let value = serde_json::to_value(value)?;
-        self.driver.borrow_mut().set(self.key.as_str(), value)
+        let data = serde_json::to_value(value)?;
+        let value = serde_json::to_value(Stamped::new(Stamp::current(self.schema), data))?;
+        self.driver.write().set(self.key.as_str(), value)
+    }
+
+    pub fn del(&self) -> Result {
+        self.driver.write().del(self.key.as_str())
     }
 
-    pub fn del(&self, key: &str) -> Result {
-        self.driver.borrow_mut().del(key)
+    fn miss(&self, err: Error) -> Result<Option<T>> {
+        let message = format!("Ignoring unreadable cache entry '{}': {}", self.key, err);
+        crate::warning(message);
+        Ok(None)
     }
 }
@@ -0,0 +1,71 @@
+use crate::py;
+use rustpython_vm::signal::UserSignalSender;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+//////////////////////////////////////////////////////////////////
+// Interrupter
+//////////////////////////////////////////////////////////////////
+
+/// Raises exceptions in the running python code from other threads. The
+/// exception is raised when the interpreter checks the signals, i.e.
+/// between the instructions or while waiting for the child processes.
+#[derive(Debug, Clone)]
+pub struct Interrupter {
+    sender: UserSignalSender,
+}
+
+impl py::Service for Interrupter {
+    const NAME: &'static str = "unimake/interrupter";
+}
+
+impl Interrupter {
+    pub fn new(sender: UserSignalSender) -> Self {
+        Self { sender }
+    }
+
+    /// Raises the 'TimeoutError' with the message after the duration,
+    /// unless the returned deadline is dropped before. The builtin which
+    /// blocks without checking the signals (e.g. 'time.sleep') is not
+    /// interrupted, the error is raised once it returns.
+    pub fn deadline(&self, after: Duration, message: impl Into<String>) -> Deadline {
+        let active = Arc::new(AtomicBool::new(true));
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let (sender, message) = (self.sender.clone(), message.into());
+        let flag = active.clone();
+        thread::spawn(move || {
+            if cancelled.recv_timeout(after) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return;
+            }
+            let _ = sender.send(Box::new(move |vm| {
+                // The code may finish while the signal is on the way.
+                match flag.load(Ordering::Acquire) {
+                    true => {
+                        Err(vm
+                            .new_exception_msg(vm.ctx.exceptions.timeout_error.to_owned(), message))
+                    }
+                    false => Ok(()),
+                }
+            }));
+        });
+        Deadline {
+            active,
+            _cancel: cancel,
+        }
+    }
+}
+
+/// Pending timeout, it is cancelled on drop.
+pub struct Deadline {
+    active: Arc<AtomicBool>,
+    _cancel: mpsc::Sender<()>,
+}
+
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::py;
+use rustpython_vm::{PyResult, VirtualMachine};
+use std::path::{Component, Path, PathBuf};
+
+//////////////////////////////////////////////////////////////////
+// Layout
+//////////////////////////////////////////////////////////////////
+
+/// Directories of the workspace. Relative paths of the script are
+/// resolved against the root, whatever the current directory is.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    root: PathBuf,
+}
+
+impl py::Service for Layout {
+    const NAME: &'static str = "unimake/layout";
+}
+
+impl Layout {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: normalize(&root.into()),
+        }
+    }
+
+    /// The published layout for the script helpers. Outside of the
+    /// workspace the current directory is the root.
+    pub fn current(vm: &VirtualMachine) -> PyResult<Self> {
+        match py::service::<Self>(vm)? {
+            Some(v) => Ok(v.as_ref().clone()),
+            None => {
+                let cwd =
+                    std::env::current_dir().map_err(|e| py::os_error(vm, e, Path::new(".")))?;
+                Ok(Self::new(cwd))
+            }
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute path without '.' and '..', relative paths are taken
+    /// from the root.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        normalize(&self.root.join(path))
+    }
+
+    /// Path relative to the root if it is inside, the absolute one otherwise.
+    pub fn relative(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = self.resolve(path);
+        match path.strip_prefix(&self.root) {
+            Ok(v) if !v.as_os_str().is_empty() => v.to_path_buf(),
+            _ => path,
+        }
+    }
+
+    /// Whether the path is inside of the root, the root itself is not.
+    /// Symbolic links of the parent directories are followed, the link
+    /// itself is not (removing the link keeps its target).
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        let path = self.resolve(path);
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let parent = real(parent);
+        let root = real(&self.root);
+        parent.join(name).starts_with(&root) && parent.join(name) != root
+    }
+}
+
+/// Canonical form of the deepest existing ancestor, followed by the rest.
+fn real(path: &Path) -> PathBuf {
+    if let Ok(v) = path.canonicalize() {
+        return v;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => real(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                // The parent of the root is the root.
+                if !result.pop() && !result.has_root() {
+                    result.push(component);
+                }
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
@@ -2,15 +2,57 @@
 // Mod
 //////////////////////////////////////////////////////////////////
 
+mod artifacts;
 mod cache;
+mod file;
+mod interrupt;
+mod layout;
+mod logs;
+mod project;
 pub mod py;
 mod result;
+mod settings;
+mod timings;
+mod warning;
 
 //////////////////////////////////////////////////////////////////
 // Use
 //////////////////////////////////////////////////////////////////
 
+pub use artifacts::Driver as ArtifactDriver;
+pub use artifacts::Key as ArtifactKey;
+pub use artifacts::Manifest as ArtifactManifest;
+pub use artifacts::Output as ArtifactOutput;
+pub use artifacts::Space as ArtifactSpace;
+pub use artifacts::Store as Artifacts;
+pub use artifacts::digest;
 pub use cache::Driver as CacheDriver;
 pub use cache::Entry as CacheEntry;
+pub use cache::Header as CacheHeader;
+pub use cache::Memory as CacheMemory;
+pub use cache::Migration as CacheMigration;
+pub use cache::Shared as Cache;
+pub use cache::Stamp as CacheStamp;
+pub use cache::share as share_cache;
+pub use file::write_atomic;
+pub use interrupt::Deadline;
+pub use interrupt::Interrupter;
+pub use layout::Layout;
+pub use logs::Logs;
+pub use logs::Recorder as LogRecorder;
+pub use logs::Run as LogRun;
+pub use logs::Status as LogStatus;
+pub use logs::Target as LogTarget;
+pub use project::Contributor as ProjectContributor;
+pub use project::Info as ProjectInfo;
 pub use result::Error;
+pub use result::Kind as ErrorKind;
+pub use result::Location as ErrorLocation;
 pub use result::Result;
+pub use result::Source as ErrorSource;
+pub use settings::Layer as SettingsLayer;
+pub use settings::Settings;
+pub use timings::Span as TimingSpan;
+pub use timings::Timings;
+pub use warning::set_handler as set_warning_handler;
+pub use warning::warning;
@@ -0,0 +1,318 @@
+use crate::{Result, py};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// History of the runs in the logs directory.
+const INDEX: &str = "index.json";
+/// Serializes the index updates of parallel runs.
+const LOCK: &str = "index.lock";
+/// Held in the run directory while the run is recorded.
+const RUN_LOCK: &str = "run.lock";
+
+//////////////////////////////////////////////////////////////////
+// History
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Succeeded,
+    Failed,
+    Interrupted,
+    /// Outputs of the target are restored from the cache, it is not run.
+    Restored,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Succeeded => "succeeded",
+            Status::Failed => "failed",
+            Status::Interrupted => "interrupted",
+            Status::Restored => "restored",
+        }
+    }
+}
+
+impl<T> From<&Result<T>> for Status {
+    fn from(value: &Result<T>) -> Self {
+        match value {
+            Ok(_) => Status::Succeeded,
+            Err(e) if e.kind() == crate::ErrorKind::Interrupted => Status::Interrupted,
+            Err(_) => Status::Failed,
+        }
+    }
+}
+
+/// Target of the run in the execution order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Target {
+    pub name: String,
+    pub status: Status,
+    /// Seconds.
+    pub duration: f64,
+    /// Log file name in the run directory, restored targets have none.
+    pub log: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub id: u64,
+    /// Command line arguments without the program name.
+    pub command: Vec<String>,
+    /// Seconds since the Unix epoch.
+    pub started: u64,
+    /// Seconds.
+    pub duration: f64,
+    pub status: Status,
+    pub targets: Vec<Target>,
+}
+
+//////////////////////////////////////////////////////////////////
+// Logs
+//////////////////////////////////////////////////////////////////
+
+/// Output of the targets captured into '<root>/<run id>/<target>.log'
+/// and the index of recent runs. Older runs are removed.
+#[derive(Debug, Clone)]
+pub struct Logs {
+    root: PathBuf,
+    keep: usize,
+}
+
+impl py::Service for Logs {
+    const NAME: &'static str = "unimake/logs";
+}
+
+impl Logs {
+    pub fn new(root: impl Into<PathBuf>, keep: usize) -> Self {
+        Self {
+            root: root.into(),
+            keep,
+        }
+    }
+
+    /// Recent runs, the oldest first.
+    pub fn history(&self) -> Result<Vec<Run>> {
+        match std::fs::read(self.root.join(INDEX)) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Path of the target log, 'None' if the target has no log.
+    pub fn file(&self, run: &Run, target: &Target) -> Option<PathBuf> {
+        let log = target.log.as_ref()?;
+        Some(self.root.join(run.id.to_string()).join(log))
+    }
+
+    /// Starts recording of the new run. Its directory is created right
+    /// away, so parallel runs get different ids.
+    pub fn start(&self, command: Vec<String>) -> Result<Recorder> {
+        std::fs::create_dir_all(&self.root)?;
+        let mut id = self.history()?.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        loop {
+            match std::fs::create_dir(self.directory(id)) {
+                Ok(()) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => id += 1,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let lock = File::create(self.directory(id).join(RUN_LOCK))?;
+        lock.lock()?;
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Ok(Recorder {
+            logs: self.clone(),
+            _lock: lock,
+            clock: Instant::now(),
+            opened: Vec::new(),
+            run: Run {
+                id,
+                command,
+                started,
+                duration: 0.0,
+                status: Status::Succeeded,
+                targets: Vec::new(),
+            },
+        })
+    }
+
+    /// Exclusive lock of the index, it is released when the file is closed.
+    fn lock(&self) -> Result<File> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.root.join(LOCK))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    fn directory(&self, id: u64) -> PathBuf {
+        self.root.join(id.to_string())
+    }
+
+    /// Removes the run directories which are not in the history, except
+    /// the ones whose recorders are alive.
+    fn prune(&self, history: &[Run]) {
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if history.iter().any(|r| r.id == id) || !entry.path().is_dir() {
+                continue;
+            }
+            // The lock is created right after the directory, so the run
+            // without it may be just starting.
+            let Ok(lock) = File::options()
+                .write(true)
+                .open(entry.path().join(RUN_LOCK))
+            else {
+                continue;
+            };
+            if lock.try_lock().is_ok() {
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+}
+
+/// Records the targets of the run, the run is added to the index when
+/// it is finished.
+#[derive(Debug)]
+pub struct Recorder {
+    logs: Logs,
+    /// Tells other runs that the directory is in use.
+    _lock: File,
+    clock: Instant,
+    /// Targets whose logs are created, but which are not finished yet.
+    opened: Vec<(String, String)>,
+    run: Run,
+}
+
+impl Recorder {
+    pub fn id(&self) -> u64 {
+        self.run.id
+    }
+
+    /// Creates the log file of the target. The target may run several
+    /// times (e.g. as the 'finally' command), each run gets its own log.
+    pub fn open(&mut self, target: &str) -> Result<File> {
+        let runs = self.run.targets.iter().filter(|t| t.name == target).count()
+            + self.opened.iter().filter(|(t, _)| t == target).count();
+        let name = match runs {
+            0 => format!("{}.log", escape(target)),
+            n => format!("{}.{}.log", escape(target), n + 1),
+        };
+        let file = File::create(self.logs.directory(self.run.id).join(&name))?;
+        self.opened.push((target.to_string(), name));
+        Ok(file)
+    }
+
+    pub fn finish(&mut self, target: &str, status: Status, duration: f64) {
+        let index = self.opened.iter().rposition(|(t, _)| t == target);
+        let log = index.map(|i| self.opened.remove(i).1);
+        self.run.targets.push(Target {
+            name: target.to_string(),
+            status,
+            duration,
+            log,
+        });
+    }
+
+    /// Adds the run to the index and removes the oldest runs. The index is
+    /// locked meanwhile, so parallel runs don't lose each other's entries.
+    /// Directories of the runs which never got to the index (e.g. killed
+    /// ones) are removed too, unless they are still recorded.
+    pub fn close(mut self, status: Status) -> Result {
+        self.run.status = status;
+        self.run.duration = self.clock.elapsed().as_secs_f64();
+        let _lock = self.logs.lock()?;
+        let mut history = self.logs.history()?;
+        history.push(self.run);
+        history.sort_by_key(|r| r.id);
+        let excess = history.len().saturating_sub(self.logs.keep.max(1));
+        for run in history.drain(..excess) {
+            let _ = std::fs::remove_dir_all(self.logs.directory(run.id));
+        }
+        self.logs.prune(&history);
+        let data = serde_json::to_vec_pretty(&history)?;
+        crate::write_atomic(&self.logs.root.join(INDEX), &data)
+    }
+}
+
+/// Target names may be paths, characters other than letters, digits,
+/// '.', '_' and '-' are replaced with '%XX'.
+fn escape(name: &str) -> String {
+    let mut result = String::new();
+    for byte in name.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(logs: &Logs, target: &str) -> u64 {
+        let mut recorder = logs.start(vec![target.to_string()]).unwrap();
+        recorder.open(target).unwrap();
+        recorder.finish(target, Status::Succeeded, 0.0);
+        let id = recorder.id();
+        recorder.close(Status::Succeeded).unwrap();
+        id
+    }
+
+    #[test]
+    fn keeps_recent_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = Logs::new(dir.path(), 2);
+        let ids: Vec<u64> = ["a", "b", "b"].iter().map(|t| record(&logs, t)).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let history = logs.history().unwrap();
+        assert_eq!(history.iter().map(|r| r.id).collect::<Vec<_>>(), [2, 3]);
+        assert!(!dir.path().join("1").exists());
+        let file = logs.file(&history[1], &history[1].targets[0]).unwrap();
+        assert_eq!(file, dir.path().join("3").join("b.log"));
+        assert!(file.is_file());
+    }
+
+    #[test]
+    fn prunes_unindexed_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = Logs::new(dir.path(), 2);
+        record(&logs, "a");
+        // The run is killed before it gets to the index.
+        let killed = logs.start(Vec::new()).unwrap().id();
+        // The directory of some other tool.
+        std::fs::create_dir(dir.path().join("7")).unwrap();
+        record(&logs, "a");
+        let running = logs.start(Vec::new()).unwrap();
+        record(&logs, "a");
+        record(&logs, "a");
+        assert!(!dir.path().join(killed.to_string()).exists());
+        assert!(dir.path().join(running.id().to_string()).is_dir());
+        assert!(dir.path().join("7").is_dir());
+        let ids: Vec<u64> = logs.history().unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(ids, [5, 6]);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::py;
+use std::collections::BTreeMap;
+
+//////////////////////////////////////////////////////////////////
+// Project
+//////////////////////////////////////////////////////////////////
+
+/// Project of the workspace, declared by the script with
+/// 'umk.project.init'. It is provided once the script is loaded, the
+/// name defaults to the workspace directory name.
+#[derive(Debug, Clone, Default)]
+pub struct Info {
+    pub name: String,
+    pub version: String,
+    pub title: String,
+    pub description: String,
+    pub contributors: Vec<Contributor>,
+}
+
+impl py::Service for Info {
+    const NAME: &'static str = "unimake/project";
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Contributor {
+    pub name: String,
+    pub emails: Vec<String>,
+    pub socials: BTreeMap<String, String>,
+}
@@ -0,0 +1,111 @@
+use rustpython_vm::builtins::{PyBaseExceptionRef, PyModule, PyType};
+use rustpython_vm::{AsObject, PyObjectRef, PyRef, VirtualMachine};
+use std::path::Path;
+
+//////////////////////////////////////////////////////////////////
+// Registration and config errors
+//////////////////////////////////////////////////////////////////
+
+pub const REGISTRATION_ERROR: &str = "RegistrationError";
+pub const CONFIG_ERROR: &str = "ConfigError";
+
+/// Declares 'umk.RegistrationError' and 'umk.ConfigError' exception
+/// classes in the given module.
+pub fn register(vm: &VirtualMachine, module: &PyRef<PyModule>) {
+    for name in [REGISTRATION_ERROR, CONFIG_ERROR] {
+        let class = vm.ctx.new_exception_type(global::kit::NAME, name, None);
+        module
+            .set_attr(name, class, vm)
+            .unwrap_or_else(|_| panic!("Failed to register '{}' exception class", name));
+    }
+}
+
+/// Creates an exception which tells that decorator got invalid inputs.
+pub fn registration_error(vm: &VirtualMachine, message: impl Into<String>) -> PyBaseExceptionRef {
+    error(vm, REGISTRATION_ERROR, message.into())
+}
+
+/// Creates an exception which tells that the value from the config files,
+/// the environment or the command line is invalid.
+pub fn config_error(vm: &VirtualMachine, message: impl Into<String>) -> PyBaseExceptionRef {
+    error(vm, CONFIG_ERROR, message.into())
+}
+
+fn error(vm: &VirtualMachine, name: &'static str, message: String) -> PyBaseExceptionRef {
+    let class = vm
+        .import(global::kit::NAME, 0)
+        .and_then(|module| module.get_attr(name, vm))
+        .and_then(|class| {
+            class
+                .downcast::<PyType>()
+                .map_err(|_| vm.new_type_error(format!("Failed to find '{}' class", name)))
+        });
+    match class {
+        Ok(class) => vm.new_exception_msg(class, message),
+        Err(_) => vm.new_value_error(message),
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// OS errors
+//////////////////////////////////////////////////////////////////
+
+/// OS error of the proper class (e.g. 'FileNotFoundError') with the path.
+pub fn os_error(vm: &VirtualMachine, error: std::io::Error, path: &Path) -> PyBaseExceptionRef {
+    let text = error.to_string();
+    let text = text.split(" (os error").next().unwrap_or(&text);
+    let result = vm.new_errno_error(error.raw_os_error().unwrap_or(0), text.to_string());
+    let filename = vm.new_pyobj(path.to_string_lossy().into_owned());
+    let _ = result.as_object().set_attr("filename", filename, vm);
+    result
+}
+
+//////////////////////////////////////////////////////////////////
+// Notes
+//////////////////////////////////////////////////////////////////
+
+/// Appends a note to the exception '__notes__' list (PEP 678).
+pub fn add_note(vm: &VirtualMachine, exception: &PyBaseExceptionRef, note: impl Into<String>) {
+    let note = vm.ctx.new_str(note.into());
+    let object = exception.as_object();
+    match object.get_attr("__notes__", vm) {
+        Ok(notes) => {
+            let _ = vm.call_method(&notes, "append", (note,));
+        }
+        Err(_) => {
+            let notes = vm.ctx.new_list(vec![note.into()]);
+            let _ = object.set_attr("__notes__", notes, vm);
+        }
+    }
+}
+
+/// Reads the exception '__notes__' list.
+pub fn notes(exception: &PyBaseExceptionRef, vm: &VirtualMachine) -> Vec<String> {
+    let Ok(notes) = exception.as_object().get_attr("__notes__", vm) else {
+        return Vec::new();
+    };
+    let Ok(notes) = vm.extract_elements_with(&notes, |v| v.str(vm).map(|s| s.to_string())) else {
+        return Vec::new();
+    };
+    notes
+}
+
+/// Notes the exception with the decorator which was applied to the function.
+pub fn decorator_note(
+    vm: &VirtualMachine,
+    exception: PyBaseExceptionRef,
+    decorator: &str,
+    func: &PyObjectRef,
+) -> PyBaseExceptionRef {
+    let name = func
+        .get_attr("__qualname__", vm)
+        .and_then(|v| v.str(vm))
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| String::from("<unknown>"));
+    add_note(
+        vm,
+        &exception,
+        format!("while applying '{}' to '{}'", decorator, name),
+    );
+    exception
+}
@@ -0,0 +1,18 @@
+use crate::py;
+use rustpython_vm::PyObjectRef;
+
+//////////////////////////////////////////////////////////////////
+// Invocation
+//////////////////////////////////////////////////////////////////
+
+/// CLI command which is running now with the values of its options,
+/// so helpers called by the command can read them.
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub command: String,
+    pub options: Vec<(String, PyObjectRef)>,
+}
+
+impl py::Service for Invocation {
+    const NAME: &'static str = "unimake/invocation";
+}
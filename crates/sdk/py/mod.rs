@@ -2,10 +2,33 @@
 // Mod
 //////////////////////////////////////////////////////////////////
 
+mod errors;
+mod invocation;
 pub mod register;
+mod service;
 mod signature;
 mod utils;
 
+//////////////////////////////////////////////////////////////////
+// Errors
+//////////////////////////////////////////////////////////////////
+
+pub use errors::CONFIG_ERROR;
+pub use errors::REGISTRATION_ERROR;
+pub use errors::add_note;
+pub use errors::config_error;
+pub use errors::decorator_note;
+pub use errors::notes;
+pub use errors::os_error;
+pub use errors::register as register_errors;
+pub use errors::registration_error;
+
+//////////////////////////////////////////////////////////////////
+// Invocation
+//////////////////////////////////////////////////////////////////
+
+pub use invocation::Invocation;
+
 //////////////////////////////////////////////////////////////////
 // Register
 //////////////////////////////////////////////////////////////////
@@ -13,6 +36,15 @@ mod utils;
 pub use register::Registerable;
 pub use register::Registerer;
 
+//////////////////////////////////////////////////////////////////
+// Service
+//////////////////////////////////////////////////////////////////
+
+pub use service::Service;
+pub use service::provide;
+pub use service::require;
+pub use service::service;
+
 //////////////////////////////////////////////////////////////////
 // Signature
 //////////////////////////////////////////////////////////////////
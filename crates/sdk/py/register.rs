@@ -27,10 +27,9 @@ pub fn submodule(vm: &VirtualMachine, module: &PyRef<PyModule>, sub: PyRef<PyMod
     module
         .dict()
         .set_item(submodule_name, sub.to_pyobject(vm), vm)
-        .expect(
-            format!("Failed to register submodule '{submodule_name}' in module '{module_name}'")
-                .as_str(),
-        );
+        .unwrap_or_else(|_| {
+            panic!("Failed to register submodule '{submodule_name}' in module '{module_name}'")
+        });
 }
 
 pub fn class<T: PyClassImpl + StaticType>(vm: &VirtualMachine, module: &PyRef<PyModule>) {
@@ -40,9 +39,9 @@ pub fn class<T: PyClassImpl + StaticType>(vm: &VirtualMachine, module: &PyRef<Py
     module
         .dict()
         .set_item(class_name.as_str(), target.to_pyobject(vm), vm)
-        .expect(
-            format!("Failed to register class '{class_name}' in module '{module_name}'").as_str(),
-        );
+        .unwrap_or_else(|_| {
+            panic!("Failed to register class '{class_name}' in module '{module_name}'")
+        });
 }
 
 pub fn function<T: Function>(vm: &VirtualMachine, module: &PyRef<PyModule>) {
@@ -51,9 +50,9 @@ pub fn function<T: Function>(vm: &VirtualMachine, module: &PyRef<PyModule>) {
     module
         .dict()
         .set_item(T::NAME, T::METHOD.to_function().to_pyobject(vm), vm)
-        .expect(
-            format!("Failed to register decorator '{name}' in module '{module_name}'").as_str(),
-        );
+        .unwrap_or_else(|_| {
+            panic!("Failed to register decorator '{name}' in module '{module_name}'")
+        });
 }
 
 pub fn func(
@@ -66,5 +65,7 @@ pub fn func(
     module
         .dict()
         .set_item(name, method.to_function().to_pyobject(vm), vm)
-        .expect(format!("Failed to register function '{name}' in module '{module_name}'").as_str());
+        .unwrap_or_else(|_| {
+            panic!("Failed to register function '{name}' in module '{module_name}'")
+        });
 }
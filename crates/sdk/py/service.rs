@@ -0,0 +1,38 @@
+use crate::py::state;
+use rustpython_vm::{PyResult, VirtualMachine};
+use std::sync::Arc;
+
+//////////////////////////////////////////////////////////////////
+// Service
+//////////////////////////////////////////////////////////////////
+
+/// Value which a plugin publishes for other plugins. Services are stored
+/// in the global container, the key is derived from the 'NAME', so
+/// consumers refer to the type instead of the string key.
+pub trait Service: Send + Sync + 'static {
+    /// Unique service name, usually '<plugin>/<service>'.
+    const NAME: &'static str;
+}
+
+fn key<T: Service>() -> String {
+    format!("{}/service/{}", global::kit::NAME, T::NAME)
+}
+
+/// Publishes the service, the previous value (if any) is replaced.
+pub fn provide<T: Service>(vm: &VirtualMachine, value: T) -> PyResult<()> {
+    state::set(vm, key::<T>(), value)
+}
+
+/// Returns the service if some plugin has published it.
+pub fn service<T: Service>(vm: &VirtualMachine) -> PyResult<Option<Arc<T>>> {
+    state::get::<T>(vm, key::<T>())
+}
+
+/// Returns the service or raises the runtime error if it is not published.
+/// The provider plugin should be listed in the consumer dependencies.
+pub fn require<T: Service>(vm: &VirtualMachine) -> PyResult<Arc<T>> {
+    match service::<T>(vm)? {
+        Some(v) => Ok(v),
+        None => Err(vm.new_runtime_error(format!("Service is not provided: '{}'", T::NAME))),
+    }
+}
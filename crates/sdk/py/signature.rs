@@ -109,17 +109,14 @@ impl Signature {
     }
 
     pub fn variadic(&self) -> Option<Argument> {
-        self.args
-            .iter()
-            .rfind(|&a| a.is_variadic())
-            .map(|a| a.clone())
+        self.args.iter().rfind(|&a| a.is_variadic()).cloned()
     }
 
     pub fn variadic_keyword(&self) -> Option<Argument> {
         self.args
             .iter()
             .rfind(|&a| a.is_variadic_keyword())
-            .map(|a| a.clone())
+            .cloned()
     }
 }
 
@@ -139,9 +136,12 @@ impl Signature {
             match callable.downcast::<PyFunction>() {
                 Ok(v) => v,
                 Err(_) => {
-                    return Err(Error::Python(vm.new_runtime_error(
-                        "Failed to downcast callable object to the 'PyFunction'".to_string(),
-                    )));
+                    return Err(Error::python(
+                        vm.new_runtime_error(
+                            "Failed to downcast callable object to the 'PyFunction'".to_string(),
+                        ),
+                        vm,
+                    ));
                 }
             }
         } else {
@@ -150,9 +150,12 @@ impl Signature {
                 Ok(v) => v,
                 Err(_) => {
                     // Case 3: not callable
-                    return Err(Error::Python(vm.new_runtime_error(
-                        "Failed to downcast '__call__' to the 'PyFunction'".to_string(),
-                    )));
+                    return Err(Error::python(
+                        vm.new_runtime_error(
+                            "Failed to downcast '__call__' to the 'PyFunction'".to_string(),
+                        ),
+                        vm,
+                    ));
                 }
             }
         };
@@ -166,9 +169,12 @@ impl Signature {
         {
             Ok(v) => v,
             Err(_) => {
-                return Err(Error::Python(vm.new_runtime_error(
-                    "Failed to get '__code__' attribute from 'PyFunction'".to_string(),
-                )));
+                return Err(Error::python(
+                    vm.new_runtime_error(
+                        "Failed to get '__code__' attribute from 'PyFunction'".to_string(),
+                    ),
+                    vm,
+                ));
             }
         };
 
@@ -184,7 +190,7 @@ impl Signature {
         let set = |result: &mut Self, name: String, kind: Kind| match result
             .args
             .iter_mut()
-            .find(|arg| arg.name == name.to_string())
+            .find(|arg| arg.name == name)
         {
             None => {}
             Some(arg) => arg.kind = kind.clone(),
@@ -220,19 +226,23 @@ impl Signature {
 
         if let Ok(annotations) = annotations {
             let annotations = annotations.downcast::<PyDict>();
-            if let Err(_) = annotations {
-                return Err(Error::Python(vm.new_runtime_error(
-                    "Failed to downcast '__annotations__' to the dict".to_string(),
-                )));
+            if annotations.is_err() {
+                return Err(Error::python(
+                    vm.new_runtime_error(
+                        "Failed to downcast '__annotations__' to the dict".to_string(),
+                    ),
+                    vm,
+                ));
             }
             let annotations = annotations.unwrap();
             for (k, v) in annotations {
-                if let Ok(name) = k.str(vm) {
-                    result
+                if let Ok(name) = k.str(vm)
+                    && let Some(arg) = result
                         .args
                         .iter_mut()
-                        .find(|arg| arg.name() == name.to_string())
-                        .map(|arg| arg.annotation = Some(v.str(vm).unwrap().to_string()));
+                        .find(|arg| arg.name() == name.as_str())
+                {
+                    arg.annotation = Some(v.str(vm).unwrap().to_string());
                 }
             }
         }
@@ -65,7 +65,7 @@ where
     T: PyPayload,
 {
     for object in iter {
-        is::<T>(vm, &object, message)?;
+        is::<T>(vm, object, message)?;
     }
     Ok(())
 }
@@ -85,8 +85,22 @@ pub fn ok<T>(object: &PyObjectRef) -> Option<String>
 where
     T: PyPayload,
 {
-    if !object.downcast_ref::<T>().is_some() {
+    if object.downcast_ref::<T>().is_none() {
         return Some(object.class().name().to_string());
     }
     None
 }
+
+//////////////////////////////////////////////////////////////////
+// Streams
+//////////////////////////////////////////////////////////////////
+
+/// Python streams are flushed only at exit, call it to keep the python
+/// output in order with the output of the application.
+pub fn flush(vm: &VirtualMachine) {
+    for name in ["stdout", "stderr"] {
+        if let Ok(stream) = vm.sys_module.get_attr(name, vm) {
+            let _ = vm.call_method(&stream, "flush", ());
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use derive_more::From;
 use rustpython_vm::builtins::PyBaseExceptionRef;
+use rustpython_vm::{AsObject, VirtualMachine};
 use std::fmt;
+use std::path::PathBuf;
 
 //////////////////////////////////////////////////////////////////
 // Result
@@ -9,45 +10,330 @@ use std::fmt;
 pub type Result<T = ()> = std::result::Result<T, Error>;
 
 //////////////////////////////////////////////////////////////////
-// Error
+// Kind
 //////////////////////////////////////////////////////////////////
 
-#[derive(From)]
-pub enum Error {
-    #[from]
-    Text(String),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Generic,
+    WorkspaceNotFound,
+    Script,
+    Registration,
+    CommandFailed,
+    CacheCorrupt,
+    Plugin,
+    Config,
+    Interrupted,
+}
 
-    #[from]
-    Io(std::io::Error),
+impl Kind {
+    /// Process exit code. The `2` is skipped since clap uses it for usage errors.
+    pub fn code(&self) -> i32 {
+        match self {
+            Kind::Generic => 1,
+            Kind::WorkspaceNotFound => 3,
+            Kind::Script => 4,
+            Kind::Registration => 5,
+            Kind::CommandFailed => 6,
+            Kind::CacheCorrupt => 7,
+            Kind::Plugin => 8,
+            Kind::Config => 9,
+            // The shell convention for the termination by SIGINT.
+            Kind::Interrupted => 130,
+        }
+    }
 
-    #[from]
-    Python(PyBaseExceptionRef),
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Generic => "error",
+            Kind::WorkspaceNotFound => "workspace-not-found",
+            Kind::Script => "script-error",
+            Kind::Registration => "registration-error",
+            Kind::CommandFailed => "command-failed",
+            Kind::CacheCorrupt => "cache-corrupt",
+            Kind::Plugin => "plugin-error",
+            Kind::Config => "config-error",
+            Kind::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Location
+//////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub function: String,
+}
+
+impl Location {
+    /// Location of every traceback frame, from outer to inner.
+    pub fn trace(exception: &PyBaseExceptionRef) -> Vec<Self> {
+        let Some(traceback) = exception.traceback() else {
+            return Vec::new();
+        };
+        // Instruction columns are not precise enough (they point to the
+        // last evaluated subexpression), so only lines are taken.
+        traceback
+            .iter()
+            .map(|tb| Self {
+                file: PathBuf::from(tb.frame.code.source_path.as_str()),
+                line: tb.lineno.to_usize(),
+                column: None,
+                function: tb.frame.code.obj_name.to_string(),
+            })
+            .collect()
+    }
+
+    /// Finds the innermost traceback frame which belongs to a real file.
+    pub fn of(exception: &PyBaseExceptionRef) -> Option<Self> {
+        Self::trace(exception)
+            .into_iter()
+            .rfind(|l| !l.is_internal())
+    }
 
-    #[from]
+    /// Frozen modules (like importlib), code compiled from strings and the
+    /// 'umk' package code, i.e. python plugins loaded as 'umk.<name>'.
+    pub fn is_internal(&self) -> bool {
+        self.file.to_string_lossy().starts_with('<')
+            || self
+                .file
+                .ancestors()
+                .any(|p| p.ends_with(global::workspace::PLUGINS))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, " in {}", self.function)
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Error
+//////////////////////////////////////////////////////////////////
+
+pub enum Source {
+    Error(Box<Error>),
+    Io(std::io::Error),
     Json(serde_json::Error),
+    Python(PyBaseExceptionRef),
+}
+
+pub struct Error {
+    kind: Kind,
+    message: String,
+    location: Option<Box<Location>>,
+    notes: Vec<String>,
+    source: Option<Source>,
+}
+
+impl Error {
+    pub fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            location: None,
+            notes: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Converts python exception using the interpreter to render its
+    /// message and to detect registration and config errors.
+    pub fn python(exception: PyBaseExceptionRef, vm: &VirtualMachine) -> Self {
+        let class = exception.class();
+        let module = class.module(vm).str(vm).map(|s| s.to_string());
+        let kind = match (module.as_deref(), class.name().as_ref()) {
+            (Ok(global::kit::NAME), crate::py::REGISTRATION_ERROR) => Kind::Registration,
+            (Ok(global::kit::NAME), crate::py::CONFIG_ERROR) => Kind::Config,
+            _ if exception.fast_isinstance(vm.ctx.exceptions.keyboard_interrupt) => {
+                Kind::Interrupted
+            }
+            _ => Kind::Script,
+        };
+        let message = match exception.as_object().str(vm) {
+            Ok(text) if !text.as_str().is_empty() => format!("{}: {}", class.name(), text),
+            _ => class.name().to_string(),
+        };
+        let location = match Location::of(&exception) {
+            Some(v) => Some(Box::new(v)),
+            None => syntax_location(&exception, vm).map(Box::new),
+        };
+        Self {
+            kind,
+            message,
+            location,
+            notes: crate::py::notes(&exception, vm),
+            source: Some(Source::Python(exception)),
+        }
+    }
+
+    /// Wraps the error into a new one, the location and notes are inherited.
+    pub fn wrap(mut self, kind: Kind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            location: self.location.clone(),
+            notes: std::mem::take(&mut self.notes),
+            source: Some(Source::Error(Box::new(self))),
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_deref()
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    pub fn source(&self) -> Option<&Source> {
+        self.source.as_ref()
+    }
+
+    /// Messages of all nested causes, from outer to inner.
+    pub fn causes(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut current = self.source.as_ref();
+        while let Some(source) = current {
+            current = None;
+            match source {
+                Source::Error(err) => {
+                    result.push(err.message.clone());
+                    current = err.source.as_ref();
+                }
+                Source::Io(err) => result.push(err.to_string()),
+                Source::Json(err) => result.push(err.to_string()),
+                Source::Python(_) => {}
+            }
+        }
+        result
+    }
+
+    /// The innermost error in the cause chain (or self).
+    pub fn innermost(&self) -> &Error {
+        match &self.source {
+            Some(Source::Error(err)) => err.innermost(),
+            _ => self,
+        }
+    }
+
+    /// The innermost python exception in the cause chain.
+    pub fn exception(&self) -> Option<&PyBaseExceptionRef> {
+        match self.source.as_ref()? {
+            Source::Error(err) => err.exception(),
+            Source::Python(exception) => Some(exception),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(text: String) -> Self {
+        Error::new(Kind::Generic, text)
+    }
 }
 
 impl From<&str> for Error {
     fn from(text: &str) -> Self {
-        Error::Text(text.to_string())
+        Error::new(Kind::Generic, text)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        let mut result = Error::new(Kind::Generic, format!("IO error: {}", err));
+        result.source = Some(Source::Io(err));
+        result
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        let mut result = Error::new(Kind::Generic, format!("Json error: {}", err));
+        result.source = Some(Source::Json(err));
+        result
+    }
+}
+
+impl From<PyBaseExceptionRef> for Error {
+    fn from(exception: PyBaseExceptionRef) -> Self {
+        let message = match exception.get_arg(0) {
+            Some(arg) => match arg.downcast_ref::<rustpython_vm::builtins::PyStr>() {
+                Some(text) => format!("{}: {}", exception.class().name(), text.as_str()),
+                None => exception.class().name().to_string(),
+            },
+            None => exception.class().name().to_string(),
+        };
+        Self {
+            kind: Kind::Script,
+            message,
+            location: Location::of(&exception).map(Box::new),
+            notes: Vec::new(),
+            source: Some(Source::Python(exception)),
+        }
     }
 }
 
+/// Syntax errors have no traceback frames in the broken file,
+/// its position is stored in the exception attributes.
+fn syntax_location(exception: &PyBaseExceptionRef, vm: &VirtualMachine) -> Option<Location> {
+    if !exception
+        .class()
+        .fast_issubclass(vm.ctx.exceptions.syntax_error)
+    {
+        return None;
+    }
+    let attr = |name: &'static str| exception.as_object().get_attr(name, vm).ok();
+    let file = attr("filename")?.str(vm).ok()?.to_string();
+    let line = attr("lineno")?.try_into_value::<usize>(vm).ok()?;
+    let column = attr("offset").and_then(|v| v.try_into_value::<usize>(vm).ok());
+    Some(Location {
+        file: PathBuf::from(file),
+        line,
+        column,
+        function: String::from("<module>"),
+    })
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Error::Text(text) => formatter.write_str(text),
-            Error::Io(err) => formatter.write_fmt(format_args!("IO error: {}", err)),
-            Error::Python(exception) => {
-                formatter.write_str(format!("Python exception: {:?}", exception).as_str())
-            }
-            Error::Json(err) => formatter.write_str(format!("Json error: {}", err).as_str()),
-        }
+        formatter.write_str(&self.message)
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        <Self as fmt::Display>::fmt(self, formatter)
+        write!(formatter, "{}: {}", self.kind, self.message)
     }
 }
@@ -0,0 +1,47 @@
+use crate::py;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+//////////////////////////////////////////////////////////////////
+// Settings
+//////////////////////////////////////////////////////////////////
+
+/// Values of the script settings from one source, e.g. the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Layer {
+    /// Human readable source name, e.g. '.unimake/config.toml [release]'.
+    pub source: String,
+    pub values: BTreeMap<String, Value>,
+}
+
+/// Overrides of the settings declared by the workspace script, the
+/// application collects them before the script is loaded. Later layers
+/// take precedence over earlier ones.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub profile: Option<String>,
+    pub layers: Vec<Layer>,
+}
+
+impl py::Service for Settings {
+    const NAME: &'static str = "unimake/settings";
+}
+
+impl Settings {
+    /// The overriding value of the setting and the layer it comes from.
+    pub fn find(&self, name: &str) -> Option<(&Layer, &Value)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|l| l.values.get(name).map(|v| (l, v)))
+    }
+
+    /// Effective overrides of all settings.
+    pub fn values(&self) -> BTreeMap<String, Value> {
+        let mut result = BTreeMap::new();
+        for layer in self.layers.iter() {
+            result.extend(layer.values.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        result
+    }
+}
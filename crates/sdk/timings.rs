@@ -0,0 +1,158 @@
+use crate::{Result, py};
+use parking_lot::Mutex;
+use serde_json::json;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+//////////////////////////////////////////////////////////////////
+// Span
+//////////////////////////////////////////////////////////////////
+
+/// Measured phase of the application (e.g. the script load) or target.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub name: String,
+    pub category: String,
+    /// Since the start of the application.
+    pub start: Duration,
+    pub duration: Duration,
+    /// Spans of different threads go to different lanes.
+    pub lane: usize,
+    /// Outcome of the target, 'None' for the phases.
+    pub status: Option<String>,
+}
+
+impl Span {
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+}
+
+//////////////////////////////////////////////////////////////////
+// Timings
+//////////////////////////////////////////////////////////////////
+
+/// Start and end times of the application phases and targets. Clones
+/// share the spans, so the application and plugins record into the same
+/// timeline.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    origin: Instant,
+    spans: Arc<Mutex<Vec<Span>>>,
+    lanes: Arc<Mutex<Vec<ThreadId>>>,
+}
+
+impl py::Service for Timings {
+    const NAME: &'static str = "unimake/timings";
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            spans: Default::default(),
+            lanes: Default::default(),
+        }
+    }
+
+    /// Calls the function and records the time it takes.
+    pub fn measure<R>(&self, name: &str, category: &str, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.record(name, category, start, None);
+        result
+    }
+
+    /// Records the span from the start till now in the lane of the
+    /// current thread.
+    pub fn record(&self, name: &str, category: &str, start: Instant, status: Option<&str>) {
+        let end = Instant::now();
+        let start = start.max(self.origin);
+        let span = Span {
+            name: name.to_string(),
+            category: category.to_string(),
+            start: start - self.origin,
+            duration: end - start,
+            lane: self.lane(),
+            status: status.map(String::from),
+        };
+        self.spans.lock().push(span);
+    }
+
+    /// Recorded spans ordered by their start.
+    pub fn spans(&self) -> Vec<Span> {
+        let mut result = self.spans.lock().clone();
+        result.sort_by_key(|s| (s.start, std::cmp::Reverse(s.duration)));
+        result
+    }
+
+    /// Time since the start of the application.
+    pub fn elapsed(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    /// Writes the spans in the Chrome trace event format, which Perfetto
+    /// and 'chrome://tracing' open. Each lane is shown as a thread.
+    pub fn trace(&self, file: &Path) -> Result {
+        let micros = |d: Duration| d.as_secs_f64() * 1e6;
+        let mut events = vec![json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": 1,
+            "args": { "name": global::project::NAME },
+        })];
+        for lane in 0..self.lanes.lock().len() {
+            let name = match lane {
+                0 => String::from("main"),
+                n => format!("lane {}", n),
+            };
+            events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": lane,
+                "args": { "name": name },
+            }));
+        }
+        for span in self.spans() {
+            let mut event = json!({
+                "name": span.name,
+                "cat": span.category,
+                "ph": "X",
+                "ts": micros(span.start),
+                "dur": micros(span.duration),
+                "pid": 1,
+                "tid": span.lane,
+            });
+            if let Some(status) = &span.status {
+                event["args"] = json!({ "status": status });
+            }
+            events.push(event);
+        }
+        let data = serde_json::to_vec(&json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        }))?;
+        crate::write_atomic(file, &data)
+    }
+
+    fn lane(&self) -> usize {
+        let id = std::thread::current().id();
+        let mut lanes = self.lanes.lock();
+        match lanes.iter().position(|l| *l == id) {
+            Some(v) => v,
+            None => {
+                lanes.push(id);
+                lanes.len() - 1
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::sync::OnceLock;
+
+//////////////////////////////////////////////////////////////////
+// Warnings
+//////////////////////////////////////////////////////////////////
+
+pub type Handler = fn(&str);
+
+static HANDLER: OnceLock<Handler> = OnceLock::new();
+
+/// Sets the function which prints warnings, it may be set only once.
+/// Warnings are printed to the stderr as is until the handler is set.
+pub fn set_handler(handler: Handler) {
+    let _ = HANDLER.set(handler);
+}
+
+/// Reports the recoverable problem, the execution continues.
+pub fn warning(message: impl AsRef<str>) {
+    match HANDLER.get() {
+        Some(handler) => handler(message.as_ref()),
+        None => eprintln!("warning: {}", message.as_ref()),
+    }
+}